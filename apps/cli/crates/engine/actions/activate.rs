@@ -0,0 +1,593 @@
+use crate::actions::env::project_env_in;
+use crate::actions::shim::ShimIndex;
+use crate::specs::config::{ConfigSources, ENV_PREFIX, LayeredConfig};
+use crate::specs::toml::{LOCAL_MANIFEST_NAME, MANIFEST_NAME, find_project_root};
+use crate::system::System;
+use crate::utils::paths::PathOps;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::{fmt, str::FromStr};
+
+/// Shell variable holding the [`Session`] of an activated shell.
+pub const SESSION_VAR: &str = "__STILL_SESSION";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Nu,
+}
+
+impl Shell {
+    pub fn all() -> &'static [Shell] {
+        &[Shell::Bash, Shell::Zsh, Shell::Fish, Shell::Nu]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Shell::Bash => "bash",
+            Shell::Zsh => "zsh",
+            Shell::Fish => "fish",
+            Shell::Nu => "nu",
+        }
+    }
+}
+
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Shell {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "bash" => Ok(Shell::Bash),
+            "zsh" => Ok(Shell::Zsh),
+            "fish" => Ok(Shell::Fish),
+            "nu" | "nushell" => Ok(Shell::Nu),
+            other => {
+                let known: Vec<&str> = Shell::all().iter().map(|s| s.as_str()).collect();
+                anyhow::bail!(
+                    "unsupported shell \"{}\" (expected one of: {})",
+                    other,
+                    known.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// What an activated shell has applied, so the next prompt can revert it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Session {
+    /// Project root and modification times the environment was computed from.
+    pub watch: String,
+    /// Files besides the manifests that went into `watch`.
+    #[serde(default)]
+    pub files: Vec<PathBuf>,
+    /// Entries put in front of `PATH`; removed again rather than restoring `PATH`,
+    /// so entries the user added since survive.
+    #[serde(default)]
+    pub path: Vec<String>,
+    /// Entries put at the end of `PATH`, removed the same way.
+    #[serde(default)]
+    pub path_append: Vec<String>,
+    /// Values the other changed variables had before (`None`: unset).
+    #[serde(default)]
+    pub original: BTreeMap<String, Option<String>>,
+}
+
+/// Variables a shell has to set and unset.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnvDiff {
+    pub set: BTreeMap<String, String>,
+    pub unset: BTreeSet<String>,
+}
+
+impl EnvDiff {
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty() && self.unset.is_empty()
+    }
+
+    /// Commands that apply the diff in `shell`; empty when there is nothing to do.
+    pub fn render(&self, shell: Shell) -> String {
+        if self.is_empty() {
+            return String::new();
+        }
+
+        let mut out = String::new();
+        match shell {
+            Shell::Bash | Shell::Zsh => {
+                for name in &self.unset {
+                    out.push_str(&format!("unset {};\n", name));
+                }
+                for (name, value) in &self.set {
+                    out.push_str(&format!("export {}={};\n", name, sh_quote(value)));
+                }
+            }
+            Shell::Fish => {
+                for name in &self.unset {
+                    out.push_str(&format!("set -e {};\n", name));
+                }
+                for (name, value) in &self.set {
+                    // fish keeps PATH as a list.
+                    let values: Vec<String> = if name == "PATH" {
+                        value.split(':').map(fish_quote).collect()
+                    } else {
+                        vec![fish_quote(value)]
+                    };
+                    out.push_str(&format!("set -gx {} {};\n", name, values.join(" ")));
+                }
+            }
+            Shell::Nu => {
+                let mut set = self.set.clone();
+                let path = set
+                    .remove("PATH")
+                    .map(|path| path.split(':').map(str::to_string).collect::<Vec<_>>());
+                let diff = serde_json::json!({
+                    "set": set,
+                    "path": path,
+                    "unset": self.unset,
+                });
+                out.push_str(&diff.to_string());
+                out.push('\n');
+            }
+        }
+        out
+    }
+}
+
+/// Everything `still hook-env` looks at.
+#[derive(Debug, Clone)]
+pub struct Activation {
+    /// The shell's current environment and directory.
+    pub sources: ConfigSources,
+    pub tool_dir: PathBuf,
+    pub shim_index: PathBuf,
+}
+
+impl Activation {
+    pub fn from_system() -> Result<Self> {
+        Ok(Self {
+            sources: ConfigSources {
+                global_file: Some(System::config_file()),
+                cwd: std::env::current_dir().context("Failed to read current directory")?,
+                env: std::env::vars().collect(),
+            },
+            tool_dir: System::tool_dir(),
+            shim_index: ShimIndex::path(),
+        })
+    }
+
+    /// The changes that bring the shell to the environment of its current directory.
+    /// Nothing when neither the directory's project nor any file it depends on
+    /// changed since the last prompt.
+    pub fn hook_env(&self) -> Result<EnvDiff> {
+        let current: BTreeMap<String, String> = self.sources.env.iter().cloned().collect();
+        let session = current
+            .get(SESSION_VAR)
+            .and_then(|raw| serde_json::from_str::<Session>(raw).ok());
+        if session
+            .as_ref()
+            .is_some_and(|s| s.watch == self.watch_key(&s.files))
+        {
+            return Ok(EnvDiff::default());
+        }
+
+        let base = revert(&current, session.as_ref());
+        let config = LayeredConfig::load_from(&ConfigSources {
+            env: base.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            ..self.sources.clone()
+        })?;
+        let project = project_env_in(&config, &self.tool_dir, &base)?;
+        let mut target = project.apply(&base);
+
+        let mut next = Session {
+            watch: self.watch_key(&project.files),
+            files: project.files.clone(),
+            path: project
+                .path
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect(),
+            path_append: project
+                .path_append
+                .iter()
+                .map(|p| p.to_string_lossy().to_string())
+                .collect(),
+            original: BTreeMap::new(),
+        };
+        for name in project.vars.keys() {
+            if base.get(name) != target.get(name) {
+                next.original.insert(name.clone(), base.get(name).cloned());
+            }
+        }
+        target.insert(SESSION_VAR.to_string(), serde_json::to_string(&next)?);
+        Ok(diff(&current, &target))
+    }
+
+    /// The changes that undo everything the hook applied.
+    pub fn deactivate(&self) -> EnvDiff {
+        let current: BTreeMap<String, String> = self.sources.env.iter().cloned().collect();
+        let session = current
+            .get(SESSION_VAR)
+            .and_then(|raw| serde_json::from_str::<Session>(raw).ok());
+        diff(&current, &revert(&current, session.as_ref()))
+    }
+
+    /// Cheap fingerprint of the inputs: only `stat`s, no parsing. `files` are the
+    /// ones the manifests named when the environment was last computed.
+    fn watch_key(&self, files: &[PathBuf]) -> String {
+        let mtime = |path: &Path| {
+            std::fs::metadata(path)
+                .and_then(|m| m.modified())
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_nanos())
+        };
+
+        let root = find_project_root(&self.sources.cwd);
+        let mut key = match &root {
+            Some(root) => format!(
+                "{}|{}|{}",
+                root.display(),
+                mtime(&root.join(MANIFEST_NAME)),
+                mtime(&root.join(LOCAL_MANIFEST_NAME))
+            ),
+            None => "-".to_string(),
+        };
+        if let Some(global) = &self.sources.global_file {
+            key.push_str(&format!("|{}", mtime(global)));
+        }
+        key.push_str(&format!("|{}", mtime(&self.shim_index)));
+        for file in files {
+            key.push_str(&format!("|{}", mtime(file)));
+        }
+
+        let mut overrides: Vec<&(String, String)> = self
+            .sources
+            .env
+            .iter()
+            .filter(|(name, _)| name.starts_with(ENV_PREFIX))
+            .collect();
+        overrides.sort();
+        for (name, value) in overrides {
+            key.push_str(&format!("|{}={}", name, value));
+        }
+        key
+    }
+}
+
+/// `current` with what `session` applied taken back out.
+fn revert(
+    current: &BTreeMap<String, String>,
+    session: Option<&Session>,
+) -> BTreeMap<String, String> {
+    let mut base = current.clone();
+    base.remove(SESSION_VAR);
+    let Some(session) = session else {
+        return base;
+    };
+
+    for (name, original) in &session.original {
+        match original {
+            Some(value) => base.insert(name.clone(), value.clone()),
+            None => base.remove(name),
+        };
+    }
+    // An `[env]` PATH was restored above along with the other variables.
+    if let Some(path) = current
+        .get("PATH")
+        .filter(|_| !session.original.contains_key("PATH"))
+    {
+        // The added runs come out where they were put, so the same directory
+        // elsewhere in the user's PATH stays.
+        let mut entries: Vec<&str> = path.split(':').collect();
+        remove_run(&mut entries, &session.path, false);
+        remove_run(&mut entries, &session.path_append, true);
+        base.insert("PATH".to_string(), entries.join(":"));
+    }
+    base
+}
+
+/// Remove the first (or, `from_end`, the last) occurrence of `run` as consecutive
+/// entries of `entries`.
+fn remove_run(entries: &mut Vec<&str>, run: &[String], from_end: bool) {
+    if run.is_empty() || run.len() > entries.len() {
+        return;
+    }
+    let matches = |start: &usize| {
+        entries[*start..*start + run.len()]
+            .iter()
+            .zip(run)
+            .all(|(entry, added)| entry == added)
+    };
+    let mut starts = 0..=entries.len() - run.len();
+    let start = if from_end {
+        starts.rev().find(matches)
+    } else {
+        starts.find(matches)
+    };
+    if let Some(start) = start {
+        entries.drain(start..start + run.len());
+    }
+}
+
+fn diff(current: &BTreeMap<String, String>, target: &BTreeMap<String, String>) -> EnvDiff {
+    EnvDiff {
+        set: target
+            .iter()
+            .filter(|(name, value)| current.get(*name) != Some(value))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect(),
+        unset: current
+            .keys()
+            .filter(|name| !target.contains_key(*name))
+            .cloned()
+            .collect(),
+    }
+}
+
+/// The script `eval "$(still activate <shell>)"` runs: a hook on every prompt and
+/// directory change, and a `still_deactivate` function that removes it again.
+pub fn hook_script(shell: Shell, exe: &Path) -> String {
+    let exe = exe.to_string_lossy();
+    match shell {
+        Shell::Bash => format!(
+            r#"_still_hook() {{
+  local previous_exit_status=$?
+  eval "$({exe} hook-env bash)"
+  return $previous_exit_status
+}}
+if [[ ";${{PROMPT_COMMAND:-}};" != *";_still_hook;"* ]]; then
+  PROMPT_COMMAND="_still_hook${{PROMPT_COMMAND:+;$PROMPT_COMMAND}}"
+fi
+still_deactivate() {{
+  PROMPT_COMMAND="${{PROMPT_COMMAND//_still_hook;/}}"
+  PROMPT_COMMAND="${{PROMPT_COMMAND//_still_hook/}}"
+  eval "$({exe} hook-env bash --deactivate)"
+  unset -f _still_hook still_deactivate
+}}
+_still_hook
+"#,
+            exe = sh_quote(&exe)
+        ),
+        Shell::Zsh => format!(
+            r#"_still_hook() {{
+  eval "$({exe} hook-env zsh)"
+}}
+typeset -ag precmd_functions chpwd_functions
+if (( ! ${{precmd_functions[(I)_still_hook]}} )); then
+  precmd_functions=(_still_hook $precmd_functions)
+fi
+if (( ! ${{chpwd_functions[(I)_still_hook]}} )); then
+  chpwd_functions=(_still_hook $chpwd_functions)
+fi
+still_deactivate() {{
+  precmd_functions=(${{precmd_functions:#_still_hook}})
+  chpwd_functions=(${{chpwd_functions:#_still_hook}})
+  eval "$({exe} hook-env zsh --deactivate)"
+  unfunction _still_hook still_deactivate
+}}
+_still_hook
+"#,
+            exe = sh_quote(&exe)
+        ),
+        Shell::Fish => format!(
+            r#"function __still_hook --on-event fish_prompt --on-variable PWD
+    {exe} hook-env fish | source
+end
+function still_deactivate
+    functions --erase __still_hook
+    {exe} hook-env fish --deactivate | source
+    functions --erase still_deactivate
+end
+__still_hook
+"#,
+            exe = fish_quote(&exe)
+        ),
+        Shell::Nu => format!(
+            r#"def --env __still_apply [out: string] {{
+  if ($out | is-empty) {{ return }}
+  let diff = ($out | from json)
+  for name in $diff.unset {{ if $name in $env {{ hide-env $name }} }}
+  load-env $diff.set
+  if $diff.path != null {{ $env.PATH = $diff.path }}
+}}
+def --env __still_hook [] {{
+  if ($env.__STILL_OFF? | default false) {{ return }}
+  __still_apply (^{exe} hook-env nu | str trim)
+}}
+def --env still_deactivate [] {{
+  __still_apply (^{exe} hook-env nu --deactivate | str trim)
+  $env.__STILL_OFF = true
+}}
+$env.__STILL_OFF = false
+if not ($env.__STILL_HOOKED? | default false) {{
+  $env.__STILL_HOOKED = true
+  $env.config = ($env.config | upsert hooks.pre_prompt (($env.config.hooks?.pre_prompt? | default []) | append {{|| __still_hook }}))
+}}
+"#,
+            exe = sh_quote(&exe)
+        ),
+    }
+}
+
+/// Single-quote `value` for POSIX shells (and nushell, which reads it the same way).
+fn sh_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\'', r"'\''"))
+}
+
+fn fish_quote(value: &str) -> String {
+    format!("'{}'", value.replace('\\', r"\\").replace('\'', r"\'"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch::Scratch;
+
+    #[test]
+    fn applies_and_reverts_the_project_environment() {
+        let root = Scratch::new("activate");
+        let tool_dir = root.join("tools");
+        let node_bin = tool_dir.join("node/20.18.1/bin");
+        std::fs::create_dir_all(&node_bin).unwrap();
+        let project = root.join("project");
+        std::fs::create_dir_all(project.join("src")).unwrap();
+        std::fs::write(
+            project.join(MANIFEST_NAME),
+            "[tools]\nnode = \"20\"\n\n[env]\nNODE_ENV = \"development\"\nEDITOR = \"vi\"\n",
+        )
+        .unwrap();
+
+        let mut env = vec![
+            ("PATH".to_string(), "/usr/bin:/bin".to_string()),
+            ("EDITOR".to_string(), "nano".to_string()),
+        ];
+        let activation = |cwd: &Path, env: &[(String, String)]| Activation {
+            sources: ConfigSources {
+                global_file: None,
+                cwd: cwd.to_path_buf(),
+                env: env.to_vec(),
+            },
+            tool_dir: tool_dir.clone(),
+            shim_index: root.join("shims.toml"),
+        };
+        let apply = |env: &mut Vec<(String, String)>, diff: &EnvDiff| {
+            env.retain(|(k, _)| !diff.unset.contains(k) && !diff.set.contains_key(k));
+            env.extend(diff.set.iter().map(|(k, v)| (k.clone(), v.clone())));
+        };
+        let get = |env: &[(String, String)], name: &str| {
+            env.iter().find(|(k, _)| k == name).map(|(_, v)| v.clone())
+        };
+
+        let entered = activation(&project.join("src"), &env).hook_env().unwrap();
+        apply(&mut env, &entered);
+        let path = format!("{}:/usr/bin:/bin", node_bin.display());
+        assert_eq!(get(&env, "PATH"), Some(path.clone()));
+        assert_eq!(get(&env, "NODE_ENV").as_deref(), Some("development"));
+        assert_eq!(get(&env, "EDITOR").as_deref(), Some("vi"));
+        assert!(
+            entered
+                .render(Shell::Bash)
+                .contains("export NODE_ENV='development';")
+        );
+
+        // Same project, nothing changed: no work at all.
+        assert!(activation(&project, &env).hook_env().unwrap().is_empty());
+
+        // The user edits PATH meanwhile; leaving keeps their entry.
+        let user_path = format!("/opt/mine:{}", path);
+        apply(
+            &mut env,
+            &EnvDiff {
+                set: BTreeMap::from([("PATH".to_string(), user_path)]),
+                unset: BTreeSet::new(),
+            },
+        );
+        let left = activation(&root, &env).hook_env().unwrap();
+        apply(&mut env, &left);
+        assert_eq!(
+            get(&env, "PATH").as_deref(),
+            Some("/opt/mine:/usr/bin:/bin")
+        );
+        assert_eq!(get(&env, "NODE_ENV"), None);
+        assert_eq!(get(&env, "EDITOR").as_deref(), Some("nano"));
+
+        let entered = activation(&project, &env).hook_env().unwrap();
+        apply(&mut env, &entered);
+        let deactivated = activation(&project, &env).deactivate();
+        apply(&mut env, &deactivated);
+        assert_eq!(get(&env, "NODE_ENV"), None);
+        assert_eq!(get(&env, SESSION_VAR), None);
+        assert!(deactivated.render(Shell::Fish).contains("set -e NODE_ENV;"));
+
+        // An appended directory the user already has is only taken off the end.
+        std::fs::write(
+            project.join(MANIFEST_NAME),
+            "[tools]\nnode = \"20\"\n\n[env.path]\nappend = [\"/usr/bin\"]\n",
+        )
+        .unwrap();
+        let entered = activation(&project, &env).hook_env().unwrap();
+        apply(&mut env, &entered);
+        assert_eq!(
+            get(&env, "PATH"),
+            Some(format!(
+                "{}:/opt/mine:/usr/bin:/bin:/usr/bin",
+                node_bin.display()
+            ))
+        );
+        let deactivated = activation(&project, &env).deactivate();
+        apply(&mut env, &deactivated);
+        assert_eq!(
+            get(&env, "PATH").as_deref(),
+            Some("/opt/mine:/usr/bin:/bin")
+        );
+    }
+
+    #[test]
+    fn follows_env_files_and_service_ports() {
+        let root = Scratch::new("activate-files");
+        let project = root.join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(
+            project.join(MANIFEST_NAME),
+            "[env]\nfiles = [\".env\"]\n\n[services]\ndb = { preset = \"postgresql\", data = \"db\" }\n",
+        )
+        .unwrap();
+        let activation = |env: &[(String, String)]| Activation {
+            sources: ConfigSources {
+                global_file: None,
+                cwd: project.clone(),
+                env: env.to_vec(),
+            },
+            tool_dir: root.join("tools"),
+            shim_index: root.join("shims.toml"),
+        };
+        let mut env = vec![("PATH".to_string(), "/usr/bin".to_string())];
+        let mut enter = |env: &mut Vec<(String, String)>| {
+            let diff = activation(env).hook_env().unwrap();
+            env.retain(|(k, _)| !diff.unset.contains(k) && !diff.set.contains_key(k));
+            env.extend(diff.set.iter().map(|(k, v)| (k.clone(), v.clone())));
+            diff
+        };
+        let touch = |path: &Path, content: &str| {
+            std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+            std::fs::write(path, content).unwrap();
+            // Later than whatever the previous write left, however coarse the clock.
+            let later = std::time::SystemTime::now() + std::time::Duration::from_secs(60);
+            std::fs::File::options()
+                .write(true)
+                .open(path)
+                .unwrap()
+                .set_modified(later)
+                .unwrap();
+        };
+
+        assert!(!enter(&mut env).is_empty());
+        assert!(enter(&mut env).is_empty());
+
+        // A `.env` created later counts, as do its edits.
+        touch(&project.join(".env"), "GREETING=hello\n");
+        let diff = enter(&mut env);
+        assert_eq!(diff.set.get("GREETING").map(String::as_str), Some("hello"));
+        touch(&project.join(".env"), "GREETING=hi\n");
+        let diff = enter(&mut env);
+        assert_eq!(diff.set.get("GREETING").map(String::as_str), Some("hi"));
+
+        // So do the ports a preset records when it starts.
+        touch(
+            &project.join("db").join(crate::actions::preset::PORTS_FILE),
+            "PGPORT = 5433\n",
+        );
+        assert!(!enter(&mut env).is_empty());
+        assert!(enter(&mut env).is_empty());
+    }
+}
@@ -0,0 +1,609 @@
+use crate::actions::activate::{EnvDiff, Shell};
+use crate::actions::install::{version_key, version_matches};
+use crate::actions::service::Services;
+use crate::registries::tool_vars;
+use crate::specs::config::LayeredConfig;
+use crate::specs::receipt::Receipt;
+use crate::specs::toml::{LOCAL_MANIFEST_NAME, MANIFEST_NAME, Manifest};
+use crate::system::System;
+use crate::utils::dotenv::Dotenv;
+use crate::utils::paths::PathOps;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// A pinned tool resolved to an installed version.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ActiveTool {
+    pub name: String,
+    pub requested: String,
+    pub version: String,
+    pub install_path: PathBuf,
+}
+
+/// The environment the pinned tools of a [`LayeredConfig`] contribute.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ToolEnv {
+    pub tools: Vec<ActiveTool>,
+    /// Pinned tools with no matching install, as `name@version`.
+    pub missing: Vec<String>,
+    /// `bin` directories to put in front of `PATH`.
+    pub path: Vec<PathBuf>,
+    /// Directories to put at the end of `PATH`.
+    pub path_append: Vec<PathBuf>,
+    /// Variables tools need, such as `GOROOT`.
+    pub vars: BTreeMap<String, String>,
+    /// Files besides the manifests the variables were read from, whether they
+    /// exist yet or not: `[env] files` and the ports of service presets.
+    pub files: Vec<PathBuf>,
+}
+
+impl ToolEnv {
+    /// `base` with this environment on top: `vars` set, `path` in front of `PATH`
+    /// and `path_append` after it.
+    pub fn apply(&self, base: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+        let mut env = base.clone();
+        env.extend(self.vars.clone());
+        if !self.path.is_empty() || !self.path_append.is_empty() {
+            let existing = env.get("PATH").cloned().unwrap_or_default();
+            let path = std::env::join_paths(
+                self.path
+                    .iter()
+                    .cloned()
+                    .chain(std::env::split_paths(&existing))
+                    .chain(self.path_append.iter().cloned()),
+            )
+            .map(|path| path.to_string_lossy().to_string())
+            .unwrap_or(existing);
+            env.insert("PATH".to_string(), path);
+        }
+        env
+    }
+
+    /// What the project sets on top of `base`: `vars` and the final `PATH`.
+    pub fn exports(&self, base: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+        let mut exports = self.vars.clone();
+        if let Some(path) = self.apply(base).remove("PATH") {
+            exports.insert("PATH".to_string(), path);
+        }
+        exports
+    }
+}
+
+/// How `still env` writes the project environment for other programs to load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Shell(Shell),
+    Dotenv,
+    Json,
+    /// `$GITHUB_ENV` of GitHub Actions: `NAME=value`, heredocs for multiline values.
+    /// `PATH` goes to `$GITHUB_PATH` instead; see [`export_github`].
+    GithubEnv,
+}
+
+impl FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "sh" | "shell" => Ok(ExportFormat::Shell(Shell::Bash)),
+            "dotenv" | ".env" => Ok(ExportFormat::Dotenv),
+            "json" => Ok(ExportFormat::Json),
+            "github" | "github-env" => Ok(ExportFormat::GithubEnv),
+            other => other.parse().map(ExportFormat::Shell).map_err(|_| {
+                anyhow::anyhow!(
+                    "unsupported format \"{}\" (expected one of: bash, zsh, fish, dotenv, json, github)",
+                    other
+                )
+            }),
+        }
+    }
+}
+
+impl ExportFormat {
+    pub fn render(&self, vars: &BTreeMap<String, String>) -> Result<String> {
+        let mut out = String::new();
+        match self {
+            ExportFormat::Shell(shell) => {
+                let diff = EnvDiff {
+                    set: vars.clone(),
+                    ..EnvDiff::default()
+                };
+                out.push_str(&diff.render(*shell));
+            }
+            ExportFormat::Dotenv => {
+                for (name, value) in vars {
+                    out.push_str(&format!("{}={}\n", name, dotenv_quote(value)));
+                }
+            }
+            ExportFormat::Json => {
+                out.push_str(&serde_json::to_string_pretty(vars)?);
+                out.push('\n');
+            }
+            ExportFormat::GithubEnv => {
+                for (name, value) in vars {
+                    if !value.contains('\n') {
+                        out.push_str(&format!("{}={}\n", name, value));
+                        continue;
+                    }
+                    let mut delimiter = "STILL_EOF".to_string();
+                    while value.lines().any(|line| line == delimiter) {
+                        delimiter.push('_');
+                    }
+                    out.push_str(&format!(
+                        "{}<<{}\n{}\n{}\n",
+                        name, delimiter, value, delimiter
+                    ));
+                }
+            }
+        }
+        Ok(out)
+    }
+}
+
+/// Append the project environment to the files GitHub Actions reads between steps:
+/// its variables to `env_file` (`$GITHUB_ENV`) and the directories it adds to
+/// `PATH` to `path_file` (`$GITHUB_PATH`). Actions can only put directories in
+/// front of `PATH`, so `append` directories land before the runner's own.
+pub fn export_github(project: &ToolEnv, env_file: &Path, path_file: &Path) -> Result<()> {
+    use std::io::Write;
+
+    let append = |file: &Path, content: String| -> Result<()> {
+        std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(file)
+            .and_then(|mut f| f.write_all(content.as_bytes()))
+            .with_context(|| format!("Failed to write {}", file.display()))
+    };
+    append(env_file, ExportFormat::GithubEnv.render(&project.vars)?)?;
+    // Every line goes in front of the ones before it, so the first directory is last.
+    let dirs: String = project
+        .path
+        .iter()
+        .chain(&project.path_append)
+        .rev()
+        .map(|dir| format!("{}\n", dir.display()))
+        .collect();
+    append(path_file, dirs)
+}
+
+/// Double-quoted with the escapes [`Dotenv::parse`] understands.
+fn dotenv_quote(value: &str) -> String {
+    let mut quoted = String::from("\"");
+    for c in value.chars() {
+        match c {
+            '\\' => quoted.push_str("\\\\"),
+            '"' => quoted.push_str("\\\""),
+            '$' => quoted.push_str("\\$"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The [`PathOps`] directories, as shown by `still env`.
+pub fn directories() -> Vec<(&'static str, PathBuf)> {
+    vec![
+        ("root", System::root_dir()),
+        ("tools", System::tool_dir()),
+        ("bin", System::bin_dir()),
+        ("apps", System::apps_dir()),
+        ("services", System::services_dir()),
+        ("cache", System::cache_dir()),
+        ("config", System::config_file()),
+    ]
+}
+
+pub fn tool_env(config: &LayeredConfig) -> ToolEnv {
+    tool_env_in(config, &System::tool_dir())
+}
+
+pub fn tool_env_in(config: &LayeredConfig, tool_dir: &Path) -> ToolEnv {
+    let mut env = ToolEnv::default();
+    for (name, requested) in config.tool_versions() {
+        let requested = requested.to_string();
+        let Some((version, install_path)) =
+            find_installed(tool_dir, &install_dir_name(&name), &requested)
+        else {
+            env.missing.push(format!("{}@{}", name, requested));
+            continue;
+        };
+
+        let bin = install_path.join("bin");
+        if bin.is_dir() {
+            env.path.push(bin);
+        }
+        env.vars.extend(tool_vars(&name, &install_path));
+        env.tools.push(ActiveTool {
+            name,
+            requested,
+            version,
+            install_path,
+        });
+    }
+    env
+}
+
+/// The full environment of the project on top of the current process environment:
+/// [`tool_env`] plus the `[env]` tables of `still.toml` and `still.local.toml`.
+pub fn project_env(config: &LayeredConfig) -> Result<ToolEnv> {
+    let base: BTreeMap<String, String> = std::env::vars().collect();
+    project_env_in(config, &System::tool_dir(), &base)
+}
+
+/// Compose `[env]` over the tool environment, lowest precedence first: tool
+/// variables, the connection variables of service presets that `base` does not
+/// set, each manifest's dotenv `files` in order, `still.toml` values, then
+/// `still.local.toml` values. `${VAR}` sees everything composed so far, then `base`.
+pub fn project_env_in(
+    config: &LayeredConfig,
+    tool_dir: &Path,
+    base: &BTreeMap<String, String>,
+) -> Result<ToolEnv> {
+    let mut env = tool_env_in(config, tool_dir);
+    let Some(root) = config.project_root.clone() else {
+        return Ok(env);
+    };
+    let services = Services::load(&root)?;
+    for (name, value) in services.exports() {
+        if !base.contains_key(&name) {
+            env.vars.insert(name, value);
+        }
+    }
+    env.files.extend(services.ports_files());
+
+    let mut sections = Vec::new();
+    for name in [MANIFEST_NAME, LOCAL_MANIFEST_NAME] {
+        let path = root.join(name);
+        if path.is_file() {
+            sections.push(Manifest::load(&path)?.env);
+        }
+    }
+
+    for file in sections.iter().flat_map(|s| &s.files) {
+        let path = root.join(file);
+        env.files.push(path.clone());
+        if !path.is_file() {
+            continue;
+        }
+        for entry in Dotenv::load(&path)? {
+            // Templates are a still.toml feature; `{{` in a `.env` value is literal.
+            let value = if entry.interpolate {
+                Dotenv::expand_vars(&entry.value, |name| {
+                    env.vars.get(name).or_else(|| base.get(name)).cloned()
+                })?
+            } else {
+                entry.value
+            };
+            env.vars.insert(entry.key, value);
+        }
+    }
+
+    for (name, value) in sections.iter().flat_map(|s| &s.vars) {
+        let raw = match value {
+            toml::Value::String(s) => s.clone(),
+            toml::Value::Integer(_) | toml::Value::Float(_) | toml::Value::Boolean(_) => {
+                value.to_string()
+            }
+            _ => anyhow::bail!("[env] {} must be a string, number or boolean", name),
+        };
+        let value = expand(&raw, &env, base, &root)?;
+        env.vars.insert(name.clone(), value);
+    }
+
+    let mut prepend = Vec::new();
+    for dir in sections.iter().flat_map(|s| &s.path.prepend) {
+        prepend.push(root.join(expand(dir, &env, base, &root)?));
+    }
+    for dir in sections.iter().flat_map(|s| &s.path.append) {
+        let dir = root.join(expand(dir, &env, base, &root)?);
+        env.path_append.push(dir);
+    }
+    prepend.append(&mut env.path);
+    env.path = prepend;
+    Ok(env)
+}
+
+/// Interpolate an `[env]` value. Templates: `{{project_root}}`,
+/// `{{tools.<name>.path}}` and `{{tools.<name>.version}}`.
+fn expand(
+    value: &str,
+    env: &ToolEnv,
+    base: &BTreeMap<String, String>,
+    root: &Path,
+) -> Result<String> {
+    Dotenv::interpolate(
+        value,
+        |name| env.vars.get(name).or_else(|| base.get(name)).cloned(),
+        |expr| {
+            if expr == "project_root" {
+                return Ok(root.to_string_lossy().to_string());
+            }
+            let (tool, field) = expr
+                .strip_prefix("tools.")
+                .and_then(|rest| rest.rsplit_once('.'))
+                .ok_or_else(|| anyhow::anyhow!("Unknown template {{{{{}}}}}", expr))?;
+            let active =
+                env.tools.iter().find(|t| t.name == tool).ok_or_else(|| {
+                    anyhow::anyhow!("{{{{{}}}}}: {} is not installed", expr, tool)
+                })?;
+            match field {
+                "path" => Ok(active.install_path.to_string_lossy().to_string()),
+                "version" => Ok(active.version.clone()),
+                other => anyhow::bail!("Unknown tool field {} in {{{{{}}}}}", other, expr),
+            }
+        },
+    )
+}
+
+/// The directory under `tool_dir` a tool key installs into. Homebrew, core tools
+/// and rustup use the name; other backends prefix it so they never share a
+/// formula's directory: `npm:prettier` into `npm-prettier`, `github:owner/repo`
+/// into `github-owner/repo`.
+pub fn install_dir_name(tool: &str) -> String {
+    match tool.split_once(':') {
+        None => tool.to_string(),
+        Some(("brew" | "core" | "rustup", name)) => name.to_string(),
+        Some((backend, name)) => format!("{}-{}", backend, name),
+    }
+}
+
+/// The newest version of `name` under `tool_dir` that satisfies `requested`.
+/// `latest` picks the newest install. Other named versions (`stable`, `lts`,
+/// `lts/iron`) match an install of that name, or one whose [`Receipt`] shows it was
+/// installed for that request; they are left unresolved otherwise.
+pub fn find_installed(tool_dir: &Path, name: &str, requested: &str) -> Option<(String, PathBuf)> {
+    let dir = tool_dir.join(name);
+    let entries = std::fs::read_dir(&dir).ok()?;
+    let installed: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_ok_and(|t| t.is_dir()))
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .filter(|version| !version.starts_with('.'))
+        .collect();
+
+    let numeric = requested.starts_with(|c: char| c.is_ascii_digit() || c == 'v');
+    let version = if installed.iter().any(|v| v == requested) {
+        requested.to_string()
+    } else {
+        installed
+            .into_iter()
+            .filter(|v| {
+                if requested.eq_ignore_ascii_case("latest") {
+                    true
+                } else if numeric {
+                    version_matches(requested, v)
+                } else {
+                    Receipt::load(&dir.join(v))
+                        .ok()
+                        .flatten()
+                        .and_then(|receipt| receipt.requested)
+                        .is_some_and(|r| r.eq_ignore_ascii_case(requested))
+                }
+            })
+            .max_by_key(|v| version_key(v))?
+    };
+
+    let path = dir.join(&version);
+    Some((version, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::specs::config::ConfigSources;
+    use crate::utils::scratch::Scratch;
+
+    #[test]
+    fn resolves_pinned_tools_to_installs() {
+        let root = Scratch::new("env");
+        let tool_dir = root.join("tools");
+        for install in [
+            "go/1.21.5",
+            "go/1.22.3",
+            "go/1.22.10",
+            "node/20.18.1",
+            "prettier/3.3.3",
+            "npm-prettier/3.2.0",
+        ] {
+            std::fs::create_dir_all(tool_dir.join(install).join("bin")).unwrap();
+        }
+        let project = root.join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(
+            project.join("still.toml"),
+            "[tools]\ngo = \"1.22\"\nnode = \"22\"\n\"npm:prettier\" = \"3\"\n",
+        )
+        .unwrap();
+
+        let config = LayeredConfig::load_from(&ConfigSources {
+            global_file: None,
+            cwd: project,
+            env: Vec::new(),
+        })
+        .unwrap();
+        let env = tool_env_in(&config, &tool_dir);
+
+        let go = tool_dir.join("go").join("1.22.10");
+        assert_eq!(env.tools.len(), 2);
+        assert_eq!(env.tools[0].version, "1.22.10");
+        // Never the Homebrew formula of the same name.
+        assert_eq!(
+            env.tools[1].install_path,
+            tool_dir.join("npm-prettier/3.2.0")
+        );
+        assert_eq!(
+            env.path,
+            vec![go.join("bin"), tool_dir.join("npm-prettier/3.2.0/bin")]
+        );
+        assert_eq!(env.vars["GOROOT"], go.to_string_lossy());
+        assert_eq!(env.missing, vec!["node@22"]);
+
+        assert_eq!(
+            find_installed(&tool_dir, "go", "latest").map(|(v, _)| v),
+            Some("1.22.10".to_string())
+        );
+        assert_eq!(find_installed(&tool_dir, "go", "1.2"), None);
+
+        // Channels only resolve to installs made for them.
+        assert_eq!(find_installed(&tool_dir, "node", "lts"), None);
+        Receipt {
+            tool: "node".into(),
+            version: "20.18.1".into(),
+            requested: Some("lts".into()),
+            ..Default::default()
+        }
+        .save(&tool_dir.join("node/20.18.1"))
+        .unwrap();
+        assert_eq!(
+            find_installed(&tool_dir, "node", "lts").map(|(v, _)| v),
+            Some("20.18.1".to_string())
+        );
+        assert_eq!(find_installed(&tool_dir, "node", "lts/jod"), None);
+        assert_eq!(find_installed(&tool_dir, "go", "stable"), None);
+    }
+
+    #[test]
+    fn composes_env_from_files_manifests_and_templates() {
+        let root = Scratch::new("env-compose");
+        let tool_dir = root.join("tools");
+        std::fs::create_dir_all(tool_dir.join("node/20.18.1/bin")).unwrap();
+        let project = root.join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(
+            project.join("still.toml"),
+            r#"[tools]
+node = "20"
+
+[env]
+files = [".env", ".env.missing"]
+CACHE = "${HOME}/.cache/app"
+NODE_HOME = "{{ tools.node.path }}"
+PORT = 3000
+MODE = "dev"
+
+[env.path]
+prepend = ["node_modules/.bin"]
+append = ["/opt/extra"]
+
+[services]
+cache = { preset = "redis", data = ".data/cache" }
+"#,
+        )
+        .unwrap();
+        std::fs::create_dir_all(project.join(".data/cache")).unwrap();
+        std::fs::write(
+            project.join(".data/cache/ports.toml"),
+            "REDIS_PORT = 6390\n",
+        )
+        .unwrap();
+        std::fs::write(
+            project.join("still.local.toml"),
+            "[env]
+MODE = \"local\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            project.join(".env"),
+            "MODE=file\nTOKEN='${literal}'\nGREETING=\"hi $USER\"\n",
+        )
+        .unwrap();
+
+        let config = LayeredConfig::load_from(&ConfigSources {
+            global_file: None,
+            cwd: project.clone(),
+            env: Vec::new(),
+        })
+        .unwrap();
+        let base = BTreeMap::from([
+            ("HOME".to_string(), "/home/me".to_string()),
+            ("USER".to_string(), "me".to_string()),
+            ("PATH".to_string(), "/usr/bin".to_string()),
+            (
+                "REDIS_URL".to_string(),
+                "redis://cache.internal".to_string(),
+            ),
+        ]);
+        let env = project_env_in(&config, &tool_dir, &base).unwrap();
+
+        let node = tool_dir.join("node/20.18.1");
+        assert_eq!(env.vars["CACHE"], "/home/me/.cache/app");
+        assert_eq!(env.vars["NODE_HOME"], node.to_string_lossy());
+        assert_eq!(env.vars["PORT"], "3000");
+        assert_eq!(env.vars["MODE"], "local");
+        assert_eq!(env.vars["TOKEN"], "${literal}");
+        assert_eq!(env.vars["GREETING"], "hi me");
+        // Preset connection variables, except those the user set already.
+        assert_eq!(env.vars["REDIS_PORT"], "6390");
+        assert!(!env.vars.contains_key("REDIS_URL"));
+
+        let path = env.apply(&base)["PATH"].clone();
+        let expected = std::env::join_paths([
+            project.join("node_modules/.bin"),
+            node.join("bin"),
+            PathBuf::from("/usr/bin"),
+            PathBuf::from("/opt/extra"),
+        ])
+        .unwrap();
+        assert_eq!(path, expected.to_string_lossy());
+    }
+
+    #[test]
+    fn exports_in_every_format() {
+        let vars = BTreeMap::from([
+            ("CERT".to_string(), "line one\nline two".to_string()),
+            ("GREETING".to_string(), "say \"hi\" for $5".to_string()),
+            ("PATH".to_string(), "/a/bin:/usr/bin".to_string()),
+        ]);
+        let render = |format: &str| {
+            format
+                .parse::<ExportFormat>()
+                .unwrap()
+                .render(&vars)
+                .unwrap()
+        };
+
+        let dotenv = render("dotenv");
+        let parsed = Dotenv::parse(&dotenv).unwrap();
+        for entry in parsed {
+            let value = Dotenv::expand_vars(&entry.value, |_| None).unwrap();
+            assert_eq!(value, vars[&entry.key], "{dotenv}");
+        }
+
+        assert!(render("github").contains("CERT<<STILL_EOF\nline one\nline two\nSTILL_EOF\n"));
+        assert!(render("zsh").contains("export PATH='/a/bin:/usr/bin';"));
+        assert!(render("fish").contains("set -gx PATH '/a/bin' '/usr/bin';"));
+        let json: BTreeMap<String, String> = serde_json::from_str(&render("json")).unwrap();
+        assert_eq!(json, vars);
+        assert!("toml".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn exports_path_additions_to_github_path() {
+        let root = Scratch::new("github-env");
+        let (env_file, path_file) = (root.join("env"), root.join("path"));
+        std::fs::write(&env_file, "EARLIER=1\n").unwrap();
+        let project = ToolEnv {
+            path: vec!["/p/node/bin".into(), "/p/go/bin".into()],
+            path_append: vec!["/opt/extra".into()],
+            vars: BTreeMap::from([("GOROOT".to_string(), "/p/go".to_string())]),
+            ..ToolEnv::default()
+        };
+
+        export_github(&project, &env_file, &path_file).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&env_file).unwrap(),
+            "EARLIER=1\nGOROOT=/p/go\n"
+        );
+        assert_eq!(
+            std::fs::read_to_string(&path_file).unwrap(),
+            "/opt/extra\n/p/go/bin\n/p/node/bin\n"
+        );
+    }
+}
@@ -0,0 +1,400 @@
+use crate::registries::rust;
+use crate::specs::toml::{MANIFEST_NAME, Manifest, ToolEntry, ToolOptions};
+use anyhow::{Context, Result};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Something `still init` found in an existing project that maps onto the manifest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detection {
+    /// Marker file the value came from (`.nvmrc`, `go.mod`, ...).
+    pub source: String,
+    pub item: DetectedItem,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DetectedItem {
+    Tool { name: String, entry: ToolEntry },
+    Package(String),
+    App(String),
+}
+
+impl fmt::Display for Detection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.item {
+            DetectedItem::Tool { name, entry } => match entry.backend() {
+                Some(backend) => write!(
+                    f,
+                    "tool {} = \"{}\" ({} backend) from {}",
+                    name,
+                    entry.version(),
+                    backend,
+                    self.source
+                ),
+                None => write!(
+                    f,
+                    "tool {} = \"{}\" from {}",
+                    name,
+                    entry.version(),
+                    self.source
+                ),
+            },
+            DetectedItem::Package(name) => write!(f, "package {} from {}", name, self.source),
+            DetectedItem::App(name) => write!(f, "app {} from {}", name, self.source),
+        }
+    }
+}
+
+pub fn manifest_path(dir: &Path) -> PathBuf {
+    dir.join(MANIFEST_NAME)
+}
+
+/// Fail early if `still init` would clobber an existing manifest.
+pub fn ensure_can_write(dir: &Path, force: bool) -> Result<()> {
+    let path = manifest_path(dir);
+    if path.exists() && !force {
+        anyhow::bail!(
+            "{} already exists. Use --force to overwrite it.",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Look for ecosystem markers in `dir`.
+pub fn detect(dir: &Path) -> Result<Vec<Detection>> {
+    let mut detections = Vec::new();
+
+    let nvmrc = read_marker(dir, ".nvmrc")?;
+    if let Some(version) = nvmrc.as_deref().and_then(parse_nvmrc) {
+        detections.push(tool(".nvmrc", "node", ToolEntry::Version(version)));
+    }
+
+    let python_version = read_marker(dir, ".python-version")?;
+    if let Some(version) = python_version.as_deref().and_then(first_line) {
+        detections.push(tool(
+            ".python-version",
+            "python",
+            ToolEntry::Version(version.to_string()),
+        ));
+    }
+
+    let toolchain_toml = match read_marker(dir, "rust-toolchain.toml")? {
+        Some(content) => parse_rust_toolchain_toml(&content)?,
+        None => None,
+    };
+    if let Some(entry) = toolchain_toml {
+        detections.push(tool("rust-toolchain.toml", "rust", entry));
+    } else {
+        let toolchain = read_marker(dir, "rust-toolchain")?;
+        if let Some(channel) = toolchain.as_deref().and_then(first_line) {
+            detections.push(tool(
+                "rust-toolchain",
+                "rust",
+                rustup_entry(channel.to_string(), Vec::new(), Vec::new()),
+            ));
+        }
+    }
+
+    let go_mod = read_marker(dir, "go.mod")?;
+    if let Some(version) = go_mod.and_then(|content| parse_go_mod(&content).go) {
+        detections.push(tool("go.mod", "go", ToolEntry::Version(version)));
+    }
+
+    if let Some(content) = read_marker(dir, "package.json")? {
+        for (name, entry) in parse_package_json(&content)? {
+            detections.push(tool("package.json", &name, entry));
+        }
+    }
+
+    if let Some(content) = read_marker(dir, "Brewfile")? {
+        for item in parse_brewfile(&content) {
+            detections.push(Detection {
+                source: "Brewfile".to_string(),
+                item,
+            });
+        }
+    }
+
+    Ok(dedupe(detections))
+}
+
+/// Build a manifest from the accepted detections.
+pub fn build_manifest(detections: &[Detection]) -> Manifest {
+    let mut manifest = Manifest::default();
+    for detection in detections {
+        match &detection.item {
+            DetectedItem::Tool { name, entry } => {
+                manifest.tools.insert(name.clone(), entry.clone());
+            }
+            DetectedItem::Package(name) => manifest.packages.latest.push(name.clone()),
+            DetectedItem::App(name) => manifest.apps.latest.push(name.clone()),
+        }
+    }
+    manifest
+}
+
+/// Write `still.toml` into `dir`, refusing to overwrite unless `force` is set.
+pub fn write_manifest(dir: &Path, manifest: &Manifest, force: bool) -> Result<PathBuf> {
+    ensure_can_write(dir, force)?;
+
+    let path = manifest_path(dir);
+    let content = format!(
+        "# Project environment managed by Still.\n\n{}",
+        manifest.to_toml_string()?
+    );
+    std::fs::write(&path, content)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(path)
+}
+
+/* ----------------------------- marker parsers ----------------------------- */
+
+fn read_marker(dir: &Path, name: &str) -> Result<Option<String>> {
+    let path = dir.join(name);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    std::fs::read_to_string(&path)
+        .map(Some)
+        .with_context(|| format!("Failed to read {}", path.display()))
+}
+
+fn tool(source: &str, name: &str, entry: ToolEntry) -> Detection {
+    Detection {
+        source: source.to_string(),
+        item: DetectedItem::Tool {
+            name: name.to_string(),
+            entry,
+        },
+    }
+}
+
+fn rustup_entry(version: String, components: Vec<String>, targets: Vec<String>) -> ToolEntry {
+    ToolEntry::Detailed(Box::new(ToolOptions {
+        version,
+        backend: Some("rustup".to_string()),
+        components,
+        targets,
+        ..ToolOptions::default()
+    }))
+}
+
+fn first_line(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))
+}
+
+/// `v20.11.0` -> `20.11.0`, `lts/*` -> `lts`, `lts/iron` stays, `node` -> `latest`.
+fn parse_nvmrc(content: &str) -> Option<String> {
+    let line = first_line(content)?;
+    let version = match line {
+        "node" | "stable" | "current" => "latest".to_string(),
+        "lts/*" => "lts".to_string(),
+        l if l.starts_with("lts/") => l.to_ascii_lowercase(),
+        l => l.strip_prefix('v').unwrap_or(l).to_string(),
+    };
+    Some(version)
+}
+
+fn parse_rust_toolchain_toml(content: &str) -> Result<Option<ToolEntry>> {
+    Ok(rust::parse_toolchain_toml(content)?
+        .map(|options| rustup_entry(options.version, options.components, options.targets)))
+}
+
+/// The `go` and `toolchain` directives of a `go.mod`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GoModVersions {
+    pub go: Option<String>,
+    /// `toolchain go1.22.3`, without the `go` prefix.
+    pub toolchain: Option<String>,
+}
+
+pub fn parse_go_mod(content: &str) -> GoModVersions {
+    let mut versions = GoModVersions::default();
+    for line in content.lines() {
+        let line = line.split("//").next().unwrap_or("").trim();
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("go"), Some(version)) => versions.go = Some(version.to_string()),
+            (Some("toolchain"), Some(name)) => {
+                versions.toolchain = name.strip_prefix("go").map(str::to_string)
+            }
+            _ => {}
+        }
+    }
+    versions
+}
+
+/// `engines.node` and `packageManager` from a `package.json`.
+fn parse_package_json(content: &str) -> Result<Vec<(String, ToolEntry)>> {
+    let json: serde_json::Value =
+        serde_json::from_str(content).context("Failed to parse package.json")?;
+    let mut tools = Vec::new();
+
+    if let Some(version) = json
+        .pointer("/engines/node")
+        .and_then(|v| v.as_str())
+        .and_then(version_from_range)
+    {
+        tools.push(("node".to_string(), ToolEntry::Version(version)));
+    }
+
+    // "pnpm@9.1.0+sha512.abc" -> pnpm 9.1.0 from npm
+    if let Some((name, version)) = json
+        .get("packageManager")
+        .and_then(|v| v.as_str())
+        .and_then(|manager| manager.split_once('@'))
+    {
+        let version = version.split('+').next().unwrap_or(version);
+        if name != "npm" && !name.is_empty() && !version.is_empty() {
+            tools.push((
+                name.to_string(),
+                ToolEntry::Detailed(Box::new(ToolOptions {
+                    version: version.to_string(),
+                    backend: Some("npm".to_string()),
+                    ..ToolOptions::default()
+                })),
+            ));
+        }
+    }
+
+    Ok(tools)
+}
+
+/// Turn a semver range into the major version it starts from: `>=18.0.0` -> `18`, `^20.1` -> `20`.
+/// Ranges without an inclusive lower bound (`<20`, `>18`, `*`) give nothing.
+fn version_from_range(range: &str) -> Option<String> {
+    let range = range.split("||").next()?.trim();
+    let version = [">=", "^", "~", "="]
+        .iter()
+        .find_map(|op| range.strip_prefix(op))
+        .unwrap_or(range)
+        .trim_start()
+        .trim_start_matches('v');
+    let major: String = version.chars().take_while(|c| c.is_ascii_digit()).collect();
+    (!major.is_empty()).then_some(major)
+}
+
+/// `brew "x"` lines become packages and `cask "y"` lines become apps.
+fn parse_brewfile(content: &str) -> Vec<DetectedItem> {
+    let mut items = Vec::new();
+    for line in content.lines() {
+        let line = line.trim();
+        let Some((kind, rest)) = line.split_once(char::is_whitespace) else {
+            continue;
+        };
+        let Some(name) = rest
+            .trim()
+            .strip_prefix('"')
+            .and_then(|r| r.split('"').next())
+        else {
+            continue;
+        };
+        match kind {
+            "brew" => items.push(DetectedItem::Package(name.to_string())),
+            "cask" => items.push(DetectedItem::App(name.to_string())),
+            _ => {}
+        }
+    }
+    items
+}
+
+/// Keep the first detection for each tool (`.nvmrc` wins over `package.json` engines).
+fn dedupe(detections: Vec<Detection>) -> Vec<Detection> {
+    let mut kept: Vec<Detection> = Vec::new();
+    for detection in detections {
+        let duplicate = kept.iter().any(|k| match (&k.item, &detection.item) {
+            (DetectedItem::Tool { name: a, .. }, DetectedItem::Tool { name: b, .. }) => a == b,
+            (a, b) => a == b,
+        });
+        if !duplicate {
+            kept.push(detection);
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch::Scratch;
+
+    #[test]
+    fn detects_project_markers() {
+        let dir = Scratch::new("init-detect");
+        std::fs::write(dir.join(".nvmrc"), "v20.11.0\n").unwrap();
+        std::fs::write(dir.join(".python-version"), "3.12\n").unwrap();
+        std::fs::write(
+            dir.join("rust-toolchain.toml"),
+            "[toolchain]\nchannel = \"1.78\"\ncomponents = [\"clippy\"]\ntargets = [\"wasm32-unknown-unknown\"]\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("go.mod"),
+            "module example.com/x\n\ngo 1.22 // minimum\ntoolchain go1.22.3\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("package.json"),
+            r#"{ "engines": { "node": ">=18" }, "packageManager": "pnpm@9.1.0+sha512.abc" }"#,
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("Brewfile"),
+            "tap \"homebrew/bundle\"\nbrew \"ripgrep\"\nbrew \"mysql\", restart_service: true\ncask \"zed\"\n",
+        )
+        .unwrap();
+
+        let manifest = build_manifest(&detect(&dir).unwrap());
+
+        assert_eq!(manifest.tools["node"].version(), "20.11.0");
+        assert_eq!(manifest.tools["python"].version(), "3.12");
+        assert_eq!(manifest.tools["go"].version(), "1.22");
+        assert_eq!(manifest.tools["pnpm"].backend(), Some("npm"));
+        assert_eq!(manifest.tools["pnpm"].version(), "9.1.0");
+        let ToolEntry::Detailed(rust) = &manifest.tools["rust"] else {
+            panic!("rust should be a detailed entry");
+        };
+        assert_eq!(rust.version, "1.78");
+        assert_eq!(rust.components, vec!["clippy"]);
+        assert_eq!(manifest.packages.latest, vec!["ripgrep", "mysql"]);
+        assert_eq!(manifest.apps.latest, vec!["zed"]);
+
+        // The written manifest parses back to the same thing.
+        let path = write_manifest(&dir, &manifest, false).unwrap();
+        assert_eq!(Manifest::load(&path).unwrap(), manifest);
+
+        assert_eq!(parse_nvmrc("lts/Iron\n").as_deref(), Some("lts/iron"));
+        assert_eq!(parse_nvmrc("lts/*\n").as_deref(), Some("lts"));
+
+        assert_eq!(version_from_range("^20.1 || ^22").as_deref(), Some("20"));
+        assert_eq!(version_from_range("~18.2").as_deref(), Some("18"));
+        assert_eq!(version_from_range("22.3.0").as_deref(), Some("22"));
+        assert_eq!(version_from_range("<20"), None);
+        assert_eq!(version_from_range("<=20.5"), None);
+        assert_eq!(version_from_range("*"), None);
+    }
+
+    #[test]
+    fn never_overwrites_without_force() {
+        let dir = Scratch::new("init-force");
+        std::fs::write(dir.join(MANIFEST_NAME), "[tools]\n").unwrap();
+
+        assert!(write_manifest(&dir, &Manifest::default(), false).is_err());
+        assert_eq!(
+            std::fs::read_to_string(dir.join(MANIFEST_NAME)).unwrap(),
+            "[tools]\n"
+        );
+        assert!(write_manifest(&dir, &Manifest::default(), true).is_ok());
+    }
+
+    #[test]
+    fn parses_go_mod_directives() {
+        let versions = parse_go_mod("module x\n\ngo 1.21.0\n\ntoolchain go1.22.3\n");
+        assert_eq!(versions.go.as_deref(), Some("1.21.0"));
+        assert_eq!(versions.toolchain.as_deref(), Some("1.22.3"));
+    }
+}
@@ -1,14 +1,23 @@
+use crate::actions::env::install_dir_name;
+use crate::actions::shim::{self, ShimIndex};
 use crate::registries::specs::tool::ToolSpec;
-use crate::specs::brew::{BottleFileSpec, BottleSpec};
+use crate::registries::{Registry, Release};
+use crate::specs::brew::{BottleFileSpec, BottleSpec, FormulaSpec};
+use crate::specs::config::LayeredConfig;
+use crate::specs::lock::{LockedTool, Lockfile, project_lockfile_path};
+use crate::specs::receipt::Receipt;
+use crate::specs::tool::Backend;
 use crate::system::{MacOS, System};
 use crate::utils::archive::ArchiveExtractor;
 use crate::utils::hashing::Hashing;
-use crate::utils::link::SymlinkOps;
 use crate::utils::net::NetUtils;
 use crate::utils::paths::PathOps;
+use crate::utils::transaction::FsTransaction;
 use anyhow::{Context, Result};
+use std::collections::{HashSet, VecDeque};
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
+use tokio::task::JoinSet;
 
 pub trait InstallOps {
     fn select_bottle_file(bottle: &BottleSpec) -> Result<BottleFileSpec>;
@@ -19,7 +28,9 @@ pub trait InstallOps {
 }
 
 pub struct InstallRequest {
-    pub tool: ToolSpec,
+    pub tools: Vec<ToolSpec>,
+    /// Build crates with their own `Cargo.lock` (`cargo install --locked`).
+    pub locked: bool,
 }
 
 pub struct InstallResult {
@@ -29,43 +40,433 @@ pub struct InstallResult {
     pub binary_path: Option<PathBuf>,
 }
 
-pub async fn run(request: InstallRequest) -> Result<InstallResult> {
+/// Where a planned install comes from.
+#[derive(Debug, Clone)]
+pub enum InstallSource {
+    /// A Homebrew bottle, poured from GHCR.
+    Bottle {
+        formula: Box<FormulaSpec>,
+        bottle_file: BottleFileSpec,
+    },
+    /// A build resolved by one of the [`Registry`] backends.
+    Release {
+        registry: Registry,
+        release: Box<Release>,
+    },
+}
+
+/// A tool that will be installed, either requested directly or pulled in as a dependency.
+#[derive(Debug, Clone)]
+pub struct PlannedInstall {
+    /// The spec that asked for this install; `None` for dependencies.
+    pub requested: Option<ToolSpec>,
+    pub name: String,
+    pub version: String,
+    pub source: InstallSource,
+    pub install_path: PathBuf,
+    /// Compressed download size, when known.
+    pub download_size: Option<u64>,
+    /// Size once installed, when known.
+    pub installed_size: Option<u64>,
+}
+
+/// Everything a single `still install` will do, resolved and deduplicated up front.
+#[derive(Debug, Clone, Default)]
+pub struct InstallPlan {
+    pub steps: Vec<PlannedInstall>,
+    /// Dependencies that are already installed at the planned version.
+    pub satisfied: Vec<String>,
+    /// Install the files only, without shims or lock entries: packages Still runs
+    /// itself, such as those of service presets.
+    pub files_only: bool,
+}
+
+impl InstallPlan {
+    pub fn requested_count(&self) -> usize {
+        self.steps.iter().filter(|s| s.requested.is_some()).count()
+    }
+
+    pub fn dependency_count(&self) -> usize {
+        self.steps.len() - self.requested_count()
+    }
+
+    /// Total download size, or `None` if any step's size is unknown.
+    pub fn download_size(&self) -> Option<u64> {
+        self.steps.iter().map(|s| s.download_size).sum()
+    }
+
+    /// Total disk usage after install, or `None` if any step's size is unknown.
+    pub fn installed_size(&self) -> Option<u64> {
+        self.steps.iter().map(|s| s.installed_size).sum()
+    }
+}
+
+/// Resolve every requested tool (and, for Homebrew, its dependencies) into one plan.
+pub async fn plan(request: &InstallRequest) -> Result<InstallPlan> {
+    let config = LayeredConfig::load()?;
+
+    let mut plan = InstallPlan::default();
+    let mut bottles = Vec::new();
+    for tool in &request.tools {
+        match Registry::for_tool(tool, &config)? {
+            Some(mut registry) => {
+                if let Registry::Cargo(cargo) = &mut registry {
+                    cargo.locked |= request.locked;
+                }
+                let release = registry
+                    .resolve(tool)
+                    .await
+                    .with_context(|| format!("Failed to resolve {}", tool))?;
+                let install_path =
+                    compute_install_path(&install_dir_name(&tool.key()), &release.version);
+                if let Registry::Asdf(asdf) = &mut registry {
+                    asdf.install_path = Some(install_path.clone());
+                }
+                plan.steps.push(PlannedInstall {
+                    requested: Some(tool.clone()),
+                    name: release.name.clone(),
+                    version: release.version.clone(),
+                    install_path,
+                    download_size: release.size,
+                    installed_size: None,
+                    source: InstallSource::Release {
+                        registry,
+                        release: Box::new(release),
+                    },
+                });
+            }
+            None => bottles.push(tool.clone()),
+        }
+    }
+
+    fill_release_sizes(&mut plan.steps).await;
+    if !bottles.is_empty() {
+        plan_bottles(&bottles, &mut plan).await?;
+    }
+    Ok(plan)
+}
+
+/// Resolve Homebrew formulae and their dependencies, breadth first.
+async fn plan_bottles(tools: &[ToolSpec], plan: &mut InstallPlan) -> Result<()> {
     let formula_path = formula_json_path();
     ensure_formula_json_exists(&formula_path)?;
-
     let formulas = load_formula_json_array(&formula_path).await?;
-    let formula = find_matching_formula(&formulas, &request.tool.name)
-        .with_context(|| format!("Formula '{}' not found in formula.json", request.tool.name))?;
 
-    warn_if_version_mismatch(&request.tool, &formula);
+    let mut queue = VecDeque::new();
+    for tool in tools {
+        queue.push_back((Some(tool.clone()), resolve_formula(&formulas, tool)?));
+    }
 
-    let bottle_info = build_bottle_info(&formula)?;
-    println!(
-        "Found bottle for {}@{}",
-        bottle_info.formula_name, bottle_info.version
-    );
+    let first_bottle = plan.steps.len();
+    let mut seen = HashSet::new();
+    while let Some((requested, formula)) = queue.pop_front() {
+        if !seen.insert(formula.name.clone()) {
+            continue;
+        }
+
+        for dependency in &formula.dependencies {
+            let dep = find_matching_formula(&formulas, dependency).with_context(|| {
+                format!(
+                    "Dependency '{}' of '{}' not found in formula.json",
+                    dependency, formula.name
+                )
+            })?;
+            queue.push_back((None, dep));
+        }
+
+        let install_path = compute_install_path(&formula.name, &formula.versions.stable);
+        if requested.is_none() && install_path.exists() {
+            plan.satisfied.push(formula.name.clone());
+            continue;
+        }
 
-    let bottle_file = System::select_bottle_file(&bottle_info.bottle)?;
-    println!("Selected bottle: {}", bottle_file.url);
+        let bottle_info = build_bottle_info(&formula)?;
+        let bottle_file = System::select_bottle_file(&bottle_info.bottle)?;
+        plan.steps.push(PlannedInstall {
+            requested,
+            name: formula.name.clone(),
+            version: formula.versions.stable.clone(),
+            install_path,
+            download_size: None,
+            installed_size: None,
+            source: InstallSource::Bottle {
+                formula: Box::new(formula),
+                bottle_file,
+            },
+        });
+    }
 
-    let bottle_data = fetch_and_verify_bottle(&formula.name, &bottle_file).await?;
+    fill_bottle_sizes(&mut plan.steps[first_bottle..]).await;
+    Ok(())
+}
 
-    let install_path = compute_install_path(&formula.name, &formula.versions.stable);
-    reinstall_to_path(&bottle_data, &install_path).await?;
+/// Download and unpack every step in parallel, then commit them all as one transaction,
+/// together with their shims and lock entries unless the plan is
+/// [`files_only`](InstallPlan::files_only). Nothing under `tool_dir`, `bin_dir` or
+/// the project's lockfile changes unless every download verifies and extracts.
+pub async fn execute(plan: InstallPlan) -> Result<Vec<InstallResult>> {
+    let mut transaction = FsTransaction::new();
+    let mut stagings = Vec::new();
+    for step in &plan.steps {
+        stagings.push(transaction.stage_install(&step.install_path)?);
+    }
 
-    let binary_path = System::find_binary_recursive(&install_path, &formula.name).await?;
-    maybe_link_binary(&binary_path).await?;
+    // npm packages are installed by a Node.js that may be part of this plan,
+    // so they wait for everything else.
+    let runs_npm = |step: &PlannedInstall| {
+        matches!(
+            &step.source,
+            InstallSource::Release {
+                registry: Registry::Npm(_),
+                ..
+            }
+        )
+    };
+    let mut first = Vec::new();
+    let mut then = Vec::new();
+    for (step, staging) in plan.steps.iter().zip(&stagings) {
+        let batch = if runs_npm(step) {
+            &mut then
+        } else {
+            &mut first
+        };
+        batch.push((step.clone(), staging.clone()));
+    }
+    stage_all(first).await?;
 
-    Ok(InstallResult {
-        tool_name: formula.name.clone(),
-        version: formula.versions.stable.clone(),
-        install_path,
-        binary_path,
-    })
+    let staged_node = plan
+        .steps
+        .iter()
+        .zip(&stagings)
+        .find(|(step, _)| {
+            step.requested
+                .as_ref()
+                .is_some_and(|tool| tool.key() == "node")
+        })
+        .map(|(step, staging)| (staging.clone(), step.install_path.clone()));
+    for (step, _) in &mut then {
+        if let InstallSource::Release {
+            registry: Registry::Npm(npm),
+            ..
+        } = &mut step.source
+        {
+            npm.staged_node = staged_node.clone();
+        }
+    }
+    stage_all(then).await?;
+
+    if plan.files_only {
+        transaction.commit()?;
+        return Ok(plan
+            .steps
+            .into_iter()
+            .filter(|step| step.requested.is_some())
+            .map(|step| InstallResult {
+                tool_name: step.name,
+                version: step.version,
+                install_path: step.install_path,
+                binary_path: None,
+            })
+            .collect());
+    }
+
+    let exe = shim::still_exe(&System::bin_dir())?;
+    let index_path = ShimIndex::path();
+    let _index_lock = ShimIndex::lock(&index_path)?;
+    let mut index = ShimIndex::load(&index_path)?;
+    let lock_path = project_lockfile_path();
+    let mut lockfile = match &lock_path {
+        Some(path) => Some(Lockfile::load(path)?),
+        None => None,
+    };
+    let mut shims = Vec::new();
+    let mut results = Vec::new();
+    for (step, staging) in plan.steps.into_iter().zip(stagings) {
+        let Some(tool) = &step.requested else {
+            continue;
+        };
+
+        // Binaries are found in the staged files and linked at their final path.
+        let (binaries, locked) = match &step.source {
+            InstallSource::Bottle {
+                formula,
+                bottle_file,
+            } => {
+                let binary = System::find_binary_recursive(&staging, &formula.name).await?;
+                let locked = LockedTool {
+                    version: step.version.clone(),
+                    url: Some(bottle_file.url.clone()),
+                    sha256: Some(bottle_file.sha256.clone()),
+                    integrity: None,
+                };
+                (binary.into_iter().collect::<Vec<_>>(), locked)
+            }
+            InstallSource::Release { release, .. } => {
+                let binaries = release_bins(release, &staging)?
+                    .into_iter()
+                    .filter(|path| path.exists())
+                    .collect();
+                let locked = LockedTool {
+                    version: step.version.clone(),
+                    url: Some(release.url.clone()),
+                    sha256: release.sha256.clone(),
+                    integrity: release.integrity.clone(),
+                };
+                (binaries, locked)
+            }
+        };
+        let binaries: Vec<PathBuf> = binaries
+            .iter()
+            .filter_map(|path| path.strip_prefix(&staging).ok())
+            .map(|relative| step.install_path.join(relative))
+            .collect();
+        for binary in &binaries {
+            let shim = shim::stage_shim_in(
+                &mut transaction,
+                &mut index,
+                &System::bin_dir(),
+                &exe,
+                &tool.key(),
+                &step.install_path,
+                binary,
+            )?;
+            shims.push((shim, binary.clone()));
+        }
+        if let Some(lockfile) = &mut lockfile {
+            lockfile.insert(tool, locked);
+        }
+
+        results.push(InstallResult {
+            tool_name: step.name,
+            version: step.version,
+            install_path: step.install_path,
+            binary_path: binaries.into_iter().next(),
+        });
+    }
+
+    if !shims.is_empty() {
+        index.stage(&mut transaction, &index_path)?;
+    }
+    if let (Some(lockfile), Some(path)) = (&lockfile, &lock_path) {
+        lockfile.stage(&mut transaction, path)?;
+    }
+    transaction.commit()?;
+
+    for (shim, binary) in shims {
+        println!(
+            "Created shim: {} -> {}",
+            shim.path.display(),
+            binary.display()
+        );
+        if let Some(replaced) = shim.replaced {
+            println!(
+                "{} was {}'s shim; it now runs this install",
+                shim.path.display(),
+                replaced
+            );
+        }
+    }
+    Ok(results)
+}
+
+/// Stage steps in parallel, each into its staging directory.
+async fn stage_all(steps: Vec<(PlannedInstall, PathBuf)>) -> Result<()> {
+    let mut installs = JoinSet::new();
+    for (step, staging) in steps {
+        installs.spawn(async move {
+            let staged = stage(&step, &staging).await;
+            (step.name, staged)
+        });
+    }
+
+    while let Some(joined) = installs.join_next().await {
+        let (name, staged) = joined.context("Install task failed")?;
+        staged.with_context(|| format!("Failed to install {}", name))?;
+    }
+    Ok(())
+}
+
+pub async fn run(request: InstallRequest) -> Result<Vec<InstallResult>> {
+    let plan = plan(&request).await?;
+    execute(plan).await
+}
+
+/// Install `step` into its staging directory and leave a [`Receipt`] beside it,
+/// so the receipt is committed (or rolled back) together with the files.
+async fn stage(step: &PlannedInstall, staging: &Path) -> Result<()> {
+    let (backend, url, sha256, integrity) = match &step.source {
+        InstallSource::Bottle { bottle_file, .. } => {
+            let data = fetch_and_verify_bottle(&step.name, bottle_file).await?;
+            println!("Extracting {}...", step.name);
+            ArchiveExtractor::extract_tar_gz(&data, staging)
+                .await
+                .map_err(|e| anyhow::anyhow!("Failed to extract {}: {e}", step.name))?;
+            (
+                Backend::Homebrew,
+                Some(bottle_file.url.clone()),
+                Some(bottle_file.sha256.clone()),
+                None,
+            )
+        }
+        InstallSource::Release { registry, release } => {
+            registry.install(release, staging).await?;
+            let backend = step
+                .requested
+                .as_ref()
+                .map_or(Backend::Core, ToolSpec::effective_backend);
+            (
+                backend,
+                Some(release.url.clone()),
+                release.sha256.clone(),
+                release.integrity.clone(),
+            )
+        }
+    };
+
+    let installed_at = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    Receipt {
+        tool: step
+            .requested
+            .as_ref()
+            .map_or_else(|| step.name.clone(), ToolSpec::key),
+        backend: backend.as_str().to_string(),
+        version: step.version.clone(),
+        requested: step.requested.as_ref().map(|tool| tool.version.clone()),
+        url,
+        sha256,
+        integrity,
+        installed_at,
+    }
+    .save(staging)
 }
 
 /* ----------------------------- small helpers ----------------------------- */
 
+/// Executables of an installed release: its declared `bins`, or everything in
+/// `bin/` for registries that only learn the names once unpacked.
+fn release_bins(release: &Release, install_path: &Path) -> Result<Vec<PathBuf>> {
+    if !release.bins.is_empty() {
+        return Ok(release
+            .bins
+            .iter()
+            .map(|bin| install_path.join(bin))
+            .collect());
+    }
+
+    let bin_dir = install_path.join("bin");
+    if !bin_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut bins = std::fs::read_dir(&bin_dir)
+        .with_context(|| format!("Failed to read {}", bin_dir.display()))?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    bins.sort();
+    Ok(bins)
+}
+
 fn formula_json_path() -> PathBuf {
     System::cache_dir().join("still").join("formula.json")
 }
@@ -92,12 +493,9 @@ async fn load_formula_json_array(path: &Path) -> Result<Vec<serde_json::Value>>
     Ok(array.clone())
 }
 
-fn find_matching_formula(
-    formulas: &[serde_json::Value],
-    tool_name: &str,
-) -> Result<crate::specs::brew::FormulaSpec> {
+fn find_matching_formula(formulas: &[serde_json::Value], tool_name: &str) -> Result<FormulaSpec> {
     for v in formulas {
-        let Ok(f) = serde_json::from_value::<crate::specs::brew::FormulaSpec>(v.clone()) else {
+        let Ok(f) = serde_json::from_value::<FormulaSpec>(v.clone()) else {
             continue; // skip malformed formulas
         };
 
@@ -112,24 +510,76 @@ fn find_matching_formula(
     anyhow::bail!("No matching formula")
 }
 
-fn warn_if_version_mismatch(tool: &ToolSpec, formula: &crate::specs::brew::FormulaSpec) {
-    let version_matches = tool.version == "latest"
-        || tool.version == formula.versions.stable
-        || semver::Version::parse(&tool.version)
-            .and_then(|req_ver| {
-                semver::Version::parse(&formula.versions.stable).map(|form_ver| req_ver == form_ver)
-            })
-            .unwrap_or(false);
+/// Pick the formula that satisfies the requested version.
+///
+/// `node@20` first tries `node` itself; if its stable version is not a 20.x,
+/// the matching sibling from `versioned_formulae` (the `node@20` formula) is used.
+fn resolve_formula(formulas: &[serde_json::Value], tool: &ToolSpec) -> Result<FormulaSpec> {
+    let formula = find_matching_formula(formulas, &tool.name)
+        .with_context(|| format!("Formula '{}' not found in formula.json", tool.name))?;
 
-    if !version_matches && tool.version != "latest" {
-        println!(
-            "Warning: Requested version '{}' does not match formula version '{}'",
-            tool.version, formula.versions.stable
-        );
+    if tool.is_latest() || version_matches(&tool.version, &formula.versions.stable) {
+        return Ok(formula);
+    }
+
+    let mut available = vec![formula.versions.stable.clone()];
+    for versioned_name in &formula.versioned_formulae {
+        let Some((_, suffix)) = versioned_name.split_once('@') else {
+            continue;
+        };
+        available.push(suffix.to_string());
+
+        if !version_matches(&tool.version, suffix) && !version_matches(suffix, &tool.version) {
+            continue;
+        }
+
+        let Ok(versioned) = find_matching_formula(formulas, versioned_name) else {
+            continue;
+        };
+        if version_matches(&tool.version, &versioned.versions.stable) {
+            return Ok(versioned);
+        }
+        available.push(format!(
+            "{} ({})",
+            versioned.versions.stable, versioned.name
+        ));
     }
+
+    anyhow::bail!(
+        "No formula provides {}@{}. Available versions: {}",
+        tool.name,
+        tool.version,
+        available.join(", ")
+    )
+}
+
+/// Whether `requested` is a (possibly partial) prefix of `available`:
+/// `20` matches `20.11.1`, `3.12` matches `3.12.4`, `1.2.3` matches only itself.
+pub(crate) fn version_matches(requested: &str, available: &str) -> bool {
+    let requested = requested.strip_prefix('v').unwrap_or(requested);
+    let available = available.strip_prefix('v').unwrap_or(available);
+
+    let mut available_parts = available.split('.');
+    requested
+        .split('.')
+        .all(|part| available_parts.next() == Some(part))
 }
 
-fn build_bottle_info(formula: &crate::specs::brew::FormulaSpec) -> Result<BottleInfo> {
+/// Numeric sort key for dotted versions: `1.20` < `1.20.1` < `1.22.10`.
+/// Non-numeric suffixes (`1.23rc1`) only count their leading digits.
+pub(crate) fn version_key(version: &str) -> Vec<u64> {
+    version
+        .strip_prefix('v')
+        .unwrap_or(version)
+        .split('.')
+        .map(|part| {
+            let digits: String = part.chars().take_while(char::is_ascii_digit).collect();
+            digits.parse().unwrap_or(0)
+        })
+        .collect()
+}
+
+fn build_bottle_info(formula: &FormulaSpec) -> Result<BottleInfo> {
     let bottle = formula.bottle.clone().ok_or_else(|| {
         anyhow::anyhow!(
             "No bottle available for {}@{}",
@@ -145,55 +595,9 @@ fn build_bottle_info(formula: &crate::specs::brew::FormulaSpec) -> Result<Bottle
     })
 }
 
-fn compute_install_path(formula_name: &str, version: &str) -> PathBuf {
-    System::tool_dir().join(formula_name).join(version)
-}
-
-async fn reinstall_to_path(bottle_data: &[u8], install_path: &Path) -> Result<()> {
-    if install_path.exists() {
-        println!("Removing existing installation...");
-        tokio::fs::remove_dir_all(install_path)
-            .await
-            .with_context(|| {
-                format!(
-                    "Failed to remove existing installation at {}",
-                    install_path.display()
-                )
-            })?;
-    }
-
-    println!("Extracting to {}...", install_path.display());
-    ArchiveExtractor::extract_tar_gz(bottle_data, install_path)
-        .await
-        .map_err(|e| anyhow::anyhow!("Failed to extract bottle: {e}"))?;
-
-    Ok(())
-}
-
-async fn maybe_link_binary(binary_path: &Option<PathBuf>) -> Result<()> {
-    let Some(binary) = binary_path.as_ref() else {
-        return Ok(());
-    };
-
-    let system_bin_dir = System::bin_dir();
-    tokio::fs::create_dir_all(&system_bin_dir).await?;
-    let symlink_path =
-        system_bin_dir.join(binary.file_name().ok_or_else(|| {
-            anyhow::anyhow!("Binary path has no file name: {}", binary.display())
-        })?);
-
-    if symlink_path.exists() || symlink_path.is_symlink() {
-        let _ = tokio::fs::remove_file(&symlink_path).await;
-    }
-
-    System::create_symlink(binary, &symlink_path).context("failed to create symlink to bin")?;
-
-    println!(
-        "Created symlink: {} -> {}",
-        symlink_path.display(),
-        binary.display()
-    );
-    Ok(())
+/// `tool_dir/<dir>/<version>`, with `dir` a formula name or an [`install_dir_name`].
+fn compute_install_path(dir: &str, version: &str) -> PathBuf {
+    System::tool_dir().join(dir).join(version)
 }
 
 /* -------------------------- network + verification -------------------------- */
@@ -202,16 +606,15 @@ async fn fetch_and_verify_bottle(
     formula_name: &str,
     bottle_file: &BottleFileSpec,
 ) -> Result<Vec<u8>> {
-    println!("Downloading bottle...");
+    println!("Downloading {}...", formula_name);
     let token = get_ghcr_token(formula_name).await?;
     let bottle_data = download_bottle(&bottle_file.url, &token)
         .await
         .context("Failed to download bottle")?;
 
-    println!("Verifying checksum...");
     Hashing::verify_sha256(&bottle_data, &bottle_file.sha256)
         .map_err(|e| anyhow::anyhow!("Checksum verification failed: {e}"))?;
-    println!("Checksum verified");
+    println!("Checksum verified for {}", formula_name);
 
     Ok(bottle_data)
 }
@@ -220,7 +623,7 @@ async fn fetch_and_verify_bottle(
 async fn get_ghcr_token(formula_name: &str) -> Result<String> {
     let token_url = format!(
         "https://ghcr.io/token?scope=repository:homebrew/core/{}:pull",
-        formula_name
+        ghcr_repository(formula_name)
     );
 
     let client = NetUtils::client();
@@ -268,6 +671,147 @@ async fn download_bottle(url: &str, token: &str) -> Result<Vec<u8>> {
     Ok(bytes.to_vec())
 }
 
+/// GHCR repository name for a formula: `node@20` lives at `homebrew/core/node/20`.
+fn ghcr_repository(formula_name: &str) -> String {
+    formula_name.replace('@', "/").replace('+', "x")
+}
+
+/// Fill in download and installed sizes from each bottle's OCI manifest.
+/// Sizes are informational, so lookups that fail leave them unknown.
+async fn fill_bottle_sizes(steps: &mut [PlannedInstall]) {
+    let mut lookups = JoinSet::new();
+    for (idx, step) in steps.iter().enumerate() {
+        let InstallSource::Bottle {
+            formula,
+            bottle_file,
+        } = step.source.clone()
+        else {
+            continue;
+        };
+        lookups.spawn(async move { (idx, fetch_bottle_sizes(&formula, &bottle_file).await) });
+    }
+
+    while let Some(joined) = lookups.join_next().await {
+        let Ok((idx, Ok((download_size, installed_size)))) = joined else {
+            continue;
+        };
+        steps[idx].download_size = download_size;
+        steps[idx].installed_size = installed_size;
+    }
+}
+
+/// Fill in the download sizes of releases whose registry does not publish them
+/// from the `Content-Length` of their download. Lookups that fail leave them unknown.
+async fn fill_release_sizes(steps: &mut [PlannedInstall]) {
+    let mut lookups = JoinSet::new();
+    for (idx, step) in steps.iter().enumerate() {
+        let InstallSource::Release { registry, release } = &step.source else {
+            continue;
+        };
+        // Their `url` is not all there is to download: a channel manifest, a
+        // package without its dependencies, a plugin repository.
+        let partial = matches!(
+            registry,
+            Registry::Rust(_) | Registry::Npm(_) | Registry::Asdf(_)
+        );
+        if partial || step.download_size.is_some() || !release.url.starts_with("http") {
+            continue;
+        }
+        let url = release.url.clone();
+        lookups.spawn(async move { (idx, fetch_content_length(&url).await) });
+    }
+
+    while let Some(joined) = lookups.join_next().await {
+        let Ok((idx, Some(size))) = joined else {
+            continue;
+        };
+        steps[idx].download_size = Some(size);
+    }
+}
+
+async fn fetch_content_length(url: &str) -> Option<u64> {
+    let response = NetUtils::client().head(url).send().await.ok()?;
+    if !response.status().is_success() {
+        return None;
+    }
+    response
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Read `sh.brew.bottle.size` and `sh.brew.bottle.installed_size` from the
+/// manifest entry whose digest matches the selected bottle.
+async fn fetch_bottle_sizes(
+    formula: &FormulaSpec,
+    bottle_file: &BottleFileSpec,
+) -> Result<(Option<u64>, Option<u64>)> {
+    let (repository_url, _) = bottle_file
+        .url
+        .split_once("/blobs/")
+        .ok_or_else(|| anyhow::anyhow!("Unexpected bottle URL: {}", bottle_file.url))?;
+
+    let mut tag = formula.versions.stable.clone();
+    if formula.revision > 0 {
+        tag = format!("{}_{}", tag, formula.revision);
+    }
+    let rebuild = formula.bottle.as_ref().map_or(0, |b| b.stable.rebuild);
+    if rebuild > 0 {
+        tag = format!("{}-{}", tag, rebuild);
+    }
+
+    let token = get_ghcr_token(&formula.name).await?;
+    let response = NetUtils::client()
+        .get(format!("{}/manifests/{}", repository_url, tag))
+        .header("Authorization", format!("Bearer {}", token))
+        .header("Accept", "application/vnd.oci.image.index.v1+json")
+        .send()
+        .await
+        .context("Failed to request bottle manifest")?;
+
+    if !response.status().is_success() {
+        anyhow::bail!("Failed to get bottle manifest: HTTP {}", response.status());
+    }
+
+    let manifest: serde_json::Value = response
+        .json()
+        .await
+        .context("Failed to parse bottle manifest")?;
+
+    Ok(bottle_sizes_from_manifest(&manifest, &bottle_file.sha256))
+}
+
+fn bottle_sizes_from_manifest(
+    manifest: &serde_json::Value,
+    sha256: &str,
+) -> (Option<u64>, Option<u64>) {
+    let annotations = manifest
+        .get("manifests")
+        .and_then(|m| m.as_array())
+        .into_iter()
+        .flatten()
+        .filter_map(|m| m.get("annotations"))
+        .find(|a| a.get("sh.brew.bottle.digest").and_then(|d| d.as_str()) == Some(sha256));
+
+    let Some(annotations) = annotations else {
+        return (None, None);
+    };
+
+    let size = |key: &str| {
+        annotations
+            .get(key)
+            .and_then(|v| v.as_str())
+            .and_then(|v| v.parse::<u64>().ok())
+    };
+    (
+        size("sh.brew.bottle.size"),
+        size("sh.brew.bottle.installed_size"),
+    )
+}
+
 /// Information about a Homebrew bottle
 #[derive(Debug, Clone)]
 pub struct BottleInfo {
@@ -373,3 +917,143 @@ impl InstallOps for MacOS {
         Ok(None)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::actions::env::find_installed;
+    use crate::actions::uninstall::installed_versions_in;
+    use crate::utils::scratch::Scratch;
+
+    #[test]
+    fn installs_backends_of_one_name_side_by_side() {
+        let tool_dir = Scratch::new("install-backends");
+        let (brew, cargo): (ToolSpec, ToolSpec) = (
+            "ripgrep@14.1.1".parse().unwrap(),
+            "cargo:ripgrep@14.1.1".parse().unwrap(),
+        );
+        let path = |tool: &ToolSpec| tool_dir.join(install_dir_name(&tool.key())).join("14.1.1");
+
+        // Both in one plan: each gets a staging directory of its own.
+        let mut transaction = FsTransaction::new();
+        for (tool, content) in [(&brew, "brew"), (&cargo, "cargo")] {
+            let staging = transaction.stage_install(&path(tool)).unwrap();
+            std::fs::create_dir_all(staging.join("bin")).unwrap();
+            std::fs::write(staging.join("bin/rg"), content).unwrap();
+        }
+        transaction.commit().unwrap();
+
+        for (tool, content) in [(&brew, "brew"), (&cargo, "cargo")] {
+            let (_, install) =
+                find_installed(&tool_dir, &install_dir_name(&tool.key()), "14").unwrap();
+            assert_eq!(install, path(tool));
+            assert_eq!(
+                std::fs::read_to_string(install.join("bin/rg")).unwrap(),
+                content
+            );
+
+            // Uninstalling one leaves the other alone.
+            let installed = installed_versions_in(&tool_dir, tool).unwrap();
+            let paths: Vec<&PathBuf> = installed.iter().map(|(_, _, path)| path).collect();
+            assert_eq!(paths, [&path(tool)]);
+        }
+    }
+
+    fn formula(name: &str, stable: &str, versioned: &[&str]) -> serde_json::Value {
+        serde_json::json!({
+            "name": name,
+            "full_name": name,
+            "tap": "homebrew/core",
+            "versioned_formulae": versioned,
+            "versions": { "stable": stable },
+            "urls": { "stable": { "url": format!("https://example.com/{name}.tar.gz") } },
+        })
+    }
+
+    fn formulas() -> Vec<serde_json::Value> {
+        vec![
+            formula("node", "23.4.0", &["node@22", "node@20", "node@18"]),
+            formula("node@22", "22.12.0", &[]),
+            formula("node@20", "20.18.1", &[]),
+            formula("python@3.13", "3.13.1", &["python@3.12", "python@3.11"]),
+            formula("python@3.12", "3.12.8", &[]),
+        ]
+    }
+
+    fn resolve(spec: &str) -> Result<String> {
+        let tool: ToolSpec = spec.parse()?;
+        resolve_formula(&formulas(), &tool).map(|f| f.name)
+    }
+
+    #[test]
+    fn reads_sizes_for_the_selected_bottle() {
+        let manifest = serde_json::json!({
+            "manifests": [
+                { "annotations": {
+                    "sh.brew.bottle.digest": "aaa",
+                    "sh.brew.bottle.size": "100",
+                    "sh.brew.bottle.installed_size": "300",
+                } },
+                { "annotations": {
+                    "sh.brew.bottle.digest": "bbb",
+                    "sh.brew.bottle.size": "200",
+                } },
+            ]
+        });
+
+        assert_eq!(
+            bottle_sizes_from_manifest(&manifest, "aaa"),
+            (Some(100), Some(300))
+        );
+        assert_eq!(
+            bottle_sizes_from_manifest(&manifest, "bbb"),
+            (Some(200), None)
+        );
+        assert_eq!(bottle_sizes_from_manifest(&manifest, "ccc"), (None, None));
+        assert_eq!(ghcr_repository("node@20"), "node/20");
+    }
+
+    #[test]
+    fn reads_release_sizes_from_their_downloads() {
+        let base = crate::utils::net::fixture::serve(vec![("/tool.tar.gz".into(), vec![0; 1234])]);
+        crate::utils::net::fixture::block_on(async {
+            assert_eq!(
+                fetch_content_length(&format!("{}/tool.tar.gz", base)).await,
+                Some(1234)
+            );
+            assert_eq!(
+                fetch_content_length(&format!("{}/missing", base)).await,
+                None
+            );
+        });
+    }
+
+    #[test]
+    fn version_prefixes() {
+        assert!(version_matches("20", "20.18.1"));
+        assert!(version_matches("3.12", "3.12.8"));
+        assert!(version_matches("v1.2.3", "1.2.3"));
+        assert!(!version_matches("2", "20.18.1"));
+        assert!(!version_matches("3.1", "3.12.8"));
+    }
+
+    #[test]
+    fn resolves_versioned_formulae() {
+        assert_eq!(resolve("node").unwrap(), "node");
+        assert_eq!(resolve("node@23").unwrap(), "node");
+        assert_eq!(resolve("node@20").unwrap(), "node@20");
+        assert_eq!(resolve("node@22.12").unwrap(), "node@22");
+    }
+
+    #[test]
+    fn lists_available_versions_instead_of_installing_the_wrong_one() {
+        let err = resolve("node@16").unwrap_err().to_string();
+        assert!(err.contains("23.4.0"), "{err}");
+        assert!(err.contains("20"), "{err}");
+
+        // node@18 is listed but missing from the formula cache.
+        assert!(resolve("node@18").is_err());
+        // node@20 exists, but not at this patch version.
+        assert!(resolve("node@20.1.0").is_err());
+    }
+}
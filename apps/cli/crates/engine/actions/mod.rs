@@ -1,2 +1,12 @@
+pub mod activate;
+pub mod env;
+pub mod init;
 pub mod install;
+pub mod preset;
+pub mod run;
+pub mod service;
+pub mod shim;
+pub mod task;
 pub mod uninstall;
+pub mod r#use;
+pub mod watch;
@@ -0,0 +1,397 @@
+use crate::actions::env::find_installed;
+use crate::actions::install::version_key;
+use crate::specs::tool::ToolSpec;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// Where a preset records the ports it picked, next to its `data` directory.
+pub const PORTS_FILE: &str = "ports.toml";
+
+/// A built-in service definition for a common development dependency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    Postgresql,
+    Redis,
+    Mysql,
+    Minio,
+}
+
+impl Preset {
+    pub fn all() -> &'static [Preset] {
+        &[
+            Preset::Postgresql,
+            Preset::Redis,
+            Preset::Mysql,
+            Preset::Minio,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Preset::Postgresql => "postgresql",
+            Preset::Redis => "redis",
+            Preset::Mysql => "mysql",
+            Preset::Minio => "minio",
+        }
+    }
+
+    /// The Homebrew formula that provides it.
+    pub fn formula(&self) -> &'static str {
+        self.as_str()
+    }
+
+    /// Used without a `version`. Homebrew only has versioned PostgreSQL formulae.
+    pub fn default_version(&self) -> &'static str {
+        match self {
+            Preset::Postgresql => "17",
+            _ => "latest",
+        }
+    }
+
+    /// The program whose `bin` directory goes on `PATH`.
+    fn program(&self) -> &'static str {
+        match self {
+            Preset::Postgresql => "postgres",
+            Preset::Redis => "redis-server",
+            Preset::Mysql => "mysqld",
+            Preset::Minio => "minio",
+        }
+    }
+
+    /// Variables holding the ports it listens on, with the port tried first.
+    pub fn ports(&self) -> &'static [(&'static str, u16)] {
+        match self {
+            Preset::Postgresql => &[("PGPORT", 5432)],
+            Preset::Redis => &[("REDIS_PORT", 6379)],
+            Preset::Mysql => &[("MYSQL_TCP_PORT", 3306)],
+            Preset::Minio => &[("MINIO_PORT", 9000), ("MINIO_CONSOLE_PORT", 9001)],
+        }
+    }
+
+    /// Initializes `$STILL_SERVICE_DATA` on first use, then runs the server in
+    /// the foreground on `127.0.0.1`.
+    pub fn start(&self) -> &'static str {
+        match self {
+            Preset::Postgresql => {
+                r#"[ -f "$STILL_SERVICE_DATA/PG_VERSION" ] || initdb -D "$STILL_SERVICE_DATA" -U postgres --auth=trust -E UTF8
+exec postgres -D "$STILL_SERVICE_DATA" -h 127.0.0.1 -p "$PGPORT" -k ''"#
+            }
+            Preset::Redis => {
+                r#"mkdir -p "$STILL_SERVICE_DATA"
+exec redis-server --bind 127.0.0.1 --port "$REDIS_PORT" --dir "$STILL_SERVICE_DATA""#
+            }
+            Preset::Mysql => {
+                r#"[ -d "$STILL_SERVICE_DATA/mysql" ] || mysqld --no-defaults --initialize-insecure --datadir="$STILL_SERVICE_DATA" --user="$(id -un)"
+exec mysqld --no-defaults --datadir="$STILL_SERVICE_DATA" --user="$(id -un)" --bind-address=127.0.0.1 --port="$MYSQL_TCP_PORT" --socket="$STILL_SERVICE_DATA/mysqld.sock" --mysqlx=OFF"#
+            }
+            Preset::Minio => {
+                r#"mkdir -p "$STILL_SERVICE_DATA"
+exec minio server "$STILL_SERVICE_DATA" --address "127.0.0.1:$MINIO_PORT" --console-address "127.0.0.1:$MINIO_CONSOLE_PORT""#
+            }
+        }
+    }
+
+    /// Passes once it accepts connections. MinIO ships no client, so its check
+    /// asks the health endpoint with the system's `curl`.
+    pub fn check(&self) -> &'static str {
+        match self {
+            Preset::Postgresql => r#"pg_isready -q -h 127.0.0.1 -p "$PGPORT""#,
+            Preset::Redis => r#"redis-cli -h 127.0.0.1 -p "$REDIS_PORT" ping | grep -q PONG"#,
+            Preset::Mysql => {
+                r#"mysqladmin --no-defaults -h 127.0.0.1 -P "$MYSQL_TCP_PORT" -u root ping"#
+            }
+            Preset::Minio => {
+                r#"curl -sf "http://127.0.0.1:$MINIO_PORT/minio/health/live" >/dev/null"#
+            }
+        }
+    }
+
+    /// Shuts down the server of `$STILL_SERVICE_DATA`, also one Still is not
+    /// supervising. MinIO has no command for it, so it is signalled by name.
+    pub fn stop(&self) -> &'static str {
+        match self {
+            Preset::Postgresql => r#"pg_ctl -D "$STILL_SERVICE_DATA" stop -m fast"#,
+            Preset::Redis => r#"redis-cli -h 127.0.0.1 -p "$REDIS_PORT" shutdown"#,
+            Preset::Mysql => {
+                r#"mysqladmin --no-defaults -h 127.0.0.1 -P "$MYSQL_TCP_PORT" -u root shutdown"#
+            }
+            Preset::Minio => r#"pkill -TERM -f "minio server $STILL_SERVICE_DATA ""#,
+        }
+    }
+
+    /// How clients reach it: the port variables plus the usual connection settings.
+    fn connection(&self, ports: &BTreeMap<String, u16>) -> Vec<(&'static str, String)> {
+        let port = |name: &str| ports.get(name).copied().unwrap_or_default();
+        match self {
+            Preset::Postgresql => vec![
+                ("PGHOST", "127.0.0.1".to_string()),
+                ("PGUSER", "postgres".to_string()),
+                (
+                    "DATABASE_URL",
+                    format!("postgres://postgres@127.0.0.1:{}/postgres", port("PGPORT")),
+                ),
+            ],
+            Preset::Redis => vec![
+                ("REDIS_HOST", "127.0.0.1".to_string()),
+                (
+                    "REDIS_URL",
+                    format!("redis://127.0.0.1:{}", port("REDIS_PORT")),
+                ),
+            ],
+            Preset::Mysql => vec![
+                ("MYSQL_HOST", "127.0.0.1".to_string()),
+                (
+                    "DATABASE_URL",
+                    format!("mysql://root@127.0.0.1:{}/mysql", port("MYSQL_TCP_PORT")),
+                ),
+            ],
+            Preset::Minio => vec![
+                ("MINIO_ROOT_USER", "minioadmin".to_string()),
+                ("MINIO_ROOT_PASSWORD", "minioadmin".to_string()),
+                ("AWS_ACCESS_KEY_ID", "minioadmin".to_string()),
+                ("AWS_SECRET_ACCESS_KEY", "minioadmin".to_string()),
+                (
+                    "AWS_ENDPOINT_URL",
+                    format!("http://127.0.0.1:{}", port("MINIO_PORT")),
+                ),
+            ],
+        }
+    }
+}
+
+impl FromStr for Preset {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "postgresql" | "postgres" => Ok(Preset::Postgresql),
+            "redis" => Ok(Preset::Redis),
+            "mysql" => Ok(Preset::Mysql),
+            "minio" => Ok(Preset::Minio),
+            other => {
+                let known: Vec<&str> = Preset::all().iter().map(|p| p.as_str()).collect();
+                anyhow::bail!(
+                    "unknown service preset \"{}\" (expected one of: {})",
+                    other,
+                    known.join(", ")
+                )
+            }
+        }
+    }
+}
+
+/// A preset used by one service: its package version, the directory holding its
+/// `data` and [`PORTS_FILE`], and the ports it listens on once it has started.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PresetService {
+    pub preset: Preset,
+    pub version: String,
+    pub dir: PathBuf,
+    pub ports: BTreeMap<String, u16>,
+}
+
+impl PresetService {
+    /// With the ports recorded in `dir`; none before its first start.
+    pub fn new(preset: Preset, version: Option<&str>, dir: PathBuf) -> Self {
+        let recorded: BTreeMap<String, u16> = std::fs::read_to_string(dir.join(PORTS_FILE))
+            .ok()
+            .and_then(|content| toml::from_str(&content).ok())
+            .unwrap_or_default();
+        let ports = preset
+            .ports()
+            .iter()
+            .filter_map(|(name, _)| Some((name.to_string(), *recorded.get(*name)?)))
+            .collect();
+        Self {
+            preset,
+            version: version.unwrap_or(preset.default_version()).to_string(),
+            dir,
+            ports,
+        }
+    }
+
+    /// The Homebrew package to install (`brew:postgresql@16`).
+    pub fn package(&self) -> Result<ToolSpec> {
+        format!("brew:{}@{}", self.preset.formula(), self.version).parse()
+    }
+
+    pub fn data_dir(&self) -> PathBuf {
+        self.dir.join("data")
+    }
+
+    /// Whether every port is recorded, which [`Self::init`] does on first start.
+    pub fn initialized(&self) -> bool {
+        self.preset
+            .ports()
+            .iter()
+            .all(|(name, _)| self.ports.contains_key(*name))
+    }
+
+    /// Variables for the project: the ports and connection settings.
+    pub fn exports(&self) -> BTreeMap<String, String> {
+        let mut vars: BTreeMap<String, String> = self
+            .ports
+            .iter()
+            .map(|(name, port)| (name.clone(), port.to_string()))
+            .collect();
+        for (name, value) in self.preset.connection(&self.ports) {
+            vars.insert(name.to_string(), value);
+        }
+        vars
+    }
+
+    /// [`Self::exports`] plus `STILL_SERVICE_DATA`, for the preset's own commands.
+    pub fn vars(&self) -> BTreeMap<String, String> {
+        let mut vars = self.exports();
+        vars.insert(
+            "STILL_SERVICE_DATA".to_string(),
+            self.data_dir().to_string_lossy().to_string(),
+        );
+        vars
+    }
+
+    /// Pick free ports for those not recorded yet, create the directory and record
+    /// them, so they stay the same from now on. The data directory itself is left
+    /// to the start command.
+    pub fn init(&mut self) -> Result<()> {
+        for (name, default) in self.preset.ports() {
+            if !self.ports.contains_key(*name) {
+                let port = free_port(*default, self.ports.values());
+                self.ports.insert(name.to_string(), port);
+            }
+        }
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("Failed to create {}", self.dir.display()))?;
+        let content = toml::to_string(&self.ports).context("Failed to serialize ports")?;
+        let path = self.dir.join(PORTS_FILE);
+        std::fs::write(&path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// The `bin` directory of the installed package in `tool_dir`, if any. Bottles
+    /// install under the name of the formula that matched, such as `postgresql@16`.
+    pub fn bin_dir(&self, tool_dir: &Path) -> Option<PathBuf> {
+        let formula = self.preset.formula();
+        let versioned = format!("{}@", formula);
+        let entries = std::fs::read_dir(tool_dir).ok()?;
+        entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .filter(|name| name == formula || name.starts_with(&versioned))
+            .filter_map(|name| find_installed(tool_dir, &name, &self.version))
+            .max_by_key(|(version, _)| version_key(version))
+            .and_then(|(_, path)| find_bin(&path, self.preset.program()))
+    }
+}
+
+/// `preferred` when nothing listens on it and it is not `taken`, otherwise one
+/// the system hands out.
+fn free_port<'a>(preferred: u16, taken: impl Iterator<Item = &'a u16> + Clone) -> u16 {
+    let bind = |port: u16| {
+        TcpListener::bind(("127.0.0.1", port))
+            .and_then(|listener| listener.local_addr())
+            .map(|addr| addr.port())
+            .ok()
+    };
+    let preferred = Some(preferred).filter(|port| !taken.clone().any(|taken| taken == port));
+    if let Some(port) = preferred.and_then(bind) {
+        return port;
+    }
+    loop {
+        if let Some(port) = bind(0).filter(|port| !taken.clone().any(|taken| taken == port)) {
+            return port;
+        }
+    }
+}
+
+/// The `bin` directory under `install_path` that holds `program`; bottles nest
+/// it a level or two down.
+fn find_bin(install_path: &Path, program: &str) -> Option<PathBuf> {
+    let mut dirs = vec![(install_path.to_path_buf(), 0)];
+    while let Some((dir, depth)) = dirs.pop() {
+        let bin = dir.join("bin");
+        if bin.join(program).is_file() {
+            return Some(bin);
+        }
+        if depth == 3 {
+            continue;
+        }
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|e| e.ok()) {
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                dirs.push((entry.path(), depth + 1));
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch::Scratch;
+
+    #[test]
+    fn keeps_the_ports_it_picked() {
+        let root = Scratch::new("preset");
+
+        // Something already listens on the port MinIO tries first.
+        let busy = TcpListener::bind(("127.0.0.1", 0)).unwrap();
+        let port = busy.local_addr().unwrap().port();
+        assert_ne!(free_port(port, [].iter()), port);
+
+        let mut service = PresetService::new(Preset::Minio, None, root.join("minio"));
+        assert_eq!(service.package().unwrap().to_string(), "minio@latest");
+        // Loading picks no ports; the first start does.
+        assert!(service.ports.is_empty() && !service.initialized());
+        service.init().unwrap();
+        let (api, console) = (
+            service.ports["MINIO_PORT"],
+            service.ports["MINIO_CONSOLE_PORT"],
+        );
+        assert_ne!(api, console);
+        let exports = service.exports();
+        assert_eq!(
+            exports["AWS_ENDPOINT_URL"],
+            format!("http://127.0.0.1:{}", api)
+        );
+        assert!(!exports.contains_key("STILL_SERVICE_DATA"));
+
+        let again = PresetService::new(Preset::Minio, None, root.join("minio"));
+        assert_eq!(again.ports, service.ports);
+    }
+
+    #[test]
+    fn finds_the_bin_directory_of_versioned_formulae() {
+        let tool_dir = Scratch::new("preset-tools");
+        for (install, program) in [
+            ("postgresql@16/16.4/16.4/bin", "postgres"),
+            ("postgresql@17/17.2/17.2/bin", "postgres"),
+            ("redis/7.4.1/bin", "redis-cli"),
+        ] {
+            std::fs::create_dir_all(tool_dir.join(install)).unwrap();
+            std::fs::write(tool_dir.join(install).join(program), "").unwrap();
+        }
+
+        let postgres = |version| {
+            PresetService::new(Preset::Postgresql, version, tool_dir.join("pg")).bin_dir(&tool_dir)
+        };
+        assert_eq!(
+            postgres(Some("16")),
+            Some(tool_dir.join("postgresql@16/16.4/16.4/bin"))
+        );
+        assert_eq!(
+            postgres(None),
+            Some(tool_dir.join("postgresql@17/17.2/17.2/bin"))
+        );
+        assert_eq!(postgres(Some("15")), None);
+        // Installed, but without the server.
+        let redis = PresetService::new(Preset::Redis, None, tool_dir.join("redis"));
+        assert_eq!(redis.bin_dir(&tool_dir), None);
+    }
+}
@@ -0,0 +1,179 @@
+use crate::actions::env::{project_env_in, tool_env_in};
+use crate::actions::install::{self, InstallRequest};
+use crate::specs::config::LayeredConfig;
+use crate::specs::tool::ToolSpec;
+use crate::system::System;
+use crate::utils::paths::PathOps;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::path::Path;
+
+pub struct RunRequest {
+    /// The program and its arguments.
+    pub command: Vec<String>,
+    /// Versions used for this run only (`--tool node@18`); the manifest is untouched.
+    pub tools: Vec<ToolSpec>,
+}
+
+/// The program `still run` execs and its complete environment.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunCommand {
+    pub program: String,
+    pub args: Vec<String>,
+    pub env: BTreeMap<String, String>,
+}
+
+/// Put the `--tool` overrides of `request` on top of `config`.
+pub fn apply_overrides(request: &RunRequest, config: &mut LayeredConfig) {
+    for tool in &request.tools {
+        config.override_tool(tool);
+    }
+}
+
+/// Pinned tools with no matching install, for `settings.auto_install`.
+pub fn missing(config: &LayeredConfig) -> Vec<ToolSpec> {
+    missing_in(config, &System::tool_dir())
+}
+
+pub fn missing_in(config: &LayeredConfig, tool_dir: &Path) -> Vec<ToolSpec> {
+    tool_env_in(config, tool_dir)
+        .missing
+        .iter()
+        .filter_map(|tool| tool.parse().ok())
+        .collect()
+}
+
+/// Install the [`missing`] tools when `settings.auto_install` is on.
+pub async fn auto_install(config: &LayeredConfig) -> Result<()> {
+    let missing = missing(config);
+    if missing.is_empty() || config.get_bool("settings.auto_install") != Some(true) {
+        return Ok(());
+    }
+    let request = InstallRequest {
+        tools: missing,
+        locked: false,
+    };
+    let plan = install::plan(&request).await?;
+    install::execute(plan).await?;
+    Ok(())
+}
+
+pub fn prepare(request: &RunRequest, config: &LayeredConfig) -> Result<RunCommand> {
+    prepare_in(request, config, &System::tool_dir(), &base_env())
+}
+
+/// `request` in the environment of the project (tools, `[env]`, `PATH`) on top of `base`.
+pub fn prepare_in(
+    request: &RunRequest,
+    config: &LayeredConfig,
+    tool_dir: &Path,
+    base: &BTreeMap<String, String>,
+) -> Result<RunCommand> {
+    let (program, args) = request
+        .command
+        .split_first()
+        .ok_or_else(|| anyhow::anyhow!("No command given"))?;
+
+    Ok(RunCommand {
+        program: program.clone(),
+        args: args.to_vec(),
+        env: environment_in(config, tool_dir, base)?,
+    })
+}
+
+/// The complete environment commands of the project run in, for `still run` and
+/// tasks. Every pinned tool has to be installed.
+pub fn environment(config: &LayeredConfig) -> Result<BTreeMap<String, String>> {
+    environment_in(config, &System::tool_dir(), &base_env())
+}
+
+fn base_env() -> BTreeMap<String, String> {
+    std::env::vars().collect()
+}
+
+pub fn environment_in(
+    config: &LayeredConfig,
+    tool_dir: &Path,
+    base: &BTreeMap<String, String>,
+) -> Result<BTreeMap<String, String>> {
+    let project = project_env_in(config, tool_dir, base)?;
+    if !project.missing.is_empty() {
+        anyhow::bail!(
+            "{} not installed; run `still install {}` or set settings.auto_install",
+            project.missing.join(", "),
+            project.missing.join(" ")
+        );
+    }
+    Ok(project.apply(base))
+}
+
+/// Replace the current process with `command`. The program is looked up on the
+/// composed `PATH`; as it takes over the process, signals reach it directly and
+/// its exit status is the one `still run` exits with.
+pub fn exec(command: &RunCommand) -> Result<std::convert::Infallible> {
+    use std::os::unix::process::CommandExt;
+
+    let error = std::process::Command::new(&command.program)
+        .args(&command.args)
+        .env_clear()
+        .envs(&command.env)
+        .exec();
+    Err(error).with_context(|| format!("Failed to run {}", command.program))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::specs::config::ConfigSources;
+    use crate::utils::scratch::Scratch;
+
+    #[test]
+    fn overrides_versions_for_one_run() {
+        let root = Scratch::new("run");
+        let tool_dir = root.join("tools");
+        for install in ["node/18.20.4", "node/20.18.1"] {
+            std::fs::create_dir_all(tool_dir.join(install).join("bin")).unwrap();
+        }
+        let project = root.join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(
+            project.join("still.toml"),
+            "[tools]\nnode = \"20\"\n\n[env]\nNODE_ENV = \"test\"\n",
+        )
+        .unwrap();
+
+        let mut config = LayeredConfig::load_from(&ConfigSources {
+            global_file: None,
+            cwd: project.clone(),
+            env: Vec::new(),
+        })
+        .unwrap();
+        let request = RunRequest {
+            command: vec!["node".into(), "script.js".into()],
+            tools: vec!["node@18".parse().unwrap(), "go@1.22".parse().unwrap()],
+        };
+        apply_overrides(&request, &mut config);
+        assert_eq!(
+            config.tool_versions()["node"].origin.to_string(),
+            "--tool node@18"
+        );
+        assert_eq!(
+            missing_in(&config, &tool_dir),
+            vec!["go@1.22".parse().unwrap()]
+        );
+
+        let base = BTreeMap::from([("PATH".to_string(), "/usr/bin".to_string())]);
+        let err = prepare_in(&request, &config, &tool_dir, &base).unwrap_err();
+        assert!(err.to_string().contains("go@1.22"), "{err}");
+
+        std::fs::create_dir_all(tool_dir.join("go/1.22.3/bin")).unwrap();
+        let command = prepare_in(&request, &config, &tool_dir, &base).unwrap();
+        assert_eq!(command.program, "node");
+        assert_eq!(command.args, vec!["script.js".to_string()]);
+        assert_eq!(command.env["NODE_ENV"], "test");
+        let path = &command.env["PATH"];
+        assert!(path.contains(&*tool_dir.join("node/18.20.4/bin").to_string_lossy()));
+        assert!(!path.contains("20.18.1"), "{path}");
+        assert!(path.ends_with("/usr/bin"));
+    }
+}
@@ -0,0 +1,1034 @@
+use crate::actions::install::{self, InstallRequest};
+use crate::actions::preset::{Preset, PresetService};
+use crate::actions::task::TaskGraph;
+use crate::actions::watch::{POLL_INTERVAL, ProcessGroup, STOP_GRACE};
+use crate::specs::toml::{LOCAL_MANIFEST_NAME, MANIFEST_NAME, Manifest, ServiceAction, project_id};
+use crate::specs::tool::ToolSpec;
+use crate::system::System;
+use crate::utils::lock::FileLock;
+use crate::utils::paths::PathOps;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::File;
+use std::io::{Seek, Write};
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// How long a `check` may run before it counts as failed.
+pub const CHECK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How long a `stop` command may run.
+pub const STOP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// How long `still service start` waits for a service to become healthy, and a
+/// start command that launched a daemon waits for its `check` to pass.
+pub const READY_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// How long a service without `check` has to stay up to count as started.
+pub const READY_GRACE: Duration = Duration::from_secs(1);
+
+/// A service's `start`, `stop` or `check`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+    /// Run through `sh -ec` in the project root.
+    Shell(String),
+    /// `still task <name>`.
+    Task(String),
+}
+
+impl Action {
+    fn from_manifest(action: &ServiceAction) -> Self {
+        match action {
+            ServiceAction::Shell(command) | ServiceAction::Command { command } => {
+                Action::Shell(command.clone())
+            }
+            ServiceAction::Task { task } => Action::Task(task.clone()),
+        }
+    }
+
+    fn command(&self, root: &Path, env: &BTreeMap<String, String>) -> Result<Command> {
+        let mut command = match self {
+            Action::Shell(script) => {
+                let mut command = Command::new("sh");
+                command.arg("-ec").arg(script);
+                command
+            }
+            Action::Task(task) => {
+                let exe =
+                    std::env::current_exe().context("Failed to locate the still executable")?;
+                let mut command = Command::new(exe);
+                command.arg("task").arg(task);
+                command
+            }
+        };
+        command
+            .current_dir(root)
+            .env_clear()
+            .envs(env)
+            .stdin(Stdio::null());
+        Ok(command)
+    }
+}
+
+impl std::fmt::Display for Action {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Action::Shell(script) => match script.split_once('\n') {
+                Some((first, _)) => write!(f, "{} ...", first),
+                None => write!(f, "{}", script),
+            },
+            Action::Task(task) => write!(f, "task {}", task),
+        }
+    }
+}
+
+/// A `[services]` entry in its expanded form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Service {
+    pub name: String,
+    pub start: Option<Action>,
+    pub stop: Option<Action>,
+    pub check: Option<Action>,
+    pub preset: Option<PresetService>,
+}
+
+impl Service {
+    /// `check`, unless it belongs to a preset that never started: without ports of
+    /// its own it would probe whatever listens on the defaults.
+    fn live_check(&self) -> Option<&Action> {
+        self.check
+            .as_ref()
+            .filter(|_| self.preset.as_ref().is_none_or(PresetService::initialized))
+    }
+}
+
+/// The services of a project, from `still.toml` with `still.local.toml` on top.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Services {
+    pub root: PathBuf,
+    pub services: BTreeMap<String, Service>,
+    /// Where the [`ServiceState`] and log of each service are kept.
+    pub state_dir: PathBuf,
+    /// Where the packages of presets are installed.
+    pub tool_dir: PathBuf,
+}
+
+impl Services {
+    pub fn load(root: &Path) -> Result<Self> {
+        let mut services = Self {
+            root: root.to_path_buf(),
+            services: BTreeMap::new(),
+            state_dir: System::cache_dir()
+                .join("still")
+                .join("services")
+                .join(project_id(root)),
+            tool_dir: System::tool_dir(),
+        };
+        for name in [MANIFEST_NAME, LOCAL_MANIFEST_NAME] {
+            let path = root.join(name);
+            if !path.is_file() {
+                continue;
+            }
+            for (name, entry) in Manifest::load(&path)?.services {
+                let options = entry.options();
+                let preset = match &options.preset {
+                    Some(preset) => {
+                        let preset: Preset = preset
+                            .parse()
+                            .with_context(|| format!("Invalid preset for service {}", name))?;
+                        let dir = match &options.data {
+                            Some(data) => root.join(data),
+                            None => System::services_dir()
+                                .join(project_id(root))
+                                .join(file_stem(&name)),
+                        };
+                        Some(PresetService::new(preset, options.version.as_deref(), dir))
+                    }
+                    None => None,
+                };
+                // What the manifest spells out replaces what the preset defines.
+                let action =
+                    |action: &Option<ServiceAction>, preset_action: fn(&Preset) -> &'static str| {
+                        action.as_ref().map(Action::from_manifest).or_else(|| {
+                            preset
+                                .as_ref()
+                                .map(|p| Action::Shell(preset_action(&p.preset).to_string()))
+                        })
+                    };
+                let service = Service {
+                    name: name.clone(),
+                    start: action(&options.start, Preset::start),
+                    stop: action(&options.stop, Preset::stop),
+                    check: action(&options.check, Preset::check),
+                    preset,
+                };
+                services.services.insert(name, service);
+            }
+        }
+        Ok(services)
+    }
+
+    pub fn get(&self, name: &str) -> Result<&Service> {
+        self.services
+            .get(name)
+            .ok_or_else(|| anyhow::anyhow!("No service named {} in {}", name, MANIFEST_NAME))
+    }
+
+    /// Output of the service's commands and its supervisor's notes.
+    pub fn log_path(&self, name: &str) -> PathBuf {
+        self.state_dir.join(format!("{}.log", file_stem(name)))
+    }
+
+    fn state_path(&self, name: &str) -> PathBuf {
+        self.state_dir.join(format!("{}.toml", file_stem(name)))
+    }
+
+    /// The state of `name` while its supervisor runs, or after it gave up.
+    fn state(&self, name: &str) -> Option<ServiceState> {
+        ServiceState::load(&self.state_path(name))
+            .filter(|state| self.supervised(name) || state.status == ServiceStatus::Failed)
+    }
+
+    fn running(&self, name: &str) -> Option<ServiceState> {
+        self.state(name).filter(|_| self.supervised(name))
+    }
+
+    /// Whether a supervisor of `name` runs: it holds the lock on the state file
+    /// for as long as it lives, which a recycled pid cannot fake.
+    fn supervised(&self, name: &str) -> bool {
+        matches!(FileLock::try_acquire(&self.state_path(name)), Ok(None))
+    }
+
+    /// Where presets record the ports they listen on.
+    pub fn ports_files(&self) -> Vec<PathBuf> {
+        self.services
+            .values()
+            .filter_map(|service| service.preset.as_ref())
+            .map(|preset| preset.dir.join(crate::actions::preset::PORTS_FILE))
+            .collect()
+    }
+
+    /// Connection variables of the presets that started before, for the project
+    /// environment. One that presets set apart (`DATABASE_URL` of postgresql and
+    /// mysql) is exported under each service's name instead: `MAIN_DB_DATABASE_URL`
+    /// for `main-db`.
+    pub fn exports(&self) -> BTreeMap<String, String> {
+        let presets: Vec<(&str, BTreeMap<String, String>)> = self
+            .services
+            .values()
+            .filter_map(|service| {
+                let preset = service.preset.as_ref().filter(|p| p.initialized())?;
+                Some((service.name.as_str(), preset.exports()))
+            })
+            .collect();
+        let mut values: BTreeMap<&str, BTreeSet<&str>> = BTreeMap::new();
+        for (name, value) in presets.iter().flat_map(|(_, exports)| exports) {
+            values.entry(name).or_default().insert(value);
+        }
+
+        let mut vars = BTreeMap::new();
+        for (service, exports) in &presets {
+            for (name, value) in exports {
+                let name = match values[name.as_str()].len() {
+                    1 => name.clone(),
+                    _ => format!("{}_{}", var_prefix(service), name),
+                };
+                vars.insert(name, value.clone());
+            }
+        }
+        vars
+    }
+
+    /// Packages of the presets of `names` that are not installed yet.
+    pub fn missing(&self, names: &[String]) -> Result<Vec<ToolSpec>> {
+        let mut missing = Vec::new();
+        for name in names {
+            let preset = self.get(name)?.preset.as_ref();
+            if let Some(preset) = preset.filter(|p| p.bin_dir(&self.tool_dir).is_none()) {
+                missing.push(preset.package()?);
+            }
+        }
+        Ok(missing)
+    }
+
+    /// `env` with what the commands of `service` need on top: a preset's
+    /// variables and its package's `bin` directory first on `PATH`.
+    fn command_env(
+        &self,
+        service: &Service,
+        env: &BTreeMap<String, String>,
+    ) -> BTreeMap<String, String> {
+        let mut env = env.clone();
+        let Some(preset) = &service.preset else {
+            return env;
+        };
+        env.extend(preset.vars());
+        if let Some(bin) = preset.bin_dir(&self.tool_dir) {
+            let bin = bin.to_string_lossy().to_string();
+            let path = match env.get("PATH") {
+                Some(path) if !path.is_empty() => format!("{}:{}", bin, path),
+                _ => bin,
+            };
+            env.insert("PATH".to_string(), path);
+        }
+        env
+    }
+}
+
+/// Install the packages of the presets of `names` through the Homebrew backend.
+/// They are not project tools: no shims, and `still.lock` stays as it is.
+pub async fn install_presets(services: &Services, names: &[String]) -> Result<()> {
+    let tools = services.missing(names)?;
+    if tools.is_empty() {
+        return Ok(());
+    }
+    let request = InstallRequest {
+        tools,
+        locked: false,
+    };
+    let mut plan = install::plan(&request).await?;
+    plan.files_only = true;
+    install::execute(plan).await?;
+    Ok(())
+}
+
+/// `name` as the start of a variable name: `main-db` is `MAIN_DB`.
+fn var_prefix(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_uppercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ServiceStatus {
+    /// The start command exited successfully; waiting for `check` to pass.
+    #[default]
+    Starting,
+    Running,
+    /// Waiting to restart after a crash.
+    Backoff,
+    /// Crashed too often in a row; the supervisor gave up.
+    Failed,
+}
+
+impl std::fmt::Display for ServiceStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = match self {
+            ServiceStatus::Starting => "starting",
+            ServiceStatus::Running => "running",
+            ServiceStatus::Backoff => "backoff",
+            ServiceStatus::Failed => "failed",
+        };
+        write!(f, "{}", status)
+    }
+}
+
+/// What the supervisor of a service records while it runs.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ServiceState {
+    /// The `still service supervise` process.
+    pub supervisor: u32,
+    /// Leader of the start command's process group; `None` while it is not
+    /// running, also after it handed over to a daemon of its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    pub status: ServiceStatus,
+    /// Restarts after crashes since the supervisor started.
+    pub restarts: u32,
+    /// Unix time `status` last changed.
+    pub since: u64,
+}
+
+impl ServiceState {
+    /// `None` when there is no readable state at `path`.
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        toml::from_str(&content).ok()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let content = toml::to_string(self).context("Failed to serialize service state")?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write service state at {}", path.display()))
+    }
+
+    fn set(&mut self, status: ServiceStatus) {
+        self.status = status;
+        self.since = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_secs());
+    }
+}
+
+/// How a supervisor restarts a crashed service.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Supervision {
+    /// Delay before the first restart; it doubles with every crash in a row.
+    pub backoff: Duration,
+    pub max_backoff: Duration,
+    /// Crashes in a row after which the supervisor gives up.
+    pub max_restarts: u32,
+    /// Running this long resets the count of crashes in a row.
+    pub stable: Duration,
+    /// How often `check` runs while a daemon started by the service is up.
+    pub probe_interval: Duration,
+}
+
+impl Default for Supervision {
+    fn default() -> Self {
+        Self {
+            backoff: Duration::from_secs(1),
+            max_backoff: Duration::from_secs(30),
+            max_restarts: 5,
+            stable: Duration::from_secs(30),
+            probe_interval: Duration::from_secs(2),
+        }
+    }
+}
+
+/// Keep `name` running until `stop` is set: run its start command in a process
+/// group of its own, restart it with exponential backoff when it exits, and keep
+/// its [`ServiceState`] up to date. Then stop the group and run the service's
+/// `stop` if its `check` still passes. All output goes to the service's log.
+pub fn supervise(
+    services: &Services,
+    name: &str,
+    env: &BTreeMap<String, String>,
+    options: &Supervision,
+    stop: &AtomicBool,
+) -> Result<()> {
+    let mut service = services.get(name)?.clone();
+    let path = services.state_path(name);
+    let Some(_supervising) = FileLock::try_acquire(&path)? else {
+        anyhow::bail!("{} is supervised already", name);
+    };
+    if let Some(preset) = &mut service.preset {
+        preset.init()?;
+    }
+    let service = &service;
+    let env = &services.command_env(service, env);
+    let start = service
+        .start
+        .as_ref()
+        .ok_or_else(|| anyhow::anyhow!("Service {} has no start command", name))?;
+    let log_path = services.log_path(name);
+    let log = open_log(&log_path)?;
+    let stopped = || stop.load(Ordering::SeqCst);
+
+    let mut state = ServiceState {
+        supervisor: std::process::id(),
+        ..ServiceState::default()
+    };
+    let mut crashes = 0;
+    while !stopped() {
+        let mut command = start.command(&services.root, env)?;
+        command.stdout(log.try_clone()?).stderr(log.try_clone()?);
+        note(&log, &format!("starting {}", start));
+        let mut group = ProcessGroup::spawn(&mut command)?;
+        state.pid = Some(group.id());
+        state.set(ServiceStatus::Running);
+        state.save(&path)?;
+        let started = Instant::now();
+
+        let status = loop {
+            if stopped() {
+                break None;
+            }
+            if let Some(status) = group.try_wait() {
+                break Some(status);
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        };
+        let Some(status) = status else {
+            group.stop(STOP_GRACE)?;
+            break;
+        };
+        state.pid = None;
+
+        // A start command that succeeds launched a daemon of its own (`open -a
+        // Docker`); `check` tells whether it is still up.
+        let check = service.check.as_ref().filter(|_| status.success());
+        if let Some(check) = check {
+            state.set(ServiceStatus::Starting);
+            state.save(&path)?;
+            let ready = Instant::now() + READY_TIMEOUT;
+            while !stopped() && Instant::now() < ready && !probe(check, services, env) {
+                pause(options.probe_interval, stop);
+            }
+            if probe(check, services, env) {
+                note(&log, &format!("{} exited, {} passes", start, check));
+                state.set(ServiceStatus::Running);
+                state.save(&path)?;
+                while !stopped() && probe(check, services, env) {
+                    pause(options.probe_interval, stop);
+                }
+            }
+            if stopped() {
+                break;
+            }
+            note(&log, &format!("{} fails", check));
+        } else {
+            note(&log, &format!("exited with {}", status));
+        }
+
+        if started.elapsed() >= options.stable {
+            crashes = 0;
+        }
+        crashes += 1;
+        if crashes > options.max_restarts {
+            state.set(ServiceStatus::Failed);
+            state.save(&path)?;
+            note(
+                &log,
+                &format!("giving up after {} crashes in a row", crashes),
+            );
+            anyhow::bail!(
+                "{} crashed {} times in a row; see {}",
+                name,
+                crashes,
+                log_path.display()
+            );
+        }
+        let delay = options
+            .backoff
+            .saturating_mul(1 << (crashes - 1).min(16))
+            .min(options.max_backoff);
+        state.restarts += 1;
+        state.set(ServiceStatus::Backoff);
+        state.save(&path)?;
+        note(&log, &format!("restarting in {:.1}s", delay.as_secs_f64()));
+        pause(delay, stop);
+    }
+
+    // A daemon the start command launched outlives the group.
+    let still_up = service
+        .live_check()
+        .is_none_or(|check| probe(check, services, env));
+    if let Some(action) = service.stop.as_ref().filter(|_| still_up) {
+        note(&log, &format!("stopping with {}", action));
+        if !run_action(action, services, env, Some(&log), STOP_TIMEOUT)? {
+            note(&log, &format!("{} failed", action));
+        }
+    }
+    note(&log, "stopped");
+    let _ = std::fs::remove_file(&path);
+    Ok(())
+}
+
+/// Start the supervisor of `name` in a session of its own, so it outlives the
+/// terminal, and wait until the service is healthy. `false` when it was running
+/// already, also when its `check` passes for an instance Still did not start.
+pub fn start(services: &Services, name: &str, env: &BTreeMap<String, String>) -> Result<bool> {
+    let mut service = services.get(name)?.clone();
+    // Held until the service is ready, so a concurrent start finds it running.
+    let _starting = FileLock::acquire(
+        &services
+            .state_dir
+            .join(format!("{}.start", file_stem(name))),
+    )?;
+    if services.running(name).is_some() {
+        return Ok(false);
+    }
+    if let Some(preset) = &mut service.preset {
+        if let Some(package) = services.missing(&[name.to_string()])?.first() {
+            anyhow::bail!(
+                "{} is not installed; run `still install {}`",
+                package,
+                package
+            );
+        }
+        // Ports are picked and recorded on first start only.
+        preset.init()?;
+    }
+    let env = &services.command_env(&service, env);
+    if service
+        .check
+        .as_ref()
+        .is_some_and(|check| probe(check, services, env))
+    {
+        return Ok(false);
+    }
+    if service.start.is_none() {
+        anyhow::bail!("Service {} is not running and has no start command", name);
+    }
+
+    let log = open_log(&services.log_path(name))?;
+    let exe = std::env::current_exe().context("Failed to locate the still executable")?;
+    let mut command = Command::new(exe);
+    command
+        .args(["service", "supervise", name])
+        .current_dir(&services.root)
+        .stdin(Stdio::null())
+        .stdout(log.try_clone()?)
+        .stderr(log);
+    // SAFETY: `setsid` is async-signal-safe and touches no memory of the parent.
+    unsafe {
+        command.pre_exec(|| {
+            libc::setsid();
+            Ok(())
+        });
+    }
+    let supervisor = command
+        .spawn()
+        .with_context(|| format!("Failed to start the supervisor of {}", name))?;
+    wait_ready(services, &service, env, supervisor)?;
+    Ok(true)
+}
+
+/// Wait until `check` passes, or without one until the start command stayed up
+/// for [`READY_GRACE`]. A crash while starting is an error; the supervisor keeps
+/// retrying in the background.
+fn wait_ready(
+    services: &Services,
+    service: &Service,
+    env: &BTreeMap<String, String>,
+    mut supervisor: Child,
+) -> Result<()> {
+    let log = services.log_path(&service.name);
+    let deadline = Instant::now() + READY_TIMEOUT;
+    let mut up_since: Option<(u32, Instant)> = None;
+    loop {
+        if let Ok(Some(status)) = supervisor.try_wait() {
+            anyhow::bail!(
+                "Supervisor of {} exited with {}; see {}",
+                service.name,
+                status,
+                log.display()
+            );
+        }
+        let state = ServiceState::load(&services.state_path(&service.name));
+        let status = state.as_ref().map(|state| state.status);
+        if matches!(status, Some(ServiceStatus::Backoff | ServiceStatus::Failed)) {
+            anyhow::bail!(
+                "{} exited while starting; see {}",
+                service.name,
+                log.display()
+            );
+        }
+
+        let ready = match &service.check {
+            Some(check) => probe(check, services, env),
+            None => match state.and_then(|state| state.pid) {
+                Some(pid) if status == Some(ServiceStatus::Running) => {
+                    let since = match up_since {
+                        Some((up, since)) if up == pid => since,
+                        _ => Instant::now(),
+                    };
+                    up_since = Some((pid, since));
+                    since.elapsed() >= READY_GRACE
+                }
+                _ => false,
+            },
+        };
+        if ready {
+            return Ok(());
+        }
+        if Instant::now() >= deadline {
+            anyhow::bail!(
+                "{} is not healthy after {}s; see {}",
+                service.name,
+                READY_TIMEOUT.as_secs(),
+                log.display()
+            );
+        }
+        std::thread::sleep(POLL_INTERVAL);
+    }
+}
+
+/// Stop `name`: its supervisor stops the start command's process group and runs
+/// `stop`. A service Still did not start is stopped with `stop` while its `check`
+/// passes. `false` when it was not running.
+pub fn stop(services: &Services, name: &str, env: &BTreeMap<String, String>) -> Result<bool> {
+    let service = services.get(name)?;
+    let env = &services.command_env(service, env);
+    let path = services.state_path(name);
+    if let Some(state) = services.running(name) {
+        signal(state.supervisor as libc::pid_t, libc::SIGTERM);
+        let deadline = Instant::now() + STOP_GRACE + STOP_TIMEOUT;
+        while services.supervised(name) && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        if services.supervised(name) {
+            signal(state.supervisor as libc::pid_t, libc::SIGKILL);
+            if let Some(pid) = state.pid {
+                signal(-(pid as libc::pid_t), libc::SIGKILL);
+            }
+        }
+        let _ = std::fs::remove_file(&path);
+        return Ok(true);
+    }
+
+    let _ = std::fs::remove_file(&path);
+    let (Some(action), Some(check)) = (&service.stop, service.live_check()) else {
+        return Ok(false);
+    };
+    if !probe(check, services, env) {
+        return Ok(false);
+    }
+    let log_path = services.log_path(name);
+    let log = open_log(&log_path)?;
+    note(&log, &format!("stopping with {}", action));
+    if !run_action(action, services, env, Some(&log), STOP_TIMEOUT)? {
+        anyhow::bail!("{} failed; see {}", action, log_path.display());
+    }
+    Ok(true)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServiceReport {
+    pub name: String,
+    /// `None` when Still is not supervising the service.
+    pub state: Option<ServiceState>,
+    /// Whether `check` passes; `None` without one.
+    pub healthy: Option<bool>,
+}
+
+/// The state and health of every service, for `still service status`.
+pub fn status(services: &Services, env: &BTreeMap<String, String>) -> Vec<ServiceReport> {
+    services
+        .services
+        .values()
+        .map(|service| ServiceReport {
+            name: service.name.clone(),
+            state: services.state(&service.name),
+            healthy: service
+                .live_check()
+                .map(|check| probe(check, services, &services.command_env(service, env))),
+        })
+        .collect()
+}
+
+/// Copy the log of `name` to `out`; with `follow`, keep copying what is appended
+/// until the process is interrupted.
+pub fn logs(services: &Services, name: &str, out: &mut impl Write, follow: bool) -> Result<()> {
+    services.get(name)?;
+    let path = services.log_path(name);
+    let mut file = File::open(&path)
+        .with_context(|| format!("No log for {} yet at {}", name, path.display()))?;
+    loop {
+        std::io::copy(&mut file, out)?;
+        out.flush()?;
+        if !follow {
+            return Ok(());
+        }
+        std::thread::sleep(POLL_INTERVAL);
+        // Start over when the log was replaced by a shorter one.
+        let len = std::fs::metadata(&path).map_or(0, |m| m.len());
+        if len < file.stream_position()? {
+            file = File::open(&path)?;
+        }
+    }
+}
+
+/// Services the tasks of `order` require, in order and each once.
+pub fn required(graph: &TaskGraph, order: &[String]) -> Vec<String> {
+    let mut required: Vec<String> = Vec::new();
+    for service in order
+        .iter()
+        .filter_map(|name| graph.tasks.get(name))
+        .flat_map(|task| &task.requires)
+    {
+        if !required.contains(service) {
+            required.push(service.clone());
+        }
+    }
+    required
+}
+
+fn probe(check: &Action, services: &Services, env: &BTreeMap<String, String>) -> bool {
+    run_action(check, services, env, None, CHECK_TIMEOUT).unwrap_or(false)
+}
+
+/// Run `action` to the end with its output in `log` (discarded without one);
+/// `false` when it fails or outlives `timeout`.
+fn run_action(
+    action: &Action,
+    services: &Services,
+    env: &BTreeMap<String, String>,
+    log: Option<&File>,
+    timeout: Duration,
+) -> Result<bool> {
+    let mut command = action.command(&services.root, env)?;
+    match log {
+        Some(log) => command.stdout(log.try_clone()?).stderr(log.try_clone()?),
+        None => command.stdout(Stdio::null()).stderr(Stdio::null()),
+    };
+    let mut group = ProcessGroup::spawn(&mut command)?;
+    let deadline = Instant::now() + timeout;
+    loop {
+        if let Some(status) = group.try_wait() {
+            return Ok(status.success());
+        }
+        if Instant::now() >= deadline {
+            group.stop(STOP_GRACE)?;
+            return Ok(false);
+        }
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+fn open_log(path: &Path) -> Result<File> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    File::options()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))
+}
+
+/// A line of the supervisor's own in the log, told apart from the service's output.
+fn note(mut log: &File, message: &str) {
+    let _ = writeln!(log, "[still] {}", message);
+}
+
+/// Sleep for `duration`, waking up early once `stop` is set.
+fn pause(duration: Duration, stop: &AtomicBool) {
+    let until = Instant::now() + duration;
+    while !stop.load(Ordering::SeqCst) {
+        let left = until.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            return;
+        }
+        std::thread::sleep(left.min(POLL_INTERVAL));
+    }
+}
+
+fn signal(pid: libc::pid_t, signal: libc::c_int) {
+    // SAFETY: `kill` only sends a signal; a stale pid yields `ESRCH`.
+    unsafe { libc::kill(pid, signal) };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch::Scratch;
+
+    fn alive(pid: u32) -> bool {
+        // SAFETY: signal 0 only checks that the process exists.
+        unsafe { libc::kill(pid as libc::pid_t, 0) == 0 }
+    }
+
+    fn services(root: &Path, manifest: &str) -> Services {
+        std::fs::write(root.join(MANIFEST_NAME), manifest).unwrap();
+        let mut services = Services::load(root).unwrap();
+        services.state_dir = root.join(".services");
+        services
+    }
+
+    fn env() -> BTreeMap<String, String> {
+        std::env::vars().filter(|(key, _)| key == "PATH").collect()
+    }
+
+    #[test]
+    fn restarts_crashed_services_with_backoff() {
+        let root = Scratch::new("service-crash");
+        let services = services(&root, "[services]\nflaky = \"echo run >> runs; exit 3\"\n");
+        let options = Supervision {
+            backoff: Duration::from_millis(10),
+            max_restarts: 2,
+            ..Supervision::default()
+        };
+
+        let err = supervise(
+            &services,
+            "flaky",
+            &env(),
+            &options,
+            &AtomicBool::new(false),
+        )
+        .unwrap_err();
+        assert!(err.to_string().contains("crashed 3 times"), "{err}");
+        let runs = std::fs::read_to_string(root.join("runs")).unwrap();
+        assert_eq!(runs.lines().count(), 3);
+
+        let state = services.state("flaky").unwrap();
+        assert_eq!(state.status, ServiceStatus::Failed);
+        assert_eq!(state.restarts, 2);
+        let log = std::fs::read_to_string(services.log_path("flaky")).unwrap();
+        assert!(log.contains("[still] exited with exit status: 3"), "{log}");
+        assert!(log.contains("[still] restarting in 0.0s"), "{log}");
+    }
+
+    #[test]
+    fn stops_services_and_runs_their_stop_command() {
+        let root = Scratch::new("service-stop");
+        let services = services(
+            &root,
+            r#"[services.web]
+start = "echo listening; exec sleep 30"
+stop = { command = "echo stopped > stopped" }
+check = "grep -q listening .services/web.log"
+"#,
+        );
+        let stop = AtomicBool::new(false);
+        let env = env();
+
+        std::thread::scope(|scope| {
+            let supervisor =
+                scope.spawn(|| supervise(&services, "web", &env, &Supervision::default(), &stop));
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while status(&services, &env)[0].healthy != Some(true) {
+                assert!(Instant::now() < deadline, "web never became healthy");
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            let state = services.running("web").unwrap();
+            assert_eq!(state.status, ServiceStatus::Running);
+            let pid = state.pid.unwrap();
+            let err = supervise(&services, "web", &env, &Supervision::default(), &stop);
+            assert!(err.unwrap_err().to_string().contains("supervised already"));
+
+            stop.store(true, Ordering::SeqCst);
+            supervisor.join().unwrap().unwrap();
+            assert!(!alive(pid));
+        });
+        assert!(root.join("stopped").is_file());
+        assert_eq!(services.state("web"), None);
+
+        // A live process that took over a dead supervisor's pid does not count.
+        let stale = ServiceState {
+            supervisor: std::process::id(),
+            status: ServiceStatus::Running,
+            ..ServiceState::default()
+        };
+        stale.save(&services.state_path("web")).unwrap();
+        assert_eq!(services.running("web"), None);
+    }
+
+    #[test]
+    fn runs_presets_from_their_installed_package() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let root = Scratch::new("service-preset");
+        let mut services = services(
+            &root,
+            "[services]\ncache = { preset = \"redis\", data = \".data/cache\" }\n",
+        );
+        services.tool_dir = root.join("tools");
+        assert_eq!(
+            services.get("cache").unwrap().stop,
+            Some(Action::Shell(Preset::Redis.stop().to_string()))
+        );
+        let names = vec!["cache".to_string()];
+        assert_eq!(
+            services.missing(&names).unwrap(),
+            vec!["brew:redis@latest".parse().unwrap()]
+        );
+
+        let bin = services.tool_dir.join("redis/7.4.1/bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        for (program, script) in [
+            (
+                "redis-server",
+                "echo \"$@\" > \"$STILL_SERVICE_DATA/args\"\nexec sleep 30",
+            ),
+            (
+                "redis-cli",
+                "[ -f \"$STILL_SERVICE_DATA/args\" ] && echo PONG",
+            ),
+        ] {
+            let path = bin.join(program);
+            std::fs::write(&path, format!("#!/bin/sh\n{}\n", script)).unwrap();
+            std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).unwrap();
+        }
+        assert!(services.missing(&names).unwrap().is_empty());
+        // Loading picks no ports, so there is nothing to export or check yet.
+        assert!(services.exports().is_empty());
+        assert_eq!(status(&services, &env())[0].healthy, None);
+
+        let reload = || {
+            let mut reloaded = Services::load(&root).unwrap();
+            reloaded.state_dir = services.state_dir.clone();
+            reloaded.tool_dir = services.tool_dir.clone();
+            reloaded
+        };
+        let stop = AtomicBool::new(false);
+        let env = env();
+        std::thread::scope(|scope| {
+            let supervisor =
+                scope.spawn(|| supervise(&services, "cache", &env, &Supervision::default(), &stop));
+            let deadline = Instant::now() + Duration::from_secs(10);
+            while status(&reload(), &env)[0].healthy != Some(true) {
+                assert!(Instant::now() < deadline, "cache never became healthy");
+                std::thread::sleep(Duration::from_millis(20));
+            }
+            stop.store(true, Ordering::SeqCst);
+            supervisor.join().unwrap().unwrap();
+        });
+        let exports = reload().exports();
+        let port = &exports["REDIS_PORT"];
+        assert_eq!(exports["REDIS_URL"], format!("redis://127.0.0.1:{}", port));
+        let args = std::fs::read_to_string(root.join(".data/cache/data/args")).unwrap();
+        assert!(args.contains(&format!("--port {}", port)), "{args}");
+        assert!(args.contains("--bind 127.0.0.1"), "{args}");
+    }
+
+    #[test]
+    fn exports_variables_presets_share_per_service() {
+        let root = Scratch::new("service-exports");
+        for (data, ports) in [
+            ("db", "PGPORT = 5433"),
+            ("main-db", "MYSQL_TCP_PORT = 3307"),
+        ] {
+            std::fs::create_dir_all(root.join(data)).unwrap();
+            std::fs::write(
+                root.join(data).join(crate::actions::preset::PORTS_FILE),
+                ports,
+            )
+            .unwrap();
+        }
+        let services = services(
+            &root,
+            r#"[services]
+db = { preset = "postgresql", data = "db" }
+main-db = { preset = "mysql", data = "main-db" }
+cache = { preset = "redis", data = "cache" }
+"#,
+        );
+
+        let exports = services.exports();
+        assert!(!exports.contains_key("DATABASE_URL"));
+        assert_eq!(
+            exports["DB_DATABASE_URL"],
+            "postgres://postgres@127.0.0.1:5433/postgres"
+        );
+        assert_eq!(
+            exports["MAIN_DB_DATABASE_URL"],
+            "mysql://root@127.0.0.1:3307/mysql"
+        );
+        assert_eq!(exports["PGPORT"], "5433");
+        // Both listen on 127.0.0.1 under names of their own.
+        assert_eq!(exports["MYSQL_HOST"], "127.0.0.1");
+        // Never started: no ports yet.
+        assert!(!exports.contains_key("REDIS_URL"));
+    }
+}
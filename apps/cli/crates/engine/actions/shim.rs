@@ -0,0 +1,387 @@
+use crate::actions::env::{find_installed, install_dir_name};
+use crate::registries::tool_vars;
+use crate::specs::config::LayeredConfig;
+use crate::system::System;
+use crate::utils::lock::FileLock;
+use crate::utils::paths::PathOps;
+use crate::utils::transaction::FsTransaction;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
+use std::path::{Path, PathBuf};
+
+/// Index of the shims in `bin_dir`, kept in `root_dir`.
+pub const SHIM_INDEX_NAME: &str = "shims.toml";
+
+/// Names the `still` executable itself is invoked as; never dispatched as shims.
+pub const STILL_NAMES: &[&str] = &["still", "still_s"];
+
+/// What a shim in `bin_dir` runs.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShimTarget {
+    /// Config key of the tool the binary belongs to (`node`, `npm:prettier`).
+    pub tool: String,
+    /// The binary, relative to an install of the tool (`bin/npx`).
+    pub path: String,
+}
+
+/// `shims.toml`: shim name to [`ShimTarget`]. When two tools ship a binary of the
+/// same name, the one installed last owns the shim.
+///
+/// Hold [`ShimIndex::lock`] from loading the index until the changes are written.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ShimIndex {
+    #[serde(flatten)]
+    pub shims: BTreeMap<String, ShimTarget>,
+}
+
+impl ShimIndex {
+    pub fn path() -> PathBuf {
+        System::root_dir().join(SHIM_INDEX_NAME)
+    }
+
+    /// Serialize read-modify-write cycles of the index at `path` across processes.
+    pub fn lock(path: &Path) -> Result<FileLock> {
+        FileLock::acquire(path)
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read shim index at {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse shim index at {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string_pretty(self).context("Failed to serialize shim index")?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write shim index at {}", path.display()))
+    }
+
+    /// Write the index to `path` when `transaction` commits.
+    pub fn stage(&self, transaction: &mut FsTransaction, path: &Path) -> Result<()> {
+        let content = toml::to_string_pretty(self).context("Failed to serialize shim index")?;
+        transaction.stage_write(path, content)
+    }
+}
+
+/// The program a shim execs, with the environment its tool needs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShimCommand {
+    pub program: PathBuf,
+    pub version: String,
+    /// `PATH` with the install's `bin` in front, so the tool finds its siblings.
+    pub path: OsString,
+    pub vars: Vec<(String, String)>,
+}
+
+/// A shim written by [`stage_shim_in`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StagedShim {
+    pub path: PathBuf,
+    /// The tool the shim ran before, when another tool ships a binary of the same name.
+    pub replaced: Option<String>,
+}
+
+/// The `still` executable shims link to. An installed `bin_dir/still` is preferred
+/// over the running executable, which may be a build tree that moves or goes away.
+pub fn still_exe(bin_dir: &Path) -> Result<PathBuf> {
+    if let Some(installed) = STILL_NAMES
+        .iter()
+        .map(|name| bin_dir.join(name))
+        .find(|path| path.is_file())
+    {
+        return Ok(installed);
+    }
+    let exe = std::env::current_exe().context("Failed to locate the still executable")?;
+    Ok(std::fs::canonicalize(&exe).unwrap_or(exe))
+}
+
+/// Expose `binary` (inside `install_path`) as `bin_dir/<name>`, a symlink to the
+/// `still` executable that dispatches on its name.
+pub fn install_shim(tool: &str, install_path: &Path, binary: &Path) -> Result<StagedShim> {
+    let bin_dir = System::bin_dir();
+    install_shim_in(
+        &bin_dir,
+        &ShimIndex::path(),
+        &still_exe(&bin_dir)?,
+        tool,
+        install_path,
+        binary,
+    )
+}
+
+pub fn install_shim_in(
+    bin_dir: &Path,
+    index_path: &Path,
+    exe: &Path,
+    tool: &str,
+    install_path: &Path,
+    binary: &Path,
+) -> Result<StagedShim> {
+    let _lock = ShimIndex::lock(index_path)?;
+    let mut transaction = FsTransaction::new();
+    let mut index = ShimIndex::load(index_path)?;
+    let shim = stage_shim_in(
+        &mut transaction,
+        &mut index,
+        bin_dir,
+        exe,
+        tool,
+        install_path,
+        binary,
+    )?;
+    index.stage(&mut transaction, index_path)?;
+    transaction.commit()?;
+    Ok(shim)
+}
+
+/// Like [`install_shim_in`], but the shim only appears when `transaction`
+/// commits. `index` is updated in memory; the caller holds its lock and stages it.
+pub fn stage_shim_in(
+    transaction: &mut FsTransaction,
+    index: &mut ShimIndex,
+    bin_dir: &Path,
+    exe: &Path,
+    tool: &str,
+    install_path: &Path,
+    binary: &Path,
+) -> Result<StagedShim> {
+    let name = binary
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Binary path has no file name: {}", binary.display()))?
+        .to_string_lossy()
+        .to_string();
+    let relative = binary.strip_prefix(install_path).with_context(|| {
+        format!(
+            "{} is not inside {}",
+            binary.display(),
+            install_path.display()
+        )
+    })?;
+
+    std::fs::create_dir_all(bin_dir)
+        .with_context(|| format!("Failed to create {}", bin_dir.display()))?;
+    let shim = bin_dir.join(&name);
+    transaction
+        .stage_symlink(&shim, exe)
+        .with_context(|| format!("Failed to create shim {}", shim.display()))?;
+
+    let previous = index.shims.insert(
+        name,
+        ShimTarget {
+            tool: tool.to_string(),
+            path: relative.to_string_lossy().to_string(),
+        },
+    );
+    Ok(StagedShim {
+        path: shim,
+        replaced: previous
+            .map(|previous| previous.tool)
+            .filter(|previous| previous != tool),
+    })
+}
+
+/// Remove the shims whose binary no remaining install of their tool provides,
+/// such as after uninstalling the only version that shipped it.
+pub fn remove_orphaned_shims() -> Result<Vec<PathBuf>> {
+    remove_orphaned_shims_in(&System::bin_dir(), &ShimIndex::path(), &System::tool_dir())
+}
+
+pub fn remove_orphaned_shims_in(
+    bin_dir: &Path,
+    index_path: &Path,
+    tool_dir: &Path,
+) -> Result<Vec<PathBuf>> {
+    let _lock = ShimIndex::lock(index_path)?;
+    let mut index = ShimIndex::load(index_path)?;
+    let mut removed = Vec::new();
+    index.shims.retain(|name, target| {
+        if is_provided(tool_dir, target) {
+            return true;
+        }
+        let shim = bin_dir.join(name);
+        if std::fs::remove_file(&shim).is_ok() {
+            removed.push(shim);
+        }
+        false
+    });
+    index.save(index_path)?;
+    Ok(removed)
+}
+
+/// Whether any install of `target.tool` under `tool_dir` has `target.path`.
+fn is_provided(tool_dir: &Path, target: &ShimTarget) -> bool {
+    let Ok(installs) = std::fs::read_dir(tool_dir.join(install_dir_name(&target.tool))) else {
+        return false;
+    };
+    installs
+        .flatten()
+        .filter(|entry| !entry.file_name().to_string_lossy().starts_with('.'))
+        .any(|entry| entry.path().join(&target.path).exists())
+}
+
+/// Pick the install `name` runs for the pins in `config` (nearest `still.toml`,
+/// `STILL_<TOOL>_VERSION`, global config), falling back to the newest install.
+pub fn resolve(
+    config: &LayeredConfig,
+    index: &ShimIndex,
+    tool_dir: &Path,
+    name: &str,
+) -> Result<ShimCommand> {
+    let target = index
+        .shims
+        .get(name)
+        .ok_or_else(|| anyhow::anyhow!("{} is not a Still shim", name))?;
+
+    let requested = config
+        .tool_versions()
+        .get(&target.tool)
+        .map_or_else(|| "latest".to_string(), |value| value.to_string());
+    let (version, install_path) =
+        find_installed(tool_dir, &install_dir_name(&target.tool), &requested).ok_or_else(|| {
+            anyhow::anyhow!(
+                "{}@{} is not installed; run `still install {}@{}`",
+                target.tool,
+                requested,
+                target.tool,
+                requested
+            )
+        })?;
+
+    let program = install_path.join(&target.path);
+    if !program.exists() {
+        anyhow::bail!(
+            "{} {} does not provide {}",
+            target.tool,
+            version,
+            target.path
+        );
+    }
+
+    let path = std::env::var_os("PATH").unwrap_or_default();
+    let path = std::env::join_paths(
+        std::iter::once(install_path.join("bin")).chain(std::env::split_paths(&path)),
+    )?;
+    Ok(ShimCommand {
+        program,
+        version,
+        path,
+        vars: tool_vars(&target.tool, &install_path),
+    })
+}
+
+/// When the current process was started through a shim (`argv[0]` is a shimmed
+/// name), run the selected tool in its place. Returns `None` for plain `still`.
+pub fn dispatch_from_args() -> Option<Result<std::convert::Infallible>> {
+    // The tool sees its real path as `argv[0]`: interpreters such as python find
+    // their prefix from it.
+    use std::os::unix::process::CommandExt;
+
+    let mut args = std::env::args_os();
+    let argv0 = PathBuf::from(args.next()?);
+    let name = argv0.file_name()?.to_string_lossy().to_string();
+    if STILL_NAMES.contains(&name.as_str()) {
+        return None;
+    }
+    let index = ShimIndex::load(&ShimIndex::path()).ok()?;
+    if !index.shims.contains_key(&name) {
+        return None;
+    }
+
+    let command = LayeredConfig::load()
+        .and_then(|config| resolve(&config, &index, &System::tool_dir(), &name));
+    Some(command.and_then(|command| {
+        let error = std::process::Command::new(&command.program)
+            .args(args)
+            .env("PATH", &command.path)
+            .envs(command.vars)
+            .exec();
+        Err(error).with_context(|| format!("Failed to run {}", command.program.display()))
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::specs::config::ConfigSources;
+    use crate::utils::scratch::Scratch;
+
+    #[test]
+    fn shims_dispatch_to_the_pinned_version() {
+        let root = Scratch::new("shim");
+        let tool_dir = root.join("tools");
+        for version in ["20.18.1", "22.11.0"] {
+            let bin = tool_dir.join("node").join(version).join("bin");
+            std::fs::create_dir_all(&bin).unwrap();
+            std::fs::write(bin.join("npx"), "").unwrap();
+        }
+        std::fs::write(tool_dir.join("node/22.11.0/bin/corepack"), "").unwrap();
+        let bin_dir = root.join("bin");
+        let index_path = root.join(SHIM_INDEX_NAME);
+        let exe = root.join("still");
+        std::fs::write(&exe, "").unwrap();
+
+        let install = tool_dir.join("node/22.11.0");
+        let shim = |tool: &str, binary: &str| {
+            install_shim_in(
+                &bin_dir,
+                &index_path,
+                &exe,
+                tool,
+                &install,
+                &install.join(binary),
+            )
+            .unwrap()
+        };
+        let npx = shim("node", "bin/npx");
+        assert_eq!(std::fs::read_link(&npx.path).unwrap(), exe);
+        assert_eq!(npx.replaced, None);
+        let corepack = shim("node", "bin/corepack");
+
+        // The tool installed last owns a shared name, and says whose it was.
+        let other = shim("npm:corepack", "bin/corepack");
+        assert_eq!(other.replaced.as_deref(), Some("node"));
+        assert_eq!(
+            shim("node", "bin/corepack").replaced.as_deref(),
+            Some("npm:corepack")
+        );
+
+        let project = root.join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(project.join("still.toml"), "[tools]\nnode = \"20\"\n").unwrap();
+        let load = |env: Vec<(String, String)>| {
+            LayeredConfig::load_from(&ConfigSources {
+                global_file: None,
+                cwd: project.clone(),
+                env,
+            })
+            .unwrap()
+        };
+        let index = ShimIndex::load(&index_path).unwrap();
+
+        let command = resolve(&load(Vec::new()), &index, &tool_dir, "npx").unwrap();
+        assert_eq!(command.version, "20.18.1");
+        assert_eq!(command.program, tool_dir.join("node/20.18.1/bin/npx"));
+
+        let env = vec![("STILL_NODE_VERSION".into(), "22".into())];
+        let command = resolve(&load(env), &index, &tool_dir, "npx").unwrap();
+        assert_eq!(command.version, "22.11.0");
+
+        // Only 22.11.0 ships corepack, so its shim goes with it.
+        std::fs::remove_dir_all(tool_dir.join("node/22.11.0")).unwrap();
+        let removed = remove_orphaned_shims_in(&bin_dir, &index_path, &tool_dir).unwrap();
+        assert_eq!(removed, vec![corepack.path]);
+        let command = resolve(&load(Vec::new()), &index, &tool_dir, "npx").unwrap();
+        assert_eq!(command.version, "20.18.1");
+
+        std::fs::remove_dir_all(tool_dir.join("node")).unwrap();
+        let removed = remove_orphaned_shims_in(&bin_dir, &index_path, &tool_dir).unwrap();
+        assert_eq!(removed, vec![npx.path]);
+        assert!(ShimIndex::load(&index_path).unwrap().shims.is_empty());
+    }
+}
@@ -0,0 +1,771 @@
+use crate::actions::watch::Watcher;
+use crate::specs::toml::{LOCAL_MANIFEST_NAME, MANIFEST_NAME, Manifest, TaskEntry, project_id};
+use crate::system::System;
+use crate::utils::glob::Glob;
+use crate::utils::hashing::Hashing;
+use crate::utils::paths::PathOps;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::mpsc;
+use std::time::{Duration, Instant};
+
+/// A `[tasks]` entry in its expanded form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Task {
+    pub name: String,
+    pub description: Option<String>,
+    /// Run in order through `sh -ec`; a multi-line script is one command.
+    pub commands: Vec<String>,
+    pub depends: Vec<String>,
+    pub requires: Vec<String>,
+    pub sources: Vec<String>,
+    pub outputs: Vec<String>,
+}
+
+impl Task {
+    fn from_entry(name: &str, entry: &TaskEntry) -> Self {
+        let options = entry.options();
+        Self {
+            name: name.to_string(),
+            description: options.description,
+            commands: options.run.commands(),
+            depends: options.depends,
+            requires: options.requires,
+            sources: options.sources,
+            outputs: options.outputs,
+        }
+    }
+}
+
+/// The tasks of a project, from `still.toml` with `still.local.toml` on top.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskGraph {
+    pub root: PathBuf,
+    pub tasks: BTreeMap<String, Task>,
+    /// Where the [`Fingerprint`] of each task's last successful run is kept.
+    pub fingerprints: PathBuf,
+}
+
+impl TaskGraph {
+    pub fn load(root: &Path) -> Result<Self> {
+        let mut graph = Self {
+            root: root.to_path_buf(),
+            tasks: BTreeMap::new(),
+            fingerprints: System::cache_dir()
+                .join("still")
+                .join("tasks")
+                .join(project_id(root)),
+        };
+        for name in [MANIFEST_NAME, LOCAL_MANIFEST_NAME] {
+            let path = root.join(name);
+            if !path.is_file() {
+                continue;
+            }
+            for (name, entry) in Manifest::load(&path)?.tasks {
+                let task = Task::from_entry(&name, &entry);
+                graph.tasks.insert(name, task);
+            }
+        }
+        Ok(graph)
+    }
+
+    /// `targets` and everything they depend on, each after its dependencies.
+    /// Fails on unknown tasks and on dependency cycles.
+    pub fn plan(&self, targets: &[String]) -> Result<Vec<String>> {
+        let mut order = Vec::new();
+        let mut visiting = Vec::new();
+        for target in targets {
+            self.visit(target, &mut visiting, &mut order)?;
+        }
+        Ok(order)
+    }
+
+    fn visit(&self, name: &str, visiting: &mut Vec<String>, order: &mut Vec<String>) -> Result<()> {
+        if order.iter().any(|done| done == name) {
+            return Ok(());
+        }
+        if let Some(start) = visiting.iter().position(|v| v == name) {
+            let mut cycle = visiting[start..].to_vec();
+            cycle.push(name.to_string());
+            anyhow::bail!("Task dependency cycle: {}", cycle.join(" -> "));
+        }
+        let task = self.tasks.get(name).ok_or_else(|| match visiting.last() {
+            Some(parent) => anyhow::anyhow!("Task {} depends on unknown task {}", parent, name),
+            None => anyhow::anyhow!("No task named {} in {}", name, MANIFEST_NAME),
+        })?;
+
+        visiting.push(name.to_string());
+        for dependency in &task.depends {
+            self.visit(dependency, visiting, order)?;
+        }
+        visiting.pop();
+        order.push(name.to_string());
+        Ok(())
+    }
+
+    /// Readable, with a hash of the full name so `a:b` and `a_b` stay apart.
+    fn fingerprint_path(&self, task: &str) -> PathBuf {
+        let name: String = task
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '-' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let hash = Hashing::sha256(task.as_bytes());
+        self.fingerprints
+            .join(format!("{}-{}.toml", name, &hash[..12]))
+    }
+
+    /// sha256 of each dependency's last recorded [`Fingerprint`], `none` for one
+    /// without any.
+    fn dependency_digests(&self, task: &Task) -> BTreeMap<String, String> {
+        task.depends
+            .iter()
+            .map(|dependency| {
+                let digest = Fingerprint::load(&self.fingerprint_path(dependency))
+                    .and_then(|f| f.digest().ok())
+                    .unwrap_or_else(|| "none".to_string());
+                (dependency.clone(), digest)
+            })
+            .collect()
+    }
+}
+
+/// What a task's last successful run depended on.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    /// sha256 of the task's commands, dependencies and globs.
+    pub definition: String,
+    /// Resolved version of every project tool.
+    pub tools: BTreeMap<String, String>,
+    /// sha256 of each source file, by path relative to the project root.
+    pub sources: BTreeMap<String, String>,
+    /// [`Fingerprint::digest`] of each dependency, so a rebuilt dependency reruns
+    /// the tasks on top of it.
+    #[serde(default)]
+    pub depends: BTreeMap<String, String>,
+}
+
+impl Fingerprint {
+    pub fn compute(
+        task: &Task,
+        root: &Path,
+        tools: &BTreeMap<String, String>,
+        depends: BTreeMap<String, String>,
+    ) -> Result<Self> {
+        let definition =
+            serde_json::to_vec(&(&task.commands, &task.depends, &task.sources, &task.outputs))?;
+        let mut sources = BTreeMap::new();
+        for file in Glob::files(root, &task.sources)? {
+            let data = std::fs::read(root.join(&file))
+                .with_context(|| format!("Failed to read {}", file))?;
+            sources.insert(file, Hashing::sha256(&data));
+        }
+        Ok(Self {
+            definition: Hashing::sha256(&definition),
+            tools: tools.clone(),
+            sources,
+            depends,
+        })
+    }
+
+    /// sha256 over the whole fingerprint.
+    pub fn digest(&self) -> Result<String> {
+        Ok(Hashing::sha256(&serde_json::to_vec(self)?))
+    }
+
+    /// `None` when there is no readable fingerprint at `path`.
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(path).ok()?;
+        toml::from_str(&content).ok()
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let content = toml::to_string(self).context("Failed to serialize fingerprint")?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write fingerprint at {}", path.display()))
+    }
+}
+
+/// Why `task` has to run, or `None` when it is up to date: `current` matches the
+/// fingerprint of its last successful run and every `outputs` glob matches a file.
+pub fn stale_reason(
+    task: &Task,
+    root: &Path,
+    current: &Fingerprint,
+    previous: Option<&Fingerprint>,
+) -> Option<String> {
+    if task.sources.is_empty() {
+        return Some("no sources declared".to_string());
+    }
+    let Some(previous) = previous else {
+        return Some("no previous run".to_string());
+    };
+    if previous.definition != current.definition {
+        return Some("task definition changed".to_string());
+    }
+
+    let changed = |old: &BTreeMap<String, String>, new: &BTreeMap<String, String>| {
+        let mut keys: Vec<&String> = old.keys().chain(new.keys()).collect();
+        keys.sort();
+        keys.dedup();
+        keys.into_iter()
+            .filter(|key| old.get(*key) != new.get(*key))
+            .cloned()
+            .collect::<Vec<_>>()
+    };
+    let tools = changed(&previous.tools, &current.tools);
+    if !tools.is_empty() {
+        return Some(format!("tool versions changed: {}", tools.join(", ")));
+    }
+    let depends = changed(&previous.depends, &current.depends);
+    if !depends.is_empty() {
+        return Some(format!("dependencies changed: {}", depends.join(", ")));
+    }
+    let sources = changed(&previous.sources, &current.sources);
+    if !sources.is_empty() {
+        let shown: Vec<&str> = sources.iter().take(3).map(String::as_str).collect();
+        let more = match sources.len() - shown.len() {
+            0 => String::new(),
+            n => format!(" and {} more", n),
+        };
+        return Some(format!("sources changed: {}{}", shown.join(", "), more));
+    }
+
+    task.outputs
+        .iter()
+        .find(|pattern| {
+            Glob::files(root, std::slice::from_ref(pattern)).is_ok_and(|f| f.is_empty())
+        })
+        .map(|pattern| format!("output {} is missing", pattern))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskRequest {
+    pub tasks: Vec<String>,
+    /// Keep starting tasks whose dependencies succeeded after a failure (`--continue`).
+    pub keep_going: bool,
+    /// Tasks running at the same time.
+    pub jobs: usize,
+    /// Run tasks even when they are up to date.
+    pub force: bool,
+}
+
+/// What tasks run in.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TaskEnv {
+    pub vars: BTreeMap<String, String>,
+    /// Resolved tool versions, part of every [`Fingerprint`].
+    pub tools: BTreeMap<String, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaskStatus {
+    Succeeded,
+    /// Exit code of the failing command; `None` when it was killed or did not start.
+    Failed(Option<i32>),
+    /// Not run because a dependency failed or the run stopped early.
+    Skipped,
+    /// Not run because nothing it depends on changed since its last success.
+    UpToDate,
+}
+
+impl TaskStatus {
+    /// Whether tasks that depend on this one may run.
+    pub fn is_ok(&self) -> bool {
+        matches!(self, TaskStatus::Succeeded | TaskStatus::UpToDate)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskOutcome {
+    pub name: String,
+    pub status: TaskStatus,
+    pub duration: Duration,
+    /// Why the task ran or did not (`sources changed: src/a.rs`).
+    pub reason: String,
+}
+
+/// Run the tasks of `order` (from [`TaskGraph::plan`]) in `env`, up to `jobs` at a
+/// time, each once all of its dependencies succeeded. Tasks with `sources` are
+/// skipped while up to date. With more than one task, output lines are prefixed
+/// with the task name.
+pub fn execute(
+    graph: &TaskGraph,
+    order: &[String],
+    request: &TaskRequest,
+    env: &TaskEnv,
+) -> Vec<TaskOutcome> {
+    let width = order.iter().map(String::len).max().unwrap_or(0);
+    let prefixed = order.len() > 1;
+    let mut pending: Vec<&Task> = order
+        .iter()
+        .filter_map(|name| graph.tasks.get(name))
+        .collect();
+    let mut outcomes: Vec<TaskOutcome> = Vec::new();
+    let mut finished: BTreeMap<String, TaskStatus> = BTreeMap::new();
+    let mut failed = false;
+    let mut running = 0;
+    let (tx, rx) = mpsc::channel::<TaskOutcome>();
+
+    std::thread::scope(|scope| {
+        loop {
+            // After a failure nothing new starts, unless `keep_going`.
+            if !failed || request.keep_going {
+                // A task can start once every dependency has finished.
+                while let Some(index) = pending
+                    .iter()
+                    .position(|task| task.depends.iter().all(|d| finished.contains_key(d)))
+                {
+                    let blocked = pending[index]
+                        .depends
+                        .iter()
+                        .find(|d| !finished[d.as_str()].is_ok());
+                    if blocked.is_none() && running >= request.jobs.max(1) {
+                        break;
+                    }
+
+                    let task = pending.remove(index);
+                    if let Some(dependency) = blocked {
+                        finished.insert(task.name.clone(), TaskStatus::Skipped);
+                        let reason = format!("{} did not succeed", dependency);
+                        outcomes.push(skipped(&task.name, reason));
+                        continue;
+                    }
+
+                    running += 1;
+                    let tx = tx.clone();
+                    let prefix = prefixed.then(|| format!("{:<width$} | ", task.name));
+                    scope.spawn(move || {
+                        let started = Instant::now();
+                        let (status, reason) =
+                            run_task(task, graph, env, request.force, prefix.as_deref());
+                        let _ = tx.send(TaskOutcome {
+                            name: task.name.clone(),
+                            status,
+                            duration: started.elapsed(),
+                            reason,
+                        });
+                    });
+                }
+            }
+
+            if running == 0 {
+                break;
+            }
+            let Ok(outcome) = rx.recv() else {
+                break;
+            };
+            running -= 1;
+            failed |= !outcome.status.is_ok();
+            finished.insert(outcome.name.clone(), outcome.status.clone());
+            outcomes.push(outcome);
+        }
+    });
+
+    let stopped = "stopped after a failure";
+    outcomes.extend(
+        pending
+            .iter()
+            .map(|task| skipped(&task.name, stopped.to_string())),
+    );
+    outcomes
+}
+
+fn skipped(name: &str, reason: String) -> TaskOutcome {
+    TaskOutcome {
+        name: name.to_string(),
+        status: TaskStatus::Skipped,
+        duration: Duration::ZERO,
+        reason,
+    }
+}
+
+/// Run `task` unless it is up to date; record its fingerprint when it succeeds.
+fn run_task(
+    task: &Task,
+    graph: &TaskGraph,
+    env: &TaskEnv,
+    force: bool,
+    prefix: Option<&str>,
+) -> (TaskStatus, String) {
+    let path = graph.fingerprint_path(&task.name);
+    let fingerprint = Fingerprint::compute(
+        task,
+        &graph.root,
+        &env.tools,
+        graph.dependency_digests(task),
+    );
+    let reason = match &fingerprint {
+        Ok(current) => {
+            let previous = Fingerprint::load(&path);
+            match stale_reason(task, &graph.root, current, previous.as_ref()) {
+                Some(reason) => reason,
+                None if force => "forced".to_string(),
+                None => return (TaskStatus::UpToDate, "up to date".to_string()),
+            }
+        }
+        Err(e) => format!("no fingerprint: {e:#}"),
+    };
+
+    for command in &task.commands {
+        let status = run_command(command, &graph.root, &env.vars, prefix);
+        match status {
+            Ok(status) if status.success() => continue,
+            Ok(status) => return (TaskStatus::Failed(status.code()), reason),
+            Err(e) => {
+                eprintln!("{}{e:#}", prefix.unwrap_or_default());
+                return (TaskStatus::Failed(None), reason);
+            }
+        }
+    }
+
+    let fingerprint = fingerprint
+        .as_ref()
+        .ok()
+        .filter(|_| !task.sources.is_empty());
+    if let Some(fingerprint) = fingerprint {
+        // Sources are hashed before the run: a file edited meanwhile reruns next time.
+        if let Err(e) = fingerprint.save(&path) {
+            eprintln!("{}{e:#}", prefix.unwrap_or_default());
+        }
+    }
+    (TaskStatus::Succeeded, reason)
+}
+
+fn run_command(
+    command: &str,
+    root: &Path,
+    env: &BTreeMap<String, String>,
+    prefix: Option<&str>,
+) -> Result<std::process::ExitStatus> {
+    let mut process = Command::new("sh");
+    process
+        .arg("-ec")
+        .arg(command)
+        .current_dir(root)
+        .env_clear()
+        .envs(env);
+
+    let Some(prefix) = prefix else {
+        return process
+            .status()
+            .with_context(|| format!("Failed to run {}", command));
+    };
+
+    let mut child = process
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", command))?;
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    std::thread::scope(|scope| {
+        if let Some(stdout) = stdout {
+            scope.spawn(|| copy_lines(stdout, std::io::stdout(), prefix));
+        }
+        if let Some(stderr) = stderr {
+            scope.spawn(|| copy_lines(stderr, std::io::stderr(), prefix));
+        }
+    });
+    child
+        .wait()
+        .with_context(|| format!("Failed to wait for {}", command))
+}
+
+/// Copy whole lines so output of parallel tasks never mixes within a line.
+fn copy_lines(from: impl Read, mut to: impl Write, prefix: &str) {
+    for line in BufReader::new(from)
+        .split(b'\n')
+        .map_while(|line| line.ok())
+    {
+        let mut out = Vec::with_capacity(prefix.len() + line.len() + 1);
+        out.extend_from_slice(prefix.as_bytes());
+        out.extend_from_slice(&line);
+        out.push(b'\n');
+        let _ = to.write_all(&out);
+    }
+}
+
+/// A [`Watcher`] over the `sources` of `targets` and everything they depend on.
+pub fn watcher(graph: &TaskGraph, targets: &[String]) -> Result<Watcher> {
+    let patterns: Vec<String> = graph
+        .plan(targets)?
+        .iter()
+        .flat_map(|name| graph.tasks[name].sources.clone())
+        .collect();
+    if patterns.is_empty() {
+        anyhow::bail!(
+            "{} and its dependencies declare no sources to watch",
+            targets.join(", ")
+        );
+    }
+    Watcher::new(&graph.root, patterns)
+}
+
+/// Every task for `still task --list`, with its description.
+pub fn list(graph: &TaskGraph) -> Vec<(&str, Option<&str>)> {
+    graph
+        .tasks
+        .values()
+        .map(|task| (task.name.as_str(), task.description.as_deref()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch::Scratch;
+
+    fn graph(root: &Path, manifest: &str) -> TaskGraph {
+        std::fs::write(root.join(MANIFEST_NAME), manifest).unwrap();
+        let mut graph = TaskGraph::load(root).unwrap();
+        graph.fingerprints = root.join(".fingerprints");
+        graph
+    }
+
+    #[test]
+    fn plans_dependencies_and_rejects_cycles() {
+        let root = Scratch::new("task-plan");
+        let graph = graph(
+            &root,
+            r#"[tasks]
+fmt = "cargo fmt"
+test = ["cargo build", "cargo test"]
+a = { run = "true", depends = ["b"] }
+b = { run = "true", depends = ["c"] }
+c = { run = "true", depends = ["a"] }
+
+[tasks.ci]
+description = "Run full CI"
+depends = ["lint", "test"]
+run = "echo ok"
+
+[tasks.lint]
+depends = ["fmt"]
+run = "cargo clippy"
+"#,
+        );
+
+        assert_eq!(
+            graph.plan(&["ci".into()]).unwrap(),
+            vec!["fmt", "lint", "test", "ci"]
+        );
+        assert_eq!(graph.tasks["test"].commands.len(), 2);
+        let err = graph.plan(&["a".into()]).unwrap_err();
+        assert_eq!(err.to_string(), "Task dependency cycle: a -> b -> c -> a");
+        assert!(graph.plan(&["deploy".into()]).is_err());
+        assert!(list(&graph).contains(&("ci", Some("Run full CI"))));
+    }
+
+    #[test]
+    fn runs_independent_tasks_in_parallel_and_stops_on_failure() {
+        let root = Scratch::new("task-run");
+        let graph = graph(
+            &root,
+            r#"[tasks]
+slow = "sleep 0.3; echo slow >> log"
+fast = "echo fast >> log"
+both = { run = "echo both >> log", depends = ["slow", "fast"] }
+broken = "exit 3"
+after = { run = "echo after >> log", depends = ["broken"] }
+other = { run = "echo other >> log", depends = ["slow"] }
+"#,
+        );
+        let env = TaskEnv {
+            vars: BTreeMap::from([("PATH".to_string(), std::env::var("PATH").unwrap())]),
+            tools: BTreeMap::new(),
+        };
+        let request = |tasks: &[&str], keep_going| TaskRequest {
+            tasks: tasks.iter().map(|t| t.to_string()).collect(),
+            keep_going,
+            jobs: 4,
+            force: false,
+        };
+
+        let run = |request: TaskRequest| {
+            let _ = std::fs::remove_file(root.join("log"));
+            let order = graph.plan(&request.tasks).unwrap();
+            let outcomes = execute(&graph, &order, &request, &env);
+            let log = std::fs::read_to_string(root.join("log")).unwrap_or_default();
+            (outcomes, log)
+        };
+
+        let (outcomes, log) = run(request(&["both"], false));
+        assert!(outcomes.iter().all(|o| o.status == TaskStatus::Succeeded));
+        assert_eq!(log, "fast\nslow\nboth\n");
+
+        let (outcomes, log) = run(request(&["after", "other"], false));
+        let status = |name: &str| {
+            outcomes
+                .iter()
+                .find(|o| o.name == name)
+                .unwrap()
+                .status
+                .clone()
+        };
+        assert_eq!(status("broken"), TaskStatus::Failed(Some(3)));
+        assert_eq!(status("after"), TaskStatus::Skipped);
+        assert_eq!(status("other"), TaskStatus::Skipped);
+        assert_eq!(log, "slow\n");
+
+        let (outcomes, log) = run(request(&["after", "other"], true));
+        let status = |name: &str| {
+            outcomes
+                .iter()
+                .find(|o| o.name == name)
+                .unwrap()
+                .status
+                .clone()
+        };
+        assert_eq!(status("after"), TaskStatus::Skipped);
+        assert_eq!(status("other"), TaskStatus::Succeeded);
+        assert_eq!(log, "slow\nother\n");
+    }
+
+    #[test]
+    fn skips_tasks_whose_fingerprint_is_unchanged() {
+        let root = Scratch::new("task-fp");
+        let graph = graph(
+            &root,
+            r#"[tasks.gen]
+run = "cat proto/*.proto > gen.out; echo ran >> log"
+sources = ["proto/**/*.proto"]
+outputs = ["gen.out"]
+"#,
+        );
+        std::fs::create_dir_all(root.join("proto/v1")).unwrap();
+        std::fs::write(root.join("proto/a.proto"), "a").unwrap();
+        std::fs::write(root.join("proto/v1/b.proto"), "b").unwrap();
+        let mut env = TaskEnv {
+            vars: BTreeMap::from([("PATH".to_string(), std::env::var("PATH").unwrap())]),
+            tools: BTreeMap::from([("protoc".to_string(), "27.1".to_string())]),
+        };
+        let run = |env: &TaskEnv, force: bool| {
+            let request = TaskRequest {
+                tasks: vec!["gen".into()],
+                keep_going: false,
+                jobs: 1,
+                force,
+            };
+            let outcome = execute(&graph, &request.tasks, &request, env).remove(0);
+            (outcome.status, outcome.reason)
+        };
+
+        assert_eq!(
+            run(&env, false),
+            (TaskStatus::Succeeded, "no previous run".into())
+        );
+        assert_eq!(
+            run(&env, false),
+            (TaskStatus::UpToDate, "up to date".into())
+        );
+        assert_eq!(run(&env, true), (TaskStatus::Succeeded, "forced".into()));
+
+        std::fs::write(root.join("proto/v1/b.proto"), "b2").unwrap();
+        assert_eq!(
+            run(&env, false),
+            (
+                TaskStatus::Succeeded,
+                "sources changed: proto/v1/b.proto".into()
+            )
+        );
+
+        env.tools.insert("protoc".into(), "28.0".into());
+        assert_eq!(
+            run(&env, false),
+            (
+                TaskStatus::Succeeded,
+                "tool versions changed: protoc".into()
+            )
+        );
+
+        std::fs::remove_file(root.join("gen.out")).unwrap();
+        assert_eq!(
+            run(&env, false),
+            (TaskStatus::Succeeded, "output gen.out is missing".into())
+        );
+        assert_eq!(run(&env, false).0, TaskStatus::UpToDate);
+        assert_eq!(
+            std::fs::read_to_string(root.join("log"))
+                .unwrap()
+                .lines()
+                .count(),
+            5
+        );
+    }
+
+    #[test]
+    fn reruns_tasks_whose_dependency_reran() {
+        let root = Scratch::new("task-fp-deps");
+        let graph = graph(
+            &root,
+            r#"[tasks."docker:check"]
+run = "echo check >> log"
+sources = ["Dockerfile"]
+
+[tasks.docker_check]
+run = "echo other >> log"
+sources = ["Dockerfile"]
+
+[tasks.build]
+run = "echo build >> log"
+depends = ["docker:check"]
+sources = ["src/*.rs"]
+"#,
+        );
+        assert_ne!(
+            graph.fingerprint_path("docker:check"),
+            graph.fingerprint_path("docker_check")
+        );
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src/main.rs"), "fn main() {}").unwrap();
+        std::fs::write(root.join("Dockerfile"), "FROM a").unwrap();
+        let env = TaskEnv {
+            vars: BTreeMap::from([("PATH".to_string(), std::env::var("PATH").unwrap())]),
+            tools: BTreeMap::new(),
+        };
+        let run = |task: &str| {
+            let request = TaskRequest {
+                tasks: vec![task.into()],
+                keep_going: false,
+                jobs: 1,
+                force: false,
+            };
+            let order = graph.plan(&request.tasks).unwrap();
+            execute(&graph, &order, &request, &env)
+                .into_iter()
+                .map(|outcome| (outcome.name, outcome.reason))
+                .collect::<Vec<_>>()
+        };
+
+        run("build");
+        run("docker_check");
+        assert_eq!(run("build")[1].1, "up to date");
+
+        std::fs::write(root.join("Dockerfile"), "FROM b").unwrap();
+        assert_eq!(
+            run("build"),
+            [
+                ("docker:check".into(), "sources changed: Dockerfile".into()),
+                ("build".into(), "dependencies changed: docker:check".into()),
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(root.join("log")).unwrap(),
+            "check\nbuild\nother\ncheck\nbuild\n"
+        );
+    }
+}
@@ -1,7 +1,166 @@
-use crate::system::MacOS;
+use crate::actions::env::install_dir_name;
+use crate::actions::install::version_matches;
+use crate::actions::shim;
+use crate::registries::specs::tool::{Backend, ToolSpec};
+use crate::specs::lock::{Lockfile, project_lockfile_path};
+use crate::system::{MacOS, System};
+use crate::utils::fs::FsUtils;
+use crate::utils::paths::PathOps;
+use crate::utils::transaction::FsTransaction;
+use anyhow::{Context, Result};
+use std::collections::HashSet;
 use std::path::{Path, PathBuf};
 
 pub trait UninstallOps {}
 
+pub struct UninstallRequest {
+    pub tools: Vec<ToolSpec>,
+}
+
+/// One installed version that will be removed.
+#[derive(Debug, Clone)]
+pub struct PlannedRemoval {
+    pub tool: ToolSpec,
+    pub name: String,
+    pub version: String,
+    pub install_path: PathBuf,
+    pub disk_usage: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct UninstallPlan {
+    pub steps: Vec<PlannedRemoval>,
+    /// Requested tools with nothing installed.
+    pub missing: Vec<ToolSpec>,
+}
+
+impl UninstallPlan {
+    pub fn disk_usage(&self) -> u64 {
+        self.steps.iter().map(|s| s.disk_usage).sum()
+    }
+}
+
+/// Find every installed version matched by the requested specs.
+/// `jq` removes all installed versions, `node@20` only matching ones
+/// (including the `node@20` versioned formula).
+pub async fn plan(request: &UninstallRequest) -> Result<UninstallPlan> {
+    let mut plan = UninstallPlan::default();
+    let mut seen = HashSet::new();
+
+    for tool in &request.tools {
+        let mut found = false;
+
+        for (name, version, install_path) in installed_versions(tool)? {
+            found = true;
+            if !seen.insert(install_path.clone()) {
+                continue;
+            }
+
+            let disk_usage = FsUtils::dir_size(&install_path).unwrap_or(0);
+            plan.steps.push(PlannedRemoval {
+                tool: tool.clone(),
+                name,
+                version,
+                install_path,
+                disk_usage,
+            });
+        }
+
+        if !found {
+            plan.missing.push(tool.clone());
+        }
+    }
+
+    Ok(plan)
+}
+
+/// Remove every planned install together with the lock entries of the versions
+/// removed as one transaction, then the shims no remaining install provides.
+pub async fn execute(plan: UninstallPlan) -> Result<Vec<PlannedRemoval>> {
+    let mut transaction = FsTransaction::new();
+    for step in &plan.steps {
+        transaction.stage_removal(&step.install_path);
+    }
+    if let Some(lock_path) = project_lockfile_path() {
+        let mut lockfile = Lockfile::load(&lock_path)?;
+        let mut changed = false;
+        for step in &plan.steps {
+            // The entry stays while it locks a version that is still installed.
+            if lockfile
+                .get(&step.tool)
+                .is_some_and(|locked| locked.version == step.version)
+            {
+                lockfile.remove(&step.tool);
+                changed = true;
+            }
+        }
+        if changed {
+            lockfile.stage(&mut transaction, &lock_path)?;
+        }
+    }
+    transaction.commit()?;
+
+    for step in &plan.steps {
+        remove_empty_parent(&step.install_path).await;
+    }
+    shim::remove_orphaned_shims()?;
+
+    Ok(plan.steps)
+}
+
+fn installed_versions(tool: &ToolSpec) -> Result<Vec<(String, String, PathBuf)>> {
+    installed_versions_in(&System::tool_dir(), tool)
+}
+
+/// Installs of `tool` under `tool_dir`, only from its own backend: `ripgrep` never
+/// matches `cargo:ripgrep`.
+pub(crate) fn installed_versions_in(
+    tool_dir: &Path,
+    tool: &ToolSpec,
+) -> Result<Vec<(String, String, PathBuf)>> {
+    let mut candidates = vec![(install_dir_name(&tool.key()), Some(tool.version.as_str()))];
+    if tool.effective_backend() == Backend::Homebrew && !tool.is_latest() {
+        // `node@20` may have been installed from the `node@20` versioned formula.
+        candidates.push((format!("{}@{}", tool.name, tool.version), None));
+    }
+
+    let mut installed = Vec::new();
+    for (name, version) in candidates {
+        let formula_dir = tool_dir.join(&name);
+        if !formula_dir.is_dir() {
+            continue;
+        }
+
+        let entries = std::fs::read_dir(&formula_dir)
+            .with_context(|| format!("Failed to read {}", formula_dir.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            let installed_version = entry.file_name().to_string_lossy().to_string();
+            if installed_version.starts_with('.') || !entry.file_type()?.is_dir() {
+                continue;
+            }
+
+            let wanted = match version {
+                Some(v) if !v.eq_ignore_ascii_case("latest") => {
+                    version_matches(v, &installed_version)
+                }
+                _ => true,
+            };
+            if wanted {
+                installed.push((name.clone(), installed_version, entry.path()));
+            }
+        }
+    }
+
+    Ok(installed)
+}
+
+async fn remove_empty_parent(install_path: &Path) {
+    if let Some(parent) = install_path.parent() {
+        // Only succeeds when no other versions are left.
+        let _ = tokio::fs::remove_dir(parent).await;
+    }
+}
+
 // MacOS implementation
 impl UninstallOps for MacOS {}
@@ -1 +1,135 @@
+use crate::actions::env::{find_installed, install_dir_name};
+use crate::registries::specs::tool::ToolSpec;
+use crate::specs::config::{self, ConfigScope, LayeredConfig};
+use crate::system::System;
+use crate::utils::paths::PathOps;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
 
+pub struct UseRequest {
+    /// Versions to pin, as requested (`node@20` stays `20`, not `20.11.1`).
+    pub tools: Vec<ToolSpec>,
+    /// Tools whose pin is removed, by key (`node`, `npm:prettier`).
+    pub unset: Vec<String>,
+    /// Write to the global config instead of the nearest `still.toml`.
+    pub global: bool,
+}
+
+/// What `still use` changed in which file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UseResult {
+    pub path: PathBuf,
+    pub pinned: Vec<ToolSpec>,
+    pub unset: Vec<String>,
+}
+
+/// Requested tools with no install that satisfies the requested version.
+pub fn missing(request: &UseRequest) -> Vec<ToolSpec> {
+    missing_in(request, &System::tool_dir())
+}
+
+pub fn missing_in(request: &UseRequest, tool_dir: &Path) -> Vec<ToolSpec> {
+    request
+        .tools
+        .iter()
+        .filter(|tool| {
+            find_installed(tool_dir, &install_dir_name(&tool.key()), &tool.version).is_none()
+        })
+        .cloned()
+        .collect()
+}
+
+/// Write the pins of `request` to the nearest `still.toml`, or the global config
+/// with `global`. Activated shells pick the change up on their next prompt.
+pub fn apply(request: &UseRequest, config: &LayeredConfig) -> Result<UseResult> {
+    let scope = if request.global {
+        ConfigScope::Global
+    } else {
+        ConfigScope::Project
+    };
+    let path = config.scope_path(scope)?;
+    apply_to(request, &path)
+}
+
+pub fn apply_to(request: &UseRequest, path: &Path) -> Result<UseResult> {
+    let mut result = UseResult {
+        path: path.to_path_buf(),
+        pinned: Vec::new(),
+        unset: Vec::new(),
+    };
+
+    for name in &request.unset {
+        if config::remove_in_file(path, &format!("tools.{}", name))? {
+            result.unset.push(name.clone());
+        }
+    }
+
+    for tool in &request.tools {
+        let key = format!("tools.{}", tool.key());
+        // `[tools.rust]` tables keep their options; only the version changes.
+        let key = if is_table(path, &key)? {
+            format!("{}.version", key)
+        } else {
+            key
+        };
+        config::set_in_file(path, &key, toml::Value::String(tool.version.clone()))?;
+        result.pinned.push(tool.clone());
+    }
+    Ok(result)
+}
+
+fn is_table(path: &Path, key: &str) -> Result<bool> {
+    if !path.exists() {
+        return Ok(false);
+    }
+    let content = std::fs::read_to_string(path)?;
+    let mut value = toml::Value::Table(toml::from_str(&content)?);
+    for part in key.split('.') {
+        match value.get(part) {
+            Some(inner) => value = inner.clone(),
+            None => return Ok(false),
+        }
+    }
+    Ok(value.is_table())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch::Scratch;
+
+    #[test]
+    fn pins_and_unsets_in_place() {
+        let root = Scratch::new("use");
+        std::fs::create_dir_all(root.join("tools/node/20.11.1")).unwrap();
+        let manifest = root.join("still.toml");
+        std::fs::write(
+            &manifest,
+            "[tools]\nnode = \"18\" # lts\ngo = \"1.22\"\n\n[tools.rust]\nversion = \"stable\"\ncomponents = [\"clippy\"]\n",
+        )
+        .unwrap();
+
+        let request = UseRequest {
+            tools: vec!["node@20".parse().unwrap(), "rust@1.78".parse().unwrap()],
+            unset: vec!["go".into(), "deno".into()],
+            global: false,
+        };
+        assert_eq!(
+            missing_in(&request, &root.join("tools")),
+            vec!["rust@1.78".parse().unwrap()]
+        );
+
+        let result = apply_to(&request, &manifest).unwrap();
+        assert_eq!(result.unset, vec!["go".to_string()]);
+
+        let content = std::fs::read_to_string(&manifest).unwrap();
+        let parsed: toml::Table = toml::from_str(&content).unwrap();
+        assert_eq!(parsed["tools"]["node"].as_str(), Some("20"));
+        assert_eq!(parsed["tools"]["rust"]["version"].as_str(), Some("1.78"));
+        assert_eq!(
+            parsed["tools"]["rust"]["components"][0].as_str(),
+            Some("clippy")
+        );
+        assert!(parsed["tools"].get("go").is_none());
+    }
+}
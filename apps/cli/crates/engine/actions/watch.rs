@@ -0,0 +1,294 @@
+use crate::utils::glob::Glob;
+use crate::utils::ignore::IgnoreRules;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::os::unix::process::CommandExt;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the watched files are checked.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// How long changes have to settle before a restart, so a save touching many
+/// files restarts once.
+pub const DEBOUNCE: Duration = Duration::from_millis(200);
+
+/// How long a process group gets to exit after `SIGTERM` before `SIGKILL`.
+pub const STOP_GRACE: Duration = Duration::from_secs(5);
+
+/// Set by `SIGINT`/`SIGTERM` once [`interrupt_flag`] installed its handlers.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Files under a root matching a set of globs, outside ignored paths, and the
+/// modification time and size each had when last looked at.
+#[derive(Debug, Clone)]
+pub struct Watcher {
+    pub root: PathBuf,
+    pub patterns: Vec<String>,
+    pub ignore: IgnoreRules,
+    snapshot: BTreeMap<String, (Option<SystemTime>, u64)>,
+}
+
+impl Watcher {
+    /// Watch `patterns` under `root`, honouring its ignore files.
+    pub fn new(root: &Path, patterns: Vec<String>) -> Result<Self> {
+        let mut watcher = Self {
+            root: root.to_path_buf(),
+            patterns,
+            ignore: IgnoreRules::load(root),
+            snapshot: BTreeMap::new(),
+        };
+        watcher.snapshot = watcher.scan()?;
+        Ok(watcher)
+    }
+
+    fn scan(&self) -> Result<BTreeMap<String, (Option<SystemTime>, u64)>> {
+        let files = Glob::walk(&self.root, &self.patterns, |path, is_dir| {
+            self.ignore.is_ignored(path, is_dir)
+        })
+        .with_context(|| format!("Failed to scan {}", self.root.display()))?;
+        Ok(files
+            .into_iter()
+            .map(|file| {
+                let metadata = std::fs::metadata(self.root.join(&file)).ok();
+                let modified = metadata.as_ref().and_then(|m| m.modified().ok());
+                let len = metadata.map_or(0, |m| m.len());
+                (file, (modified, len))
+            })
+            .collect())
+    }
+
+    /// Files added, changed or removed since the last call.
+    pub fn changes(&mut self) -> Result<Vec<String>> {
+        let current = self.scan()?;
+        let mut changed: Vec<String> = current
+            .iter()
+            .filter(|(file, state)| self.snapshot.get(*file) != Some(state))
+            .map(|(file, _)| file.clone())
+            .chain(
+                self.snapshot
+                    .keys()
+                    .filter(|file| !current.contains_key(*file))
+                    .cloned(),
+            )
+            .collect();
+        changed.sort();
+        self.snapshot = current;
+        Ok(changed)
+    }
+
+    /// Block until files change and then stay unchanged for `debounce`, polling
+    /// every `poll`. `None` when `until` returns true first.
+    pub fn wait(
+        &mut self,
+        poll: Duration,
+        debounce: Duration,
+        mut until: impl FnMut() -> bool,
+    ) -> Result<Option<Vec<String>>> {
+        let mut changed: Vec<String> = Vec::new();
+        let mut last_change = Instant::now();
+        loop {
+            if until() {
+                return Ok(None);
+            }
+            let batch = self.changes()?;
+            if !batch.is_empty() {
+                changed.extend(batch);
+                last_change = Instant::now();
+            } else if !changed.is_empty() && last_change.elapsed() >= debounce {
+                changed.sort();
+                changed.dedup();
+                return Ok(Some(changed));
+            }
+            std::thread::sleep(if changed.is_empty() {
+                poll
+            } else {
+                poll.min(debounce)
+            });
+        }
+    }
+}
+
+/// A child started as the leader of a new process group, so stopping it also
+/// stops everything it started.
+#[derive(Debug)]
+pub struct ProcessGroup {
+    child: Child,
+}
+
+impl ProcessGroup {
+    pub fn spawn(command: &mut Command) -> Result<Self> {
+        let child = command
+            .process_group(0)
+            .spawn()
+            .with_context(|| format!("Failed to start {:?}", command.get_program()))?;
+        Ok(Self { child })
+    }
+
+    /// Pid of the leader, which is also the id of the group.
+    pub fn id(&self) -> u32 {
+        self.child.id()
+    }
+
+    pub fn try_wait(&mut self) -> Option<ExitStatus> {
+        self.child.try_wait().ok().flatten()
+    }
+
+    /// `SIGTERM` the whole group and wait for it to exit, `SIGKILL` whatever is
+    /// left after `grace`.
+    pub fn stop(&mut self, grace: Duration) -> Result<()> {
+        let group = -(self.child.id() as libc::pid_t);
+        // SAFETY: `kill` only sends a signal; a stale group yields `ESRCH`.
+        unsafe { libc::kill(group, libc::SIGTERM) };
+
+        let deadline = Instant::now() + grace;
+        loop {
+            let leader_done = self.try_wait().is_some();
+            // SAFETY: signal 0 only checks whether the group still has members.
+            let group_alive = unsafe { libc::kill(group, 0) } == 0;
+            if leader_done && !group_alive {
+                return Ok(());
+            }
+            if Instant::now() >= deadline {
+                break;
+            }
+            std::thread::sleep(Duration::from_millis(50));
+        }
+
+        // SAFETY: as above.
+        unsafe { libc::kill(group, libc::SIGKILL) };
+        self.child
+            .wait()
+            .context("Failed to wait for the stopped process")?;
+        Ok(())
+    }
+}
+
+extern "C" fn on_interrupt(_: libc::c_int) {
+    INTERRUPTED.store(true, Ordering::SeqCst);
+}
+
+/// Turn `SIGINT` and `SIGTERM` into a flag, so the process can stop its children
+/// before it exits.
+pub fn interrupt_flag() -> &'static AtomicBool {
+    // SAFETY: the handler only stores to an atomic.
+    unsafe {
+        libc::signal(
+            libc::SIGINT,
+            on_interrupt as *const () as libc::sighandler_t,
+        );
+        libc::signal(
+            libc::SIGTERM,
+            on_interrupt as *const () as libc::sighandler_t,
+        );
+    }
+    &INTERRUPTED
+}
+
+/// Run `command` in its own process group and restart it whenever `watcher`
+/// reports changes, until `SIGINT` or `SIGTERM`. A command that exits on its own
+/// runs again on the next change.
+pub fn watch(watcher: &mut Watcher, mut command: impl FnMut() -> Command) -> Result<()> {
+    // The children are not in the terminal's foreground group, so Ctrl-C only
+    // reaches `still`, which stops them.
+    let interrupted = interrupt_flag();
+
+    let mut group = ProcessGroup::spawn(&mut command())?;
+    let mut reported = false;
+    loop {
+        let changed = watcher.wait(POLL_INTERVAL, DEBOUNCE, || {
+            if !reported {
+                if let Some(status) = group.try_wait() {
+                    reported = true;
+                    println!("[watch] exited with {}, waiting for changes", status);
+                }
+            }
+            interrupted.load(Ordering::SeqCst)
+        })?;
+        let Some(changed) = changed else {
+            return group.stop(STOP_GRACE);
+        };
+
+        let shown = changed
+            .iter()
+            .take(3)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(", ");
+        let more = match changed.len().saturating_sub(3) {
+            0 => String::new(),
+            n => format!(" and {} more", n),
+        };
+        println!("[watch] {}{} changed, restarting", shown, more);
+        group.stop(STOP_GRACE)?;
+        group = ProcessGroup::spawn(&mut command())?;
+        reported = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch::Scratch;
+
+    #[test]
+    fn reports_changes_outside_ignored_paths() {
+        let root = Scratch::new("watch");
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::create_dir_all(root.join("gen")).unwrap();
+        std::fs::write(root.join(".gitignore"), "gen/\n").unwrap();
+        std::fs::write(root.join("src/a.rs"), "a").unwrap();
+
+        let patterns = vec!["src/**".to_string(), "gen/**".to_string()];
+        let mut watcher = Watcher::new(&root, patterns).unwrap();
+        assert!(watcher.changes().unwrap().is_empty());
+
+        std::fs::write(root.join("gen/out.rs"), "generated").unwrap();
+        std::fs::write(root.join("src/b.rs"), "b").unwrap();
+        std::fs::write(root.join("src/a.rs"), "a changed").unwrap();
+        let changed = watcher
+            .wait(Duration::from_millis(10), Duration::from_millis(30), || {
+                false
+            })
+            .unwrap();
+        assert_eq!(changed, Some(vec!["src/a.rs".into(), "src/b.rs".into()]));
+
+        std::fs::remove_file(root.join("src/b.rs")).unwrap();
+        assert_eq!(watcher.changes().unwrap(), vec!["src/b.rs".to_string()]);
+        assert_eq!(
+            watcher
+                .wait(Duration::from_millis(10), Duration::ZERO, || true)
+                .unwrap(),
+            None
+        );
+    }
+
+    #[test]
+    fn stops_the_whole_process_group() {
+        let mut command = Command::new("sh");
+        command
+            .arg("-c")
+            .arg("sleep 30 & sleep 30 & echo $! ; wait");
+        command.stdout(std::process::Stdio::piped());
+        let mut group = ProcessGroup::spawn(&mut command).unwrap();
+
+        let mut line = String::new();
+        let stdout = group.child.stdout.take().unwrap();
+        std::io::BufRead::read_line(&mut std::io::BufReader::new(stdout), &mut line).unwrap();
+        let grandchild: libc::pid_t = line.trim().parse().unwrap();
+
+        group.stop(Duration::from_secs(2)).unwrap();
+        // Gone, or a zombie waiting for init to reap it.
+        let ps = Command::new("ps")
+            .args(["-o", "stat=", "-p", &grandchild.to_string()])
+            .output()
+            .unwrap();
+        let state = String::from_utf8_lossy(&ps.stdout);
+        assert!(
+            state.trim().is_empty() || state.trim().starts_with('Z'),
+            "{state}"
+        );
+    }
+}
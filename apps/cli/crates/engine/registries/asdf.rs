@@ -0,0 +1,455 @@
+use crate::actions::install::{version_key, version_matches};
+use crate::registries::{Release, fetch_text};
+use crate::specs::config::LayeredConfig;
+use crate::specs::tool::ToolSpec;
+use crate::system::System;
+use crate::utils::paths::PathOps;
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use tokio::process::Command;
+
+/// The community plugin index; `plugins/<name>` holds `repository = <git url>`.
+pub const DEFAULT_INDEX_URL: &str =
+    "https://raw.githubusercontent.com/asdf-vm/asdf-plugins/master/plugins";
+
+/// File in a plugin directory recording the source it was cloned from.
+const PLUGIN_SOURCE_FILE: &str = ".still-source";
+
+/// Tools installed by an asdf (or mise) plugin: `asdf:terraform@1.9`.
+///
+/// The plugin comes from the tool's `plugin` option (a git URL or a local
+/// directory), or from the community index at `settings.asdf.index_url`. It is
+/// cloned into `root_dir/asdf/plugins/<name>`, and cloned again when that source
+/// changes. Versions are listed with
+/// `bin/list-all` (or `bin/latest-stable` for `latest`), and installs run
+/// `bin/download` and `bin/install` with the usual `ASDF_INSTALL_*` variables.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsdfRegistry {
+    pub index_url: String,
+    /// Where the plugin is cloned from; looked up in the index when absent.
+    pub plugin: Option<String>,
+    pub plugins_dir: PathBuf,
+    /// Where the install ends up once committed. Plugins write this path into
+    /// the files they install, so they run against it rather than the staging
+    /// directory.
+    pub install_path: Option<PathBuf>,
+}
+
+impl AsdfRegistry {
+    pub fn new(index_url: impl Into<String>, plugins_dir: PathBuf) -> Self {
+        Self {
+            index_url: index_url.into().trim_end_matches('/').to_string(),
+            plugin: None,
+            plugins_dir,
+            install_path: None,
+        }
+    }
+
+    pub fn from_config(config: &LayeredConfig, tool: &ToolSpec) -> Result<Self> {
+        let mut registry = Self::new(
+            config
+                .get_str("settings.asdf.index_url")
+                .unwrap_or(DEFAULT_INDEX_URL),
+            System::root_dir().join("asdf").join("plugins"),
+        );
+        registry.plugin = config
+            .tool_options(tool)?
+            .and_then(|options| options.plugin);
+        Ok(registry)
+    }
+
+    /// Where `name`'s plugin is cloned from.
+    async fn plugin_source(&self, name: &str) -> Result<String> {
+        if let Some(plugin) = &self.plugin {
+            return Ok(plugin.clone());
+        }
+
+        let url = format!("{}/{}", self.index_url, name);
+        let entry = fetch_text(&url)
+            .await
+            .with_context(|| format!("No asdf plugin named {} in the plugin index", name))?;
+        parse_index_entry(&entry)
+            .ok_or_else(|| anyhow::anyhow!("Plugin index entry {} has no repository", url))
+    }
+
+    /// The plugin directory for `name`, cloning or copying it from `source` on
+    /// first use or when it was cloned from somewhere else.
+    pub async fn ensure_plugin(&self, name: &str, source: &str) -> Result<PathBuf> {
+        let dir = self.plugins_dir.join(name);
+        let cloned_from = std::fs::read_to_string(dir.join(PLUGIN_SOURCE_FILE)).ok();
+        if dir.join("bin").is_dir() && cloned_from.as_deref() == Some(source) {
+            return Ok(dir);
+        }
+
+        tokio::fs::create_dir_all(&self.plugins_dir)
+            .await
+            .with_context(|| format!("Failed to create {}", self.plugins_dir.display()))?;
+        let _ = tokio::fs::remove_dir_all(&dir).await;
+
+        let local = Path::new(&source);
+        if local.is_dir() && !local.join(".git").exists() {
+            copy_dir(local, &dir)?;
+        } else {
+            println!("Cloning asdf plugin {} from {}...", name, source);
+            let status = Command::new("git")
+                .args(["clone", "--quiet", "--depth", "1", source])
+                .arg(&dir)
+                .status()
+                .await
+                .context("Failed to run git")?;
+            if !status.success() {
+                let _ = tokio::fs::remove_dir_all(&dir).await;
+                anyhow::bail!("Failed to clone asdf plugin {} from {}", name, source);
+            }
+        }
+
+        if !dir.join("bin/list-all").is_file() || !dir.join("bin/install").is_file() {
+            anyhow::bail!(
+                "{} is not an asdf plugin (missing bin/list-all or bin/install)",
+                source
+            );
+        }
+        std::fs::write(dir.join(PLUGIN_SOURCE_FILE), source)
+            .with_context(|| format!("Failed to write {}", dir.display()))?;
+        Ok(dir)
+    }
+
+    pub async fn resolve(&self, tool: &ToolSpec) -> Result<Release> {
+        let source = self.plugin_source(&tool.name).await?;
+        let plugin = self.ensure_plugin(&tool.name, &source).await?;
+
+        let latest_stable = plugin.join("bin/latest-stable");
+        let version = if tool.version == "latest" && latest_stable.is_file() {
+            let output = run_plugin_script(&latest_stable, &[]).await?;
+            output.split_whitespace().last().map(str::to_string)
+        } else {
+            let output = run_plugin_script(&plugin.join("bin/list-all"), &[]).await?;
+            let versions: Vec<&str> = output.split_whitespace().collect();
+            select(&versions, &tool.version).map(str::to_string)
+        }
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "The {} plugin lists no version matching {}",
+                tool.name,
+                tool.version
+            )
+        })?;
+
+        Ok(Release {
+            name: tool.name.clone(),
+            version,
+            url: source,
+            sha256: None,
+            integrity: None,
+            size: None,
+            bins: Vec::new(),
+            from_source: false,
+        })
+    }
+
+    /// Install `release` into `dest`. `release.url` is the plugin source.
+    ///
+    /// With an [`install_path`](Self::install_path) the plugin installs there and
+    /// the result is moved into `dest`, to be moved back when the transaction
+    /// commits. A version already installed there is set aside meanwhile, and
+    /// whatever a failed plugin left behind is removed.
+    pub async fn install(&self, release: &Release, dest: &Path) -> Result<()> {
+        let Some(install_path) = self.install_path.as_deref().filter(|path| *path != dest) else {
+            return self.install_at(release, dest).await;
+        };
+
+        let previous = install_path.with_file_name(format!(".{}.previous", release.version));
+        if install_path.exists() {
+            let _ = tokio::fs::remove_dir_all(&previous).await;
+            tokio::fs::rename(install_path, &previous)
+                .await
+                .with_context(|| format!("Failed to move aside {}", install_path.display()))?;
+        }
+
+        let mut result = self.install_at(release, install_path).await;
+        if result.is_ok() {
+            result = async {
+                tokio::fs::remove_dir_all(dest).await?;
+                tokio::fs::rename(install_path, dest).await
+            }
+            .await
+            .with_context(|| format!("Failed to stage {}", install_path.display()));
+        }
+        if result.is_err() {
+            let _ = tokio::fs::remove_dir_all(install_path).await;
+        }
+        if previous.exists() {
+            let _ = tokio::fs::rename(&previous, install_path).await;
+        }
+        result
+    }
+
+    /// Run the plugin's `bin/download` and `bin/install` into `dest`, then link the
+    /// directories from `bin/list-bin-paths` into `dest/bin`.
+    async fn install_at(&self, release: &Release, dest: &Path) -> Result<()> {
+        let plugin = self.ensure_plugin(&release.name, &release.url).await?;
+        let download = dest.join(".download");
+        tokio::fs::create_dir_all(&download)
+            .await
+            .with_context(|| format!("Failed to create {}", download.display()))?;
+
+        let concurrency = std::thread::available_parallelism()
+            .map_or(1, |n| n.get())
+            .to_string();
+        let env = [
+            ("ASDF_INSTALL_TYPE", "version".as_ref()),
+            ("ASDF_INSTALL_VERSION", release.version.as_ref()),
+            ("ASDF_INSTALL_PATH", dest.as_os_str()),
+            ("ASDF_DOWNLOAD_PATH", download.as_os_str()),
+            ("ASDF_PLUGIN_PATH", plugin.as_os_str()),
+            ("ASDF_CONCURRENCY", concurrency.as_ref()),
+        ];
+
+        println!(
+            "Installing {} {} with its asdf plugin...",
+            release.name, release.version
+        );
+        let result = async {
+            for script in ["download", "install"] {
+                let path = plugin.join("bin").join(script);
+                if script == "download" && !path.is_file() {
+                    continue;
+                }
+                run_plugin_script(&path, &env)
+                    .await
+                    .with_context(|| format!("asdf plugin {} failed", script))?;
+            }
+            Ok::<_, anyhow::Error>(())
+        }
+        .await;
+        let _ = tokio::fs::remove_dir_all(&download).await;
+        result?;
+
+        let list_bin_paths = plugin.join("bin/list-bin-paths");
+        if list_bin_paths.is_file() {
+            let output = run_plugin_script(&list_bin_paths, &env).await?;
+            link_bin_paths(dest, output.split_whitespace())?;
+        }
+        Ok(())
+    }
+}
+
+/// The `repository` of a plugin index entry.
+pub fn parse_index_entry(entry: &str) -> Option<String> {
+    entry.lines().find_map(|line| {
+        let (key, value) = line.split_once('=')?;
+        (key.trim() == "repository").then(|| value.trim().to_string())
+    })
+}
+
+/// An exact match, else the newest stable version matching `requested`.
+pub fn select<'a>(versions: &[&'a str], requested: &str) -> Option<&'a str> {
+    if let Some(exact) = versions.iter().find(|v| **v == requested) {
+        return Some(exact);
+    }
+
+    versions
+        .iter()
+        .copied()
+        .filter(|v| !is_prerelease(v))
+        .filter(|v| requested == "latest" || version_matches(requested, v))
+        .max_by_key(|v| version_key(v))
+}
+
+fn is_prerelease(version: &str) -> bool {
+    let version = version.to_ascii_lowercase();
+    version.contains('-')
+        || ["alpha", "beta", "rc", "dev", "pre", "nightly"]
+            .iter()
+            .any(|tag| version.contains(tag))
+}
+
+async fn run_plugin_script(script: &Path, env: &[(&str, &std::ffi::OsStr)]) -> Result<String> {
+    let output = Command::new(script)
+        .envs(env.iter().copied())
+        .stdin(std::process::Stdio::null())
+        .stderr(std::process::Stdio::inherit())
+        .output()
+        .await
+        .with_context(|| format!("Failed to run {}", script.display()))?;
+    if !output.status.success() {
+        anyhow::bail!("{} exited with {}", script.display(), output.status);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Symlink every executable in the plugin's bin paths into `dest/bin`, so they
+/// are linked like any other tool's. `bin` itself is left as it is.
+fn link_bin_paths<'a>(dest: &Path, bin_paths: impl Iterator<Item = &'a str>) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let bin_dir = dest.join("bin");
+    for bin_path in bin_paths.filter(|p| *p != "bin") {
+        let Ok(entries) = std::fs::read_dir(dest.join(bin_path)) else {
+            continue;
+        };
+        std::fs::create_dir_all(&bin_dir)
+            .with_context(|| format!("Failed to create {}", bin_dir.display()))?;
+        for entry in entries {
+            let entry = entry?;
+            let link = bin_dir.join(entry.file_name());
+            let executable = entry
+                .metadata()
+                .is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0);
+            if !executable || link.exists() {
+                continue;
+            }
+            let target = Path::new("..").join(bin_path).join(entry.file_name());
+            std::os::unix::fs::symlink(&target, &link)
+                .with_context(|| format!("Failed to link {}", link.display()))?;
+        }
+    }
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    std::fs::create_dir_all(to).with_context(|| format!("Failed to create {}", to.display()))?;
+    for entry in
+        std::fs::read_dir(from).with_context(|| format!("Failed to read {}", from.display()))?
+    {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            std::fs::copy(entry.path(), &target)
+                .with_context(|| format!("Failed to copy {}", entry.path().display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::net::fixture;
+    use crate::utils::scratch::Scratch;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn script(path: &Path, body: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, format!("#!/bin/sh\nset -e\n{}", body)).unwrap();
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)).unwrap();
+    }
+
+    #[test]
+    fn selects_versions_like_asdf() {
+        let versions = ["1.8.5", "1.9.0", "1.9.8", "1.10.0-rc1", "1.10.0-beta2"];
+        assert_eq!(select(&versions, "latest"), Some("1.9.8"));
+        assert_eq!(select(&versions, "1.9"), Some("1.9.8"));
+        assert_eq!(select(&versions, "1.10.0-rc1"), Some("1.10.0-rc1"));
+        assert_eq!(select(&versions, "2"), None);
+        assert_eq!(
+            parse_index_entry(
+                "repository = https://github.com/asdf-community/asdf-hashicorp.git\n"
+            ),
+            Some("https://github.com/asdf-community/asdf-hashicorp.git".into())
+        );
+    }
+
+    /// A plugin whose `hello` prints a file by its absolute path under
+    /// `$ASDF_INSTALL_PATH`.
+    fn hello_plugin(root: &Path) -> PathBuf {
+        let source = root.join("asdf-hello");
+        script(&source.join("bin/list-all"), "echo 0.9.0 1.0.0 1.1.0-rc1\n");
+        script(
+            &source.join("bin/download"),
+            "echo \"hello $ASDF_INSTALL_VERSION\" > \"$ASDF_DOWNLOAD_PATH/greeting\"\n",
+        );
+        script(
+            &source.join("bin/install"),
+            "mkdir -p \"$ASDF_INSTALL_PATH/libexec\"\n\
+             printf '#!/bin/sh\\ncat %s/greeting\\n' \"$ASDF_INSTALL_PATH\" > \"$ASDF_INSTALL_PATH/libexec/hello\"\n\
+             cp \"$ASDF_DOWNLOAD_PATH/greeting\" \"$ASDF_INSTALL_PATH/greeting\"\n\
+             chmod +x \"$ASDF_INSTALL_PATH/libexec/hello\"\n",
+        );
+        script(&source.join("bin/list-bin-paths"), "echo libexec\n");
+        source
+    }
+
+    #[test]
+    fn installs_with_a_local_plugin() {
+        let root = Scratch::new("asdf");
+        let source = hello_plugin(&root);
+
+        let mut registry = AsdfRegistry::new("http://127.0.0.1:9", root.join("plugins"));
+        registry.plugin = Some(source.display().to_string());
+
+        let dest = root.join("tools/hello/1.0.0");
+        std::fs::create_dir_all(&dest).unwrap();
+        fixture::block_on(async {
+            let release = registry
+                .resolve(&"asdf:hello".parse().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(release.version, "1.0.0");
+            registry.install(&release, &dest).await.unwrap();
+        });
+
+        assert!(root.join("plugins/hello/bin/install").is_file());
+        assert!(!dest.join(".download").exists());
+        let output = std::process::Command::new(dest.join("bin/hello"))
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "hello 1.0.0\n");
+
+        // Pointing the tool at another plugin replaces the cached one.
+        let fork = root.join("asdf-hello-fork");
+        copy_dir(&source, &fork).unwrap();
+        script(&fork.join("bin/list-all"), "echo 2.0.0\n");
+        registry.plugin = Some(fork.display().to_string());
+        let release = fixture::block_on(registry.resolve(&"asdf:hello".parse().unwrap())).unwrap();
+        assert_eq!(release.version, "2.0.0");
+    }
+
+    #[test]
+    fn plugins_install_against_the_final_path() {
+        use crate::utils::transaction::FsTransaction;
+
+        let root = Scratch::new("asdf-final");
+        let source = hello_plugin(&root);
+        let mut registry = AsdfRegistry::new("http://127.0.0.1:9", root.join("plugins"));
+        registry.plugin = Some(source.display().to_string());
+        let target = root.join("tools/asdf-hello/1.0.0");
+        registry.install_path = Some(target.clone());
+        let release = fixture::block_on(registry.resolve(&"asdf:hello".parse().unwrap())).unwrap();
+
+        // A failing plugin leaves the version already installed as it was.
+        std::fs::create_dir_all(&target).unwrap();
+        std::fs::write(target.join("old"), "").unwrap();
+        let broken = root.join("asdf-broken");
+        copy_dir(&source, &broken).unwrap();
+        script(
+            &broken.join("bin/install"),
+            "touch \"$ASDF_INSTALL_PATH/partial\"\nexit 1\n",
+        );
+        let failing = Release {
+            url: broken.display().to_string(),
+            ..release.clone()
+        };
+        let mut transaction = FsTransaction::new();
+        let staging = transaction.stage_install(&target).unwrap();
+        assert!(fixture::block_on(registry.install(&failing, &staging)).is_err());
+        drop(transaction);
+        assert!(target.join("old").is_file());
+        assert!(!target.join("partial").exists());
+
+        let mut transaction = FsTransaction::new();
+        let staging = transaction.stage_install(&target).unwrap();
+        fixture::block_on(registry.install(&release, &staging)).unwrap();
+        assert!(target.join("old").is_file());
+        transaction.commit().unwrap();
+
+        assert!(!target.join("old").exists());
+        let output = std::process::Command::new(target.join("bin/hello"))
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "hello 1.0.0\n");
+    }
+}
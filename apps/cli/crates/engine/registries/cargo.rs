@@ -0,0 +1,724 @@
+use crate::actions::env::find_installed;
+use crate::actions::install::version_matches;
+use crate::registries::{Release, fetch_bytes, fetch_text, target_triple, url_exists};
+use crate::specs::config::LayeredConfig;
+use crate::specs::tool::ToolSpec;
+use crate::system::System;
+use crate::utils::archive::{ArchiveExtractor, ArchiveFormat};
+use crate::utils::hashing::Hashing;
+use crate::utils::paths::PathOps;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// The crates.io web API.
+pub const DEFAULT_API_URL: &str = "https://crates.io/api/v1";
+
+/// Rust CLIs published on crates.io, installed into `tool_dir/cargo-<crate>/<version>`.
+///
+/// A prebuilt release asset for the host triple is preferred. Its location comes
+/// from `[package.metadata.binstall]` in the crate's `Cargo.toml` (`pkg-url`,
+/// `pkg-fmt`, per-target `overrides`), or from the usual GitHub release naming
+/// schemes cargo-binstall also tries, and it must publish a `.sha256`. Without one,
+/// the verified `.crate` is built with `cargo install --root` using the
+/// Still-managed Rust toolchain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CargoRegistry {
+    pub api_url: String,
+    /// Install path of the Rust toolchain that builds from source.
+    pub rust_path: Option<PathBuf>,
+    /// Build with the crate's own `Cargo.lock` (`cargo install --locked`).
+    pub locked: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CrateInfo {
+    #[serde(rename = "crate")]
+    pub krate: CrateSummary,
+    #[serde(default)]
+    pub versions: Vec<CrateVersion>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CrateSummary {
+    pub name: String,
+    #[serde(default)]
+    pub repository: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CrateVersion {
+    pub num: String,
+    #[serde(default)]
+    pub yanked: bool,
+    #[serde(default)]
+    pub checksum: String,
+    #[serde(default)]
+    pub bin_names: Vec<String>,
+}
+
+/// `[package.metadata.binstall]`, plus `overrides.<target>` with the same keys.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub struct BinstallMeta {
+    #[serde(default)]
+    pub pkg_url: Option<String>,
+    #[serde(default)]
+    pub pkg_fmt: Option<String>,
+    #[serde(default)]
+    pub overrides: std::collections::BTreeMap<String, BinstallMeta>,
+}
+
+/// The parts of a crate's `Cargo.toml` that locate its prebuilt binaries.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CrateManifest {
+    pub repository: Option<String>,
+    pub binstall: BinstallMeta,
+}
+
+/// How a prebuilt asset is packaged (`pkg-fmt`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PkgFmt {
+    Tgz,
+    Zip,
+    Bin,
+}
+
+impl BinstallMeta {
+    /// The `pkg-url` template for `target`, from its override or the crate-wide one.
+    pub fn pkg_url(&self, target: &str) -> Option<&String> {
+        self.overrides
+            .get(target)
+            .and_then(|o| o.pkg_url.as_ref())
+            .or(self.pkg_url.as_ref())
+    }
+}
+
+impl PkgFmt {
+    pub fn parse(fmt: &str) -> Option<Self> {
+        match fmt {
+            "tgz" | "tar.gz" => Some(PkgFmt::Tgz),
+            "zip" => Some(PkgFmt::Zip),
+            "bin" => Some(PkgFmt::Bin),
+            _ => None,
+        }
+    }
+
+    fn suffix(&self) -> &'static str {
+        match self {
+            PkgFmt::Tgz => ".tar.gz",
+            PkgFmt::Zip => ".zip",
+            PkgFmt::Bin => "",
+        }
+    }
+}
+
+impl CargoRegistry {
+    pub fn new(api_url: impl Into<String>, rust_path: Option<PathBuf>) -> Self {
+        Self {
+            api_url: api_url.into().trim_end_matches('/').to_string(),
+            rust_path,
+            locked: false,
+        }
+    }
+
+    /// Uses the installed Rust toolchain matching the configured `rust` version,
+    /// or the newest installed one. `settings.cargo.locked` sets the default for `--locked`.
+    pub fn from_config(config: &LayeredConfig) -> Self {
+        let rust_version = config
+            .tool_versions()
+            .get("rust")
+            .map_or_else(|| "latest".to_string(), |v| v.to_string());
+        let rust_path = find_installed(&System::tool_dir(), "rust", &rust_version).map(|(_, p)| p);
+
+        let mut registry = Self::new(
+            config
+                .get_str("settings.cargo.api_url")
+                .unwrap_or(DEFAULT_API_URL),
+            rust_path,
+        );
+        registry.locked = config.get_bool("settings.cargo.locked").unwrap_or(false);
+        registry
+    }
+
+    pub async fn crate_info(&self, name: &str) -> Result<CrateInfo> {
+        let url = format!("{}/crates/{}", self.api_url, name);
+        let body = fetch_text(&url).await?;
+        serde_json::from_str(&body).with_context(|| format!("Failed to parse {}", url))
+    }
+
+    fn crate_url(&self, name: &str, version: &str) -> String {
+        format!("{}/crates/{}/{}/download", self.api_url, name, version)
+    }
+
+    pub async fn resolve(&self, tool: &ToolSpec) -> Result<Release> {
+        let info = self.crate_info(&tool.name).await?;
+        let selected = select(&info.versions, &tool.version).ok_or_else(|| {
+            anyhow::anyhow!(
+                "No published version of {} matches {}",
+                tool.name,
+                tool.version
+            )
+        })?;
+
+        let bin_names = if selected.bin_names.is_empty() {
+            vec![tool.name.clone()]
+        } else {
+            selected.bin_names.clone()
+        };
+        let bins = bin_names.iter().map(|bin| format!("bin/{}", bin)).collect();
+
+        // Planning reads only the API metadata. A `pkg-url` in the crate's own
+        // `Cargo.toml` is tried by `install`, which downloads the `.crate` anyway.
+        let manifest = CrateManifest {
+            repository: info.krate.repository,
+            binstall: BinstallMeta::default(),
+        };
+        let triple = target_triple(std::env::consts::OS, std::env::consts::ARCH)?;
+        if let Some((url, sha256)) =
+            find_prebuilt(&manifest, &tool.name, &selected.num, triple).await
+        {
+            return Ok(Release {
+                name: tool.name.clone(),
+                version: selected.num.clone(),
+                url,
+                sha256: Some(sha256),
+                integrity: None,
+                size: None,
+                bins,
+                from_source: false,
+            });
+        }
+
+        Ok(Release {
+            name: tool.name.clone(),
+            version: selected.num.clone(),
+            url: self.crate_url(&tool.name, &selected.num),
+            sha256: Some(selected.checksum.clone()),
+            integrity: None,
+            size: None,
+            bins,
+            from_source: true,
+        })
+    }
+
+    /// Unpack a prebuilt asset, or build the `.crate` when `release` is a source build.
+    pub async fn install(&self, release: &Release, dest: &Path) -> Result<()> {
+        let data = fetch_verified(release).await?;
+        if !release.from_source {
+            return install_prebuilt(release, &data, dest).await;
+        }
+
+        let manifest = read_crate_manifest(&data)?;
+        let triple = target_triple(std::env::consts::OS, std::env::consts::ARCH)?;
+        let prebuilt = match manifest.binstall.pkg_url(triple) {
+            Some(_) => find_prebuilt(&manifest, &release.name, &release.version, triple).await,
+            None => None,
+        };
+        if let Some((url, sha256)) = prebuilt {
+            let prebuilt = Release {
+                url,
+                sha256: Some(sha256),
+                from_source: false,
+                ..release.clone()
+            };
+            let data = fetch_verified(&prebuilt).await?;
+            return install_prebuilt(&prebuilt, &data, dest).await;
+        }
+        self.build_from_source(release, &data, dest).await
+    }
+
+    async fn build_from_source(&self, release: &Release, data: &[u8], dest: &Path) -> Result<()> {
+        let rust = self.rust_path.as_ref().ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} has no prebuilt binary for this platform and needs a Still-managed Rust \
+                 toolchain to build. Run `still install rust` first",
+                release.name
+            )
+        })?;
+
+        let source = dest.join(".source");
+        ArchiveExtractor::extract_tar_gz(data, &source)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to extract {}: {e}", release.url))?;
+
+        println!(
+            "Building {} {} from source...",
+            release.name, release.version
+        );
+        let rust_bin = rust.join("bin");
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        let path = std::env::join_paths(
+            std::iter::once(rust_bin.clone()).chain(std::env::split_paths(&path)),
+        )?;
+        let status = tokio::process::Command::new(rust_bin.join("cargo"))
+            .args(cargo_install_args(&source, dest, self.locked))
+            .env("PATH", path)
+            .env("CARGO_TARGET_DIR", source.join("target"))
+            .status()
+            .await
+            .context("Failed to run cargo");
+        let _ = tokio::fs::remove_dir_all(&source).await;
+        if !status?.success() {
+            anyhow::bail!("cargo install {} failed", release.name);
+        }
+        Ok(())
+    }
+}
+
+/// The newest non-yanked version matching `requested`; prereleases only match exactly.
+pub fn select<'a>(versions: &'a [CrateVersion], requested: &str) -> Option<&'a CrateVersion> {
+    let candidates = versions.iter().filter(|v| !v.yanked);
+    if let Some(exact) = candidates.clone().find(|v| v.num == requested) {
+        return Some(exact);
+    }
+
+    candidates
+        .filter(|v| !v.num.contains('-'))
+        .filter(|v| requested == "latest" || version_matches(requested, &v.num))
+        .max_by_key(|v| semver::Version::parse(&v.num).ok())
+}
+
+/// Download `release.url` and check it against `release.sha256`, which is required.
+async fn fetch_verified(release: &Release) -> Result<Vec<u8>> {
+    let sha256 = release.sha256.as_deref().ok_or_else(|| {
+        anyhow::anyhow!(
+            "{} {} has no checksum to verify {}",
+            release.name,
+            release.version,
+            release.url
+        )
+    })?;
+    println!("Downloading {} {}...", release.name, release.version);
+    let data = fetch_bytes(&release.url).await?;
+    Hashing::verify_sha256(&data, sha256)
+        .map_err(|e| anyhow::anyhow!("Checksum verification failed: {e}"))?;
+    println!("Checksum verified for {}", release.name);
+    Ok(data)
+}
+
+/// The first prebuilt candidate that exists and publishes a `.sha256` beside it,
+/// with that checksum. Assets without one are skipped rather than installed unverified.
+async fn find_prebuilt(
+    manifest: &CrateManifest,
+    name: &str,
+    version: &str,
+    target: &str,
+) -> Option<(String, String)> {
+    for url in prebuilt_candidates(manifest, name, version, target) {
+        if !url_exists(&url).await {
+            continue;
+        }
+        let Ok(sum) = fetch_text(&format!("{}.sha256", url)).await else {
+            continue;
+        };
+        if let Some(sha256) = sum.split_whitespace().next() {
+            return Some((url, sha256.to_ascii_lowercase()));
+        }
+    }
+    None
+}
+
+/// Read `repository` and `[package.metadata.binstall]` from a `.crate` archive.
+pub fn read_crate_manifest(crate_data: &[u8]) -> Result<CrateManifest> {
+    let decoder = flate2::read::GzDecoder::new(crate_data);
+    let mut archive = tar::Archive::new(decoder);
+    for entry in archive.entries().context("Failed to read .crate archive")? {
+        let mut entry = entry?;
+        let path = entry.path()?.to_path_buf();
+        if path.components().count() != 2 || !path.ends_with("Cargo.toml") {
+            continue;
+        }
+
+        let mut content = String::new();
+        std::io::Read::read_to_string(&mut entry, &mut content)?;
+        let cargo_toml: toml::Table = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        let package = cargo_toml.get("package").and_then(|p| p.as_table());
+
+        let repository = package
+            .and_then(|p| p.get("repository"))
+            .and_then(|r| r.as_str())
+            .map(str::to_string);
+        let binstall = match package
+            .and_then(|p| p.get("metadata"))
+            .and_then(|m| m.get("binstall"))
+        {
+            Some(meta) => BinstallMeta::deserialize(meta.clone())
+                .context("Invalid [package.metadata.binstall]")?,
+            None => BinstallMeta::default(),
+        };
+        return Ok(CrateManifest {
+            repository,
+            binstall,
+        });
+    }
+    anyhow::bail!("The .crate archive has no Cargo.toml")
+}
+
+/// Prebuilt asset URLs to try, in order. A `pkg-url` from the crate's metadata is
+/// the only candidate; otherwise the common GitHub release naming schemes are tried.
+pub fn prebuilt_candidates(
+    manifest: &CrateManifest,
+    name: &str,
+    version: &str,
+    target: &str,
+) -> Vec<String> {
+    let meta = &manifest.binstall;
+    let overrides = meta.overrides.get(target);
+    let pkg_url = meta.pkg_url(target);
+    let pkg_fmt = overrides
+        .and_then(|o| o.pkg_fmt.as_deref())
+        .or(meta.pkg_fmt.as_deref())
+        .unwrap_or("tgz");
+    let Some(fmt) = PkgFmt::parse(pkg_fmt) else {
+        return Vec::new();
+    };
+
+    let repo = manifest
+        .repository
+        .as_deref()
+        .map(|repo| repo.trim_end_matches('/').trim_end_matches(".git"));
+    let render = |template: &str| -> Option<String> {
+        if template.contains("{ repo }") || template.contains("{repo}") {
+            repo?;
+        }
+        let mut url = template.to_string();
+        for (key, value) in [
+            ("name", name),
+            ("version", version),
+            ("target", target),
+            ("repo", repo.unwrap_or_default()),
+            ("archive-format", pkg_fmt),
+            ("format", pkg_fmt),
+            ("archive-suffix", fmt.suffix()),
+            ("binary-ext", ""),
+        ] {
+            url = url
+                .replace(&format!("{{ {} }}", key), value)
+                .replace(&format!("{{{}}}", key), value);
+        }
+        Some(url)
+    };
+
+    if let Some(template) = pkg_url {
+        return render(template).into_iter().collect();
+    }
+    if repo.is_none_or(|repo| !repo.starts_with("https://github.com/")) {
+        return Vec::new();
+    }
+
+    let mut candidates = Vec::new();
+    for tag in ["v{ version }", "{ version }"] {
+        for file in [
+            "{ name }-{ target }-v{ version }",
+            "{ name }-v{ version }-{ target }",
+            "{ name }-{ version }-{ target }",
+            "{ name }-{ target }",
+        ] {
+            let template = format!(
+                "{{ repo }}/releases/download/{}/{}{{ archive-suffix }}",
+                tag, file
+            );
+            candidates.extend(render(&template));
+        }
+    }
+    candidates
+}
+
+/// Arguments for `cargo install` of an unpacked `.crate`.
+pub fn cargo_install_args(source: &Path, root: &Path, locked: bool) -> Vec<std::ffi::OsString> {
+    let mut args: Vec<std::ffi::OsString> = vec!["install".into(), "--path".into()];
+    args.push(source.into());
+    args.push("--root".into());
+    args.push(root.into());
+    args.push("--no-track".into());
+    if locked {
+        args.push("--locked".into());
+    }
+    args
+}
+
+/// Put the release's binaries from a downloaded asset into `dest/bin`.
+async fn install_prebuilt(release: &Release, data: &[u8], dest: &Path) -> Result<()> {
+    let bin_dir = dest.join("bin");
+    tokio::fs::create_dir_all(&bin_dir).await?;
+
+    if ArchiveFormat::detect(data).is_none() {
+        let bin = release.bins.first().map_or("bin", String::as_str);
+        let path = dest.join(bin);
+        tokio::fs::write(&path, data).await?;
+        tokio::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).await?;
+        return Ok(());
+    }
+
+    let unpacked = dest.join(".unpacked");
+    ArchiveExtractor::extract(data, &unpacked)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to extract {}: {e}", release.url))?;
+
+    for bin in &release.bins {
+        let name = Path::new(bin)
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let found = find_file(&unpacked, &name).ok_or_else(|| {
+            anyhow::anyhow!("{} does not contain the binary {}", release.url, name)
+        })?;
+        let target = bin_dir.join(&name);
+        tokio::fs::rename(&found, &target).await?;
+        tokio::fs::set_permissions(&target, std::fs::Permissions::from_mode(0o755)).await?;
+    }
+    tokio::fs::remove_dir_all(&unpacked).await?;
+    Ok(())
+}
+
+/// The first regular file called `name` under `dir`, breadth first.
+fn find_file(dir: &Path, name: &str) -> Option<PathBuf> {
+    let mut dirs = std::collections::VecDeque::from([dir.to_path_buf()]);
+    while let Some(dir) = dirs.pop_front() {
+        for entry in std::fs::read_dir(&dir).ok()?.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push_back(path);
+            } else if entry.file_name() == name {
+                return Some(path);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::net::fixture;
+    use crate::utils::scratch::Scratch;
+
+    fn crate_file(name: &str, version: &str, cargo_toml_extra: &str) -> Vec<u8> {
+        let cargo_toml = format!(
+            "[package]\nname = \"{name}\"\nversion = \"{version}\"\nedition = \"2021\"\n{cargo_toml_extra}"
+        );
+        fixture::tar_gz(
+            &format!("{name}-{version}"),
+            &[
+                ("Cargo.toml", cargo_toml.as_bytes()),
+                (
+                    "src/main.rs",
+                    b"fn main() { println!(\"built {}\", env!(\"CARGO_PKG_VERSION\")); }\n",
+                ),
+            ],
+        )
+    }
+
+    fn api(name: &str, crate_data: &[u8], repository: Option<&str>) -> Vec<u8> {
+        serde_json::json!({
+            "crate": { "name": name, "repository": repository },
+            "versions": [
+                { "num": "0.3.0", "yanked": true, "checksum": "x", "bin_names": [name] },
+                { "num": "0.2.1", "checksum": Hashing::sha256(crate_data), "bin_names": [name] },
+                { "num": "0.2.0", "checksum": "x", "bin_names": [name] },
+                { "num": "0.4.0-rc.1", "checksum": "x", "bin_names": [name] },
+            ],
+        })
+        .to_string()
+        .into_bytes()
+    }
+
+    #[test]
+    fn builds_prebuilt_candidates() {
+        let mut manifest = CrateManifest {
+            repository: Some("https://github.com/BurntSushi/ripgrep.git".into()),
+            binstall: BinstallMeta::default(),
+        };
+        let candidates =
+            prebuilt_candidates(&manifest, "ripgrep", "14.1.1", "x86_64-unknown-linux-gnu");
+        assert_eq!(candidates.len(), 8);
+        assert_eq!(
+            candidates[0],
+            "https://github.com/BurntSushi/ripgrep/releases/download/v14.1.1/ripgrep-x86_64-unknown-linux-gnu-v14.1.1.tar.gz"
+        );
+
+        manifest.binstall = BinstallMeta {
+            pkg_url: Some(
+                "{ repo }/releases/download/{ version }/rg-{ target }{ archive-suffix }".into(),
+            ),
+            pkg_fmt: None,
+            overrides: [(
+                "aarch64-apple-darwin".to_string(),
+                BinstallMeta {
+                    pkg_fmt: Some("txz".into()),
+                    ..Default::default()
+                },
+            )]
+            .into(),
+        };
+        assert_eq!(
+            prebuilt_candidates(&manifest, "ripgrep", "14.1.1", "x86_64-unknown-linux-gnu"),
+            [
+                "https://github.com/BurntSushi/ripgrep/releases/download/14.1.1/rg-x86_64-unknown-linux-gnu.tar.gz"
+            ]
+        );
+        // Formats that cannot be unpacked fall back to building from source.
+        assert!(
+            prebuilt_candidates(&manifest, "ripgrep", "14.1.1", "aarch64-apple-darwin").is_empty()
+        );
+
+        let args = cargo_install_args(Path::new("/src"), Path::new("/root"), true);
+        assert_eq!(
+            args,
+            [
+                "install",
+                "--path",
+                "/src",
+                "--root",
+                "/root",
+                "--no-track",
+                "--locked"
+            ]
+        );
+    }
+
+    /// A Rust toolchain whose `cargo install --path <src> --root <root>` writes a
+    /// `hello-still` that prints the crate's version.
+    fn fake_rust(root: &Path) -> PathBuf {
+        let rust = root.join("rust");
+        let bin = rust.join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        std::fs::write(
+            bin.join("cargo"),
+            r#"#!/bin/sh
+while [ $# -gt 0 ]; do
+  case "$1" in
+    --path) src=$2; shift ;;
+    --root) root=$2; shift ;;
+  esac
+  shift
+done
+version=$(sed -n 's/^version = "\(.*\)"/\1/p' "$src/Cargo.toml")
+mkdir -p "$root/bin"
+printf '#!/bin/sh\necho built %s\n' "$version" > "$root/bin/hello-still"
+chmod +x "$root/bin/hello-still"
+"#,
+        )
+        .unwrap();
+        std::fs::set_permissions(bin.join("cargo"), std::fs::Permissions::from_mode(0o755))
+            .unwrap();
+        rust
+    }
+
+    #[test]
+    fn prefers_a_prebuilt_asset() {
+        let triple = target_triple(std::env::consts::OS, std::env::consts::ARCH).unwrap();
+        let asset = fixture::tar_gz(
+            &format!("hello-still-{triple}"),
+            &[
+                ("hello-still", b"#!/bin/sh\necho prebuilt\n"),
+                ("README.md", b""),
+            ],
+        );
+        let base = fixture::serve_with(|base| {
+            let crate_data = crate_file(
+                "hello-still",
+                "0.2.1",
+                &format!(
+                    "repository = \"{base}/repo\"\n[package.metadata.binstall]\n\
+                     pkg-url = \"{{ repo }}/dl/{{ name }}-{{ version }}-{{ target }}.tar.gz\"\n"
+                ),
+            );
+            vec![
+                (
+                    "/crates/hello-still".into(),
+                    api("hello-still", &crate_data, Some(&format!("{base}/repo"))),
+                ),
+                ("/crates/hello-still/0.2.1/download".into(), crate_data),
+                (
+                    format!("/repo/dl/hello-still-0.2.1-{triple}.tar.gz"),
+                    asset.clone(),
+                ),
+                (
+                    format!("/repo/dl/hello-still-0.2.1-{triple}.tar.gz.sha256"),
+                    Hashing::sha256(&asset).into_bytes(),
+                ),
+            ]
+        });
+        let registry = CargoRegistry::new(base.clone(), None);
+
+        let dest = Scratch::new("cargo-bin");
+        let release = fixture::block_on(async {
+            let release = registry
+                .resolve(&"cargo:hello-still@0.2".parse().unwrap())
+                .await
+                .unwrap();
+            registry.install(&release, &dest).await.unwrap();
+            release
+        });
+        // `pkg-url` lives in the `.crate`, so planning only sees the source release.
+        assert_eq!(release.version, "0.2.1");
+        assert!(release.from_source);
+        assert_eq!(
+            release.url,
+            format!("{base}/crates/hello-still/0.2.1/download")
+        );
+        let output = std::process::Command::new(dest.join("bin/hello-still"))
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "prebuilt\n");
+        assert!(!dest.join(".unpacked").exists());
+    }
+
+    #[test]
+    fn falls_back_to_building_from_source() {
+        let root = Scratch::new("cargo-src");
+        let triple = target_triple(std::env::consts::OS, std::env::consts::ARCH).unwrap();
+
+        // The prebuilt asset publishes no checksum, so it is not used.
+        let base = fixture::serve_with(|base| {
+            let crate_data = crate_file(
+                "hello-still",
+                "0.2.1",
+                &format!(
+                    "repository = \"{base}/repo\"\n[package.metadata.binstall]\n\
+                     pkg-url = \"{{ repo }}/dl/{{ name }}-{{ target }}.tar.gz\"\n"
+                ),
+            );
+            vec![
+                (
+                    "/crates/hello-still".into(),
+                    api("hello-still", &crate_data, Some(&format!("{base}/repo"))),
+                ),
+                ("/crates/hello-still/0.2.1/download".into(), crate_data),
+                (
+                    format!("/repo/dl/hello-still-{triple}.tar.gz"),
+                    b"unverified".to_vec(),
+                ),
+            ]
+        });
+        let registry = CargoRegistry::new(base, Some(fake_rust(&root)));
+
+        let dest = root.join("tools/hello-still/0.2.1");
+        std::fs::create_dir_all(&dest).unwrap();
+        fixture::block_on(async {
+            let release = registry
+                .resolve(&"cargo:hello-still".parse().unwrap())
+                .await
+                .unwrap();
+            assert!(release.from_source);
+            assert_eq!(release.sha256.as_deref().map(str::len), Some(64));
+            registry.install(&release, &dest).await.unwrap();
+
+            let unverified = Release {
+                sha256: None,
+                ..release
+            };
+            let err = registry.install(&unverified, &dest).await.unwrap_err();
+            assert!(err.to_string().contains("no checksum"), "{err:#}");
+        });
+        let output = std::process::Command::new(dest.join("bin/hello-still"))
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "built 0.2.1\n");
+        assert!(!dest.join(".source").exists());
+    }
+}
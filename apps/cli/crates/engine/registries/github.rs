@@ -0,0 +1,699 @@
+use crate::actions::install::{version_key, version_matches};
+use crate::registries::{Release, checksum_for};
+use crate::specs::config::LayeredConfig;
+use crate::specs::tool::ToolSpec;
+use crate::utils::archive::{ArchiveExtractor, ArchiveFormat};
+use crate::utils::glob::Glob;
+use crate::utils::hashing::Hashing;
+use crate::utils::net::NetUtils;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// The public GitHub REST API.
+pub const DEFAULT_API_URL: &str = "https://api.github.com";
+
+/// Releases per page of the listing; the API's maximum.
+const PAGE_SIZE: usize = 100;
+
+/// Tools shipped as GitHub release assets (`github:owner/repo@v1.2.3`).
+///
+/// The asset for this platform is picked by OS and architecture names in the file
+/// name, or by the `asset` glob of the tool's `[tools]` table. Only the files listed
+/// in `bins` are linked into `bin/`; without it, the executable named after the
+/// repository, or the asset's only executable. A checksum asset
+/// (`<asset>.sha256` or a `checksums.txt`-style listing) is verified when the release
+/// has one. The API base comes from `settings.github.api_url`, so GitHub Enterprise
+/// (`https://ghe.example.com/api/v3`) works too; `GITHUB_TOKEN` or `GH_TOKEN`
+/// authenticates requests for private repositories.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GithubRegistry {
+    pub api_url: String,
+    pub asset: Option<String>,
+    /// Files inside the asset to link, relative to its root (`bins` in `[tools]`).
+    pub bins: Vec<String>,
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GithubRelease {
+    pub tag_name: String,
+    #[serde(default)]
+    pub draft: bool,
+    #[serde(default)]
+    pub prerelease: bool,
+    #[serde(default)]
+    pub assets: Vec<GithubAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GithubAsset {
+    pub name: String,
+    /// API URL; serves the file itself with `Accept: application/octet-stream`.
+    pub url: String,
+    pub browser_download_url: String,
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+impl GithubRelease {
+    /// The tag without a leading `v`.
+    pub fn version(&self) -> &str {
+        self.tag_name.strip_prefix('v').unwrap_or(&self.tag_name)
+    }
+}
+
+impl GithubRegistry {
+    pub fn new(api_url: impl Into<String>) -> Self {
+        Self {
+            api_url: api_url.into().trim_end_matches('/').to_string(),
+            asset: None,
+            bins: Vec::new(),
+            token: None,
+        }
+    }
+
+    pub fn from_config(config: &LayeredConfig, tool: &ToolSpec) -> Result<Self> {
+        let mut registry = Self::new(
+            config
+                .get_str("settings.github.api_url")
+                .unwrap_or(DEFAULT_API_URL),
+        );
+        if let Some(options) = config.tool_options(tool)? {
+            registry.asset = options.asset;
+            registry.bins = options.bins;
+        }
+        registry.token = ["GITHUB_TOKEN", "GH_TOKEN"]
+            .iter()
+            .find_map(|var| std::env::var(var).ok().filter(|token| !token.is_empty()));
+        Ok(registry)
+    }
+
+    async fn get(&self, url: &str, accept: &str) -> Result<Vec<u8>> {
+        let mut request = NetUtils::client().get(url).header("Accept", accept);
+        if let Some(token) = &self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Failed to download {}", url))?;
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to download {}: HTTP {}", url, response.status());
+        }
+        let bytes = response
+            .bytes()
+            .await
+            .with_context(|| format!("Failed to read {}", url))?;
+        Ok(bytes.to_vec())
+    }
+
+    /// Where to download an asset: through the API when authenticated (private
+    /// repositories), otherwise from its public download URL.
+    fn asset_url(&self, asset: &GithubAsset) -> String {
+        match self.token {
+            Some(_) => asset.url.clone(),
+            None => asset.browser_download_url.clone(),
+        }
+    }
+
+    /// Every release of `repo`, following the API's pages.
+    pub async fn releases(&self, repo: &str) -> Result<Vec<GithubRelease>> {
+        let mut releases = Vec::new();
+        for page in 1.. {
+            let url = format!(
+                "{}/repos/{}/releases?per_page={}&page={}",
+                self.api_url, repo, PAGE_SIZE, page
+            );
+            let body = self.get(&url, "application/vnd.github+json").await?;
+            let batch: Vec<GithubRelease> = serde_json::from_slice(&body)
+                .with_context(|| format!("Failed to parse {}", url))?;
+            let last = batch.len() < PAGE_SIZE;
+            releases.extend(batch);
+            if last {
+                break;
+            }
+        }
+        Ok(releases)
+    }
+
+    pub async fn resolve(&self, tool: &ToolSpec) -> Result<Release> {
+        let releases = self.releases(&tool.name).await?;
+        let release = select_release(&releases, &tool.version).ok_or_else(|| {
+            anyhow::anyhow!("{} has no release matching {}", tool.name, tool.version)
+        })?;
+
+        let (os, arch) = (std::env::consts::OS, std::env::consts::ARCH);
+        let asset = select_asset(
+            &release.assets,
+            self.asset.as_deref(),
+            release.version(),
+            os,
+            arch,
+        )
+        .ok_or_else(|| {
+            let names: Vec<&str> = release.assets.iter().map(|a| a.name.as_str()).collect();
+            anyhow::anyhow!(
+                "No asset of {} {} matches {}-{}{}. Assets: {}",
+                tool.name,
+                release.tag_name,
+                os,
+                arch,
+                self.asset
+                    .as_ref()
+                    .map(|pattern| format!(" and \"{}\"", pattern))
+                    .unwrap_or_default(),
+                names.join(", ")
+            )
+        })?;
+
+        let sha256 = match checksum_asset(&release.assets, &asset.name) {
+            Some((checksums, listing)) => {
+                let text = String::from_utf8(
+                    self.get(&self.asset_url(checksums), "application/octet-stream")
+                        .await?,
+                )
+                .with_context(|| format!("{} is not valid UTF-8", checksums.name))?;
+                let sha256 = if listing {
+                    checksum_for(&text, &asset.name)
+                } else {
+                    text.split_whitespace().next().map(str::to_ascii_lowercase)
+                };
+                Some(sha256.ok_or_else(|| {
+                    anyhow::anyhow!("{} has no checksum for {}", checksums.name, asset.name)
+                })?)
+            }
+            None => None,
+        };
+
+        let bins = self
+            .bins
+            .iter()
+            .map(|bin| format!("bin/{}", link_name(bin)))
+            .collect();
+
+        Ok(Release {
+            name: tool.name.clone(),
+            version: release.version().to_string(),
+            url: self.asset_url(asset),
+            sha256,
+            integrity: None,
+            size: asset.size,
+            bins,
+            from_source: false,
+        })
+    }
+
+    /// Download and verify the asset, unpack it (or keep it as the binary itself)
+    /// and link its binaries into `dest/bin`.
+    pub async fn install(&self, release: &Release, dest: &Path) -> Result<()> {
+        println!("Downloading {} {}...", release.name, release.version);
+        let data = self.get(&release.url, "application/octet-stream").await?;
+        if let Some(sha256) = &release.sha256 {
+            Hashing::verify_sha256(&data, sha256)
+                .map_err(|e| anyhow::anyhow!("Checksum verification failed: {e}"))?;
+            println!("Checksum verified for {}", release.name);
+        }
+
+        let bin_dir = dest.join("bin");
+        let repo_name = release.name.rsplit('/').next().unwrap_or(&release.name);
+        if ArchiveFormat::detect(&data).is_none() {
+            tokio::fs::create_dir_all(&bin_dir).await?;
+            let path = match release.bins.first() {
+                Some(bin) => dest.join(bin),
+                None => bin_dir.join(repo_name),
+            };
+            tokio::fs::write(&path, &data).await?;
+            tokio::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).await?;
+            return Ok(());
+        }
+
+        ArchiveExtractor::extract(&data, dest)
+            .await
+            .map_err(|e| anyhow::anyhow!("Failed to extract {}: {e}", release.url))?;
+
+        // Each file with the name it is linked under, free of `{version}`.
+        let files: Vec<(PathBuf, String)> = if self.bins.is_empty() {
+            let file = default_bin(dest, repo_name).map_err(|e| {
+                anyhow::anyhow!("{}: {e}; list the ones to link in `bins`", release.url)
+            })?;
+            let name = file
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            vec![(file, name)]
+        } else {
+            self.bins
+                .iter()
+                .map(|bin| {
+                    let file = PathBuf::from(bin.replace("{version}", &release.version));
+                    (file, link_name(bin))
+                })
+                .collect()
+        };
+        for (file, name) in files {
+            let path = dest.join(&file);
+            if !path.is_file() {
+                anyhow::bail!("{} does not contain {}", release.url, file.display());
+            }
+            tokio::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).await?;
+
+            tokio::fs::create_dir_all(&bin_dir).await?;
+            let link = bin_dir.join(name);
+            if link != path && !link.exists() {
+                std::os::unix::fs::symlink(Path::new("..").join(&file), &link)
+                    .with_context(|| format!("Failed to link {}", link.display()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The file name of a `bins` entry with `{version}` and a separator next to it
+/// left out, so its shim keeps one name across upgrades: `tool-{version}` is `tool`.
+fn link_name(bin: &str) -> String {
+    let name = bin.rsplit('/').next().unwrap_or(bin);
+    let mut name = name.to_string();
+    for pattern in [
+        "-{version}",
+        "_{version}",
+        ".{version}",
+        "{version}-",
+        "{version}_",
+    ] {
+        name = name.replace(pattern, "");
+    }
+    name.replace("{version}", "")
+}
+
+/// `latest` is the newest full release; a tag (`v1.2.3` or `1.2.3`) matches exactly;
+/// a partial version picks the newest full release with that prefix.
+pub fn select_release<'a>(
+    releases: &'a [GithubRelease],
+    requested: &str,
+) -> Option<&'a GithubRelease> {
+    let published = releases.iter().filter(|r| !r.draft);
+    let requested_version = requested.strip_prefix('v').unwrap_or(requested);
+    if let Some(exact) = published
+        .clone()
+        .find(|r| r.tag_name == requested || r.version() == requested_version)
+    {
+        return Some(exact);
+    }
+
+    let stable = published.filter(|r| !r.prerelease);
+    if requested.eq_ignore_ascii_case("latest") {
+        return stable.max_by_key(|r| version_key(r.version()));
+    }
+    stable
+        .filter(|r| version_matches(requested_version, r.version()))
+        .max_by_key(|r| version_key(r.version()))
+}
+
+/// The asset to install on `os`/`arch`. With a `pattern`, only assets matching the
+/// glob (`*`, `?`, `{version}`) are considered; the platform heuristics then break ties.
+pub fn select_asset<'a>(
+    assets: &'a [GithubAsset],
+    pattern: Option<&str>,
+    version: &str,
+    os: &str,
+    arch: &str,
+) -> Option<&'a GithubAsset> {
+    let pattern = pattern.map(|p| p.replace("{version}", version));
+    let candidates = assets
+        .iter()
+        .filter(|asset| is_installable(&asset.name))
+        .filter(|asset| match &pattern {
+            Some(pattern) => Glob::match_name(pattern, &asset.name),
+            None => true,
+        });
+
+    if pattern.is_some() {
+        let matching: Vec<&GithubAsset> = candidates.collect();
+        if let [only] = matching.as_slice() {
+            return Some(only);
+        }
+        return matching
+            .into_iter()
+            .max_by_key(|asset| platform_score(&asset.name, os, arch).unwrap_or(0));
+    }
+
+    candidates
+        .filter_map(|asset| Some((platform_score(&asset.name, os, arch)?, asset)))
+        .max_by_key(|(score, asset)| (*score, std::cmp::Reverse(asset.name.len())))
+        .map(|(_, asset)| asset)
+}
+
+const OS_NAMES: &[(&str, &[&str])] = &[
+    ("macos", &["darwin", "macos", "apple", "osx", "mac"]),
+    ("linux", &["linux"]),
+    ("windows", &["windows", "win32", "win64", "pc-windows"]),
+    ("freebsd", &["freebsd"]),
+];
+
+const ARCH_NAMES: &[(&str, &[&str])] = &[
+    ("x86_64", &["x86_64", "x86-64", "amd64", "x64"]),
+    ("aarch64", &["aarch64", "arm64"]),
+    ("x86", &["i386", "i686", "386"]),
+    ("arm", &["armv7", "armv6", "armhf"]),
+];
+
+/// The lowercase words of an asset name, split on `-`, `_` and `.`.
+fn name_words(name: &str) -> Vec<String> {
+    name.to_ascii_lowercase()
+        .split(['-', '_', '.'])
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// How well an asset name fits the platform; `None` if it names another OS or arch.
+/// Names are compared as whole words, so `macchina-linux` does not read as macOS.
+fn platform_score(name: &str, os: &str, arch: &str) -> Option<u32> {
+    let lower = name.to_ascii_lowercase();
+    let words = name_words(name);
+    let has = |candidate: &str| {
+        let candidate = name_words(candidate);
+        words
+            .windows(candidate.len())
+            .any(|window| window == candidate.as_slice())
+    };
+    let names_any = |names: &[&str]| names.iter().any(|n| has(n));
+
+    let mut score = 0;
+    for (candidate, names) in OS_NAMES {
+        if *candidate == os {
+            if !names_any(names) {
+                return None;
+            }
+            score += 10;
+        } else if names_any(names) {
+            return None;
+        }
+    }
+
+    let arch_names = |wanted: &str| {
+        ARCH_NAMES
+            .iter()
+            .find(|(candidate, _)| *candidate == wanted)
+            .map_or(&[][..], |(_, names)| *names)
+    };
+    if names_any(arch_names(arch)) {
+        score += 5;
+    } else if os == "macos" && has("universal") {
+        score += 4;
+    } else if ARCH_NAMES.iter().any(|(_, names)| names_any(names)) {
+        return None;
+    }
+
+    score += match ArchiveFormat::from_name(&lower) {
+        Some(ArchiveFormat::TarGz) => 3,
+        Some(ArchiveFormat::Zip) => 2,
+        None => 1,
+    };
+    if os == "linux" && has("musl") {
+        score += 1;
+    }
+    Some(score)
+}
+
+/// Whether Still can install an asset: archives and bare binaries, not packages,
+/// checksums or signatures.
+fn is_installable(name: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    const SKIPPED: &[&str] = &[
+        ".sha256",
+        ".sha256sum",
+        ".sha512",
+        ".md5",
+        ".sig",
+        ".asc",
+        ".pem",
+        ".sbom",
+        ".json",
+        ".txt",
+        ".deb",
+        ".rpm",
+        ".apk",
+        ".msi",
+        ".exe",
+        ".dmg",
+        ".pkg",
+        ".tar.xz",
+        ".tar.bz2",
+        ".tar.zst",
+        ".7z",
+    ];
+    !SKIPPED.iter().any(|ext| lower.ends_with(ext))
+        && !lower.contains("checksums")
+        && !lower.contains("sha256sums")
+}
+
+/// A checksum asset for `asset_name`, and whether it is a multi-file listing.
+fn checksum_asset<'a>(
+    assets: &'a [GithubAsset],
+    asset_name: &str,
+) -> Option<(&'a GithubAsset, bool)> {
+    for suffix in [".sha256", ".sha256sum"] {
+        let sidecar = format!("{}{}", asset_name, suffix);
+        if let Some(asset) = assets.iter().find(|a| a.name == sidecar) {
+            return Some((asset, false));
+        }
+    }
+    assets
+        .iter()
+        .find(|a| {
+            let lower = a.name.to_ascii_lowercase();
+            lower.contains("checksums") || lower.contains("sha256sums")
+        })
+        .map(|asset| (asset, true))
+}
+
+/// The executable to link when `bins` is not set, relative to `dest`: the one
+/// named after the repository, else the only executable in the asset.
+fn default_bin(dest: &Path, repo_name: &str) -> Result<PathBuf> {
+    let mut executables = Vec::new();
+    let mut dirs = vec![dest.to_path_buf()];
+    while let Some(dir) = dirs.pop() {
+        for entry in std::fs::read_dir(&dir)? {
+            let entry = entry?;
+            let path = entry.path();
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                if !entry.file_name().to_string_lossy().starts_with('.') {
+                    dirs.push(path);
+                }
+            } else if file_type.is_file() && entry.metadata()?.permissions().mode() & 0o111 != 0 {
+                executables.push(path.strip_prefix(dest)?.to_path_buf());
+            }
+        }
+    }
+    executables.sort();
+
+    if let Some(named) = executables
+        .iter()
+        .find(|path| path.file_name().is_some_and(|name| name == repo_name))
+    {
+        return Ok(named.clone());
+    }
+    match executables.as_slice() {
+        [only] => Ok(only.clone()),
+        [] => anyhow::bail!("no executable found"),
+        several => anyhow::bail!(
+            "no executable is named {} and there are several ({})",
+            repo_name,
+            several
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::net::fixture;
+    use crate::utils::scratch::Scratch;
+
+    fn asset(base: &str, name: &str) -> serde_json::Value {
+        serde_json::json!({
+            "name": name,
+            "url": format!("{base}/api/assets/{name}"),
+            "browser_download_url": format!("{base}/download/{name}"),
+        })
+    }
+
+    fn assets(names: &[&str]) -> Vec<GithubAsset> {
+        names
+            .iter()
+            .map(|name| serde_json::from_value(asset("https://example.com", name)).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn picks_assets_for_the_platform() {
+        let macchina = assets(&["macchina-linux-x86_64", "macchina-macos-x86_64"]);
+        let assets = assets(&[
+            "tool-1.2.3-x86_64-unknown-linux-gnu.tar.gz",
+            "tool-1.2.3-x86_64-unknown-linux-musl.tar.gz",
+            "tool-1.2.3-aarch64-unknown-linux-gnu.tar.gz",
+            "tool-1.2.3-x86_64-apple-darwin.zip",
+            "tool-1.2.3-universal-apple-darwin.tar.gz",
+            "tool-1.2.3-x86_64-pc-windows-msvc.zip",
+            "tool_1.2.3_amd64.deb",
+            "checksums.txt",
+        ]);
+        let pick = |pattern: Option<&str>, os: &str, arch: &str| {
+            select_asset(&assets, pattern, "1.2.3", os, arch).map(|a| a.name.as_str())
+        };
+
+        assert_eq!(
+            pick(None, "linux", "x86_64"),
+            Some("tool-1.2.3-x86_64-unknown-linux-musl.tar.gz")
+        );
+        assert_eq!(
+            pick(None, "linux", "aarch64"),
+            Some("tool-1.2.3-aarch64-unknown-linux-gnu.tar.gz")
+        );
+        assert_eq!(
+            pick(None, "macos", "x86_64"),
+            Some("tool-1.2.3-x86_64-apple-darwin.zip")
+        );
+        assert_eq!(
+            pick(None, "macos", "aarch64"),
+            Some("tool-1.2.3-universal-apple-darwin.tar.gz")
+        );
+        assert_eq!(
+            pick(Some("tool-{version}-*-linux-gnu.tar.gz"), "linux", "x86_64"),
+            Some("tool-1.2.3-x86_64-unknown-linux-gnu.tar.gz")
+        );
+        assert_eq!(pick(Some("other-*"), "linux", "x86_64"), None);
+
+        // Platform names only count as whole words of the asset name.
+        assert_eq!(
+            select_asset(&macchina, None, "6.1.0", "linux", "x86_64").map(|a| a.name.as_str()),
+            Some("macchina-linux-x86_64")
+        );
+    }
+
+    #[test]
+    fn selects_releases_by_tag_and_prefix() {
+        let releases: Vec<GithubRelease> = serde_json::from_value(serde_json::json!([
+            { "tag_name": "v2.0.0-rc.1", "prerelease": true },
+            { "tag_name": "v1.3.0", "draft": true },
+            { "tag_name": "v1.2.10" },
+            { "tag_name": "v1.2.3" },
+            { "tag_name": "v1.1.0" },
+        ]))
+        .unwrap();
+        let pick = |requested: &str| select_release(&releases, requested).map(|r| r.version());
+
+        assert_eq!(pick("latest"), Some("1.2.10"));
+        assert_eq!(pick("v1.2.3"), Some("1.2.3"));
+        assert_eq!(pick("1.2"), Some("1.2.10"));
+        assert_eq!(pick("v2.0.0-rc.1"), Some("2.0.0-rc.1"));
+        assert_eq!(pick("1.3"), None);
+    }
+
+    #[test]
+    fn installs_from_a_local_api() {
+        let (os, arch) = (std::env::consts::OS, std::env::consts::ARCH);
+        let platform = match (os, arch) {
+            ("macos", "aarch64") => "darwin-arm64",
+            ("macos", _) => "darwin-amd64",
+            (_, "aarch64") => "linux-arm64",
+            _ => "linux-amd64",
+        };
+        let name = format!("mytool_1.2.3_{platform}.tar.gz");
+        let tarball = fixture::tar_gz(
+            &format!("mytool_1.2.3_{platform}"),
+            &[
+                ("mytool", b"#!/bin/sh\necho mytool\n"),
+                ("mytool-1.2.3", b"#!/bin/sh\necho versioned\n"),
+                ("docs/README.md", b""),
+                ("completions/install.sh", b""),
+            ],
+        );
+        let checksums = format!(
+            "{}  {}\n{}  other.tar.gz\n",
+            Hashing::sha256(&tarball),
+            name,
+            "0".repeat(64)
+        );
+
+        let base = fixture::serve_with(|base| {
+            // A full first page of newer releases pushes v1.2.3 onto the second.
+            let newer: Vec<serde_json::Value> = (0..100)
+                .map(|patch| serde_json::json!({ "tag_name": format!("v2.0.{patch}") }))
+                .collect();
+            let releases = serde_json::json!([{
+                "tag_name": "v1.2.3",
+                "assets": [
+                    asset(base, &name),
+                    asset(base, "mytool_1.2.3_windows-amd64.zip"),
+                    asset(base, "mytool_1.2.3_checksums.txt"),
+                ],
+            }]);
+            vec![
+                (
+                    "/repos/acme/mytool/releases?per_page=100&page=1".into(),
+                    serde_json::Value::from(newer).to_string().into_bytes(),
+                ),
+                (
+                    "/repos/acme/mytool/releases?per_page=100&page=2".into(),
+                    releases.to_string().into_bytes(),
+                ),
+                (format!("/download/{name}"), tarball.clone()),
+                (
+                    "/download/mytool_1.2.3_checksums.txt".into(),
+                    checksums.clone().into_bytes(),
+                ),
+            ]
+        });
+        let registry = GithubRegistry::new(format!("{base}/"));
+
+        let dest = Scratch::new("github");
+        fixture::block_on(async {
+            let release = registry
+                .resolve(&"github:acme/mytool@v1.2.3".parse().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(release.version, "1.2.3");
+            assert_eq!(release.url, format!("{base}/download/{name}"));
+            assert_eq!(release.sha256, Some(Hashing::sha256(&tarball)));
+            registry.install(&release, &dest).await.unwrap();
+        });
+
+        let output = std::process::Command::new(dest.join("bin/mytool"))
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "mytool\n");
+        let linked: Vec<_> = std::fs::read_dir(dest.join("bin"))
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(linked, ["mytool"]);
+
+        // A versioned file is linked under a name that stays the same.
+        let mut registry = registry;
+        registry.bins = vec!["mytool-{version}".to_string()];
+        let dest = Scratch::new("github-versioned");
+        fixture::block_on(async {
+            let release = registry
+                .resolve(&"github:acme/mytool@v1.2.3".parse().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(release.bins, ["bin/mytool"]);
+            registry.install(&release, &dest).await.unwrap();
+        });
+        let output = std::process::Command::new(dest.join("bin/mytool"))
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "versioned\n");
+        assert_eq!(link_name("dist/tool_{version}_linux"), "tool_linux");
+    }
+}
@@ -0,0 +1,288 @@
+use crate::actions::init::{GoModVersions, parse_go_mod};
+use crate::actions::install::{version_key, version_matches};
+use crate::registries::{Release, fetch_text};
+use crate::specs::config::LayeredConfig;
+use crate::specs::tool::ToolSpec;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Where Go releases are listed and downloaded.
+pub const DEFAULT_DL_URL: &str = "https://go.dev/dl";
+
+/// Go toolchains from the official download page (`?mode=json&include=all`).
+///
+/// An unpinned `go` uses the `toolchain` directive of the nearest `go.mod`,
+/// then its `go` directive. The base URL comes from `settings.go.dl_url`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GoRegistry {
+    pub dl_url: String,
+    pub go_mod: Option<GoModVersions>,
+}
+
+/// One entry of the release listing.
+#[derive(Debug, Clone, Deserialize)]
+pub struct GoRelease {
+    /// `go1.22.3`
+    pub version: String,
+    #[serde(default)]
+    pub stable: bool,
+    #[serde(default)]
+    pub files: Vec<GoFile>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GoFile {
+    pub filename: String,
+    #[serde(default)]
+    pub os: String,
+    #[serde(default)]
+    pub arch: String,
+    #[serde(default)]
+    pub sha256: String,
+    #[serde(default)]
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub kind: String,
+}
+
+impl GoRelease {
+    pub fn number(&self) -> &str {
+        self.version.strip_prefix("go").unwrap_or(&self.version)
+    }
+}
+
+impl GoRegistry {
+    pub fn from_config(config: &LayeredConfig) -> Result<Self> {
+        let start = match &config.project_root {
+            Some(root) => root.clone(),
+            None => std::env::current_dir().context("Failed to read current directory")?,
+        };
+        // go.mod only stands in for a version when Still pins none, not even `latest`.
+        let pinned = config.get("tools.go").is_some();
+        let go_mod = match find_go_mod(&start).filter(|_| !pinned) {
+            Some(path) => {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                Some(parse_go_mod(&content))
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            dl_url: config
+                .get_str("settings.go.dl_url")
+                .unwrap_or(DEFAULT_DL_URL)
+                .trim_end_matches('/')
+                .to_string(),
+            go_mod,
+        })
+    }
+
+    /// The version to resolve: the requested one, else `go.mod`, else `latest`.
+    pub fn requested_version(&self, tool: &ToolSpec) -> String {
+        if !tool.is_latest() {
+            return tool.version.clone();
+        }
+        self.go_mod
+            .as_ref()
+            .and_then(|go_mod| go_mod.toolchain.clone().or_else(|| go_mod.go.clone()))
+            .unwrap_or_else(|| "latest".to_string())
+    }
+
+    pub async fn releases(&self) -> Result<Vec<GoRelease>> {
+        let url = format!("{}/?mode=json&include=all", self.dl_url);
+        let body = fetch_text(&url).await?;
+        serde_json::from_str(&body).with_context(|| format!("Failed to parse {}", url))
+    }
+
+    pub async fn resolve(&self, tool: &ToolSpec) -> Result<Release> {
+        let (os, arch) = platform(std::env::consts::OS, std::env::consts::ARCH)?;
+        let requested = self.requested_version(tool);
+        let releases = self.releases().await?;
+        let release = select(&releases, &requested)
+            .ok_or_else(|| anyhow::anyhow!("No Go release matches {}", requested))?;
+
+        let file = release
+            .files
+            .iter()
+            .find(|f| f.os == os && f.arch == arch && f.kind == "archive")
+            .ok_or_else(|| anyhow::anyhow!("{} has no {}-{} archive", release.version, os, arch))?;
+
+        Ok(Release {
+            name: "go".to_string(),
+            version: release.number().to_string(),
+            url: format!("{}/{}", self.dl_url, file.filename),
+            sha256: Some(file.sha256.clone()),
+            integrity: None,
+            size: file.size,
+            bins: ["bin/go", "bin/gofmt"].map(String::from).to_vec(),
+            from_source: false,
+        })
+    }
+}
+
+/// `GOROOT` for a Go installed at `install_path`.
+pub fn env(install_path: &Path) -> Vec<(String, String)> {
+    vec![(
+        "GOROOT".to_string(),
+        install_path.to_string_lossy().to_string(),
+    )]
+}
+
+/// Go's names for an OS/arch pair.
+pub fn platform(os: &str, arch: &str) -> Result<(&'static str, &'static str)> {
+    let os = match os {
+        "macos" => "darwin",
+        "linux" => "linux",
+        other => anyhow::bail!("Go archives are not available for {}", other),
+    };
+    let arch = match arch {
+        "aarch64" => "arm64",
+        "x86_64" => "amd64",
+        other => anyhow::bail!("Go archives are not available for {}", other),
+    };
+    Ok((os, arch))
+}
+
+/// The newest release matching `requested`. `latest` and partial versions only
+/// consider stable releases; an exact version (`1.23rc1`) may pick a prerelease.
+pub fn select<'a>(releases: &'a [GoRelease], requested: &str) -> Option<&'a GoRelease> {
+    let requested = requested.strip_prefix("go").unwrap_or(requested);
+    if let Some(exact) = releases.iter().find(|r| r.number() == requested) {
+        return Some(exact);
+    }
+
+    releases
+        .iter()
+        .filter(|r| r.stable)
+        .filter(|r| requested == "latest" || version_matches(requested, r.number()))
+        .max_by_key(|r| version_key(r.number()))
+}
+
+/// The nearest `go.mod` at or above `start`.
+fn find_go_mod(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .map(|dir| dir.join("go.mod"))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::hashing::Hashing;
+    use crate::utils::net::fixture;
+    use crate::utils::scratch::Scratch;
+
+    fn listing(files_for: &str, sha256: &str) -> String {
+        let (os, arch) = files_for.split_once('-').unwrap();
+        let release = |version: &str, stable: bool| {
+            serde_json::json!({
+                "version": format!("go{version}"),
+                "stable": stable,
+                "files": [
+                    { "filename": format!("go{version}.src.tar.gz"), "os": "", "arch": "", "kind": "source", "sha256": "x" },
+                    { "filename": format!("go{version}.{os}-{arch}.tar.gz"), "os": os, "arch": arch, "kind": "archive", "sha256": sha256, "size": 10 },
+                ],
+            })
+        };
+        serde_json::json!([
+            release("1.23rc1", false),
+            release("1.22.3", true),
+            release("1.22.10", true),
+            release("1.21.0", true),
+            release("1.20", true),
+        ])
+        .to_string()
+    }
+
+    #[test]
+    fn selects_latest_minor_and_exact_versions() {
+        let releases: Vec<GoRelease> = serde_json::from_str(&listing("linux-amd64", "x")).unwrap();
+        let pick = |requested: &str| select(&releases, requested).map(|r| r.number());
+
+        assert_eq!(pick("latest"), Some("1.22.10"));
+        assert_eq!(pick("1.22"), Some("1.22.10"));
+        assert_eq!(pick("1.22.3"), Some("1.22.3"));
+        assert_eq!(pick("go1.20"), Some("1.20"));
+        assert_eq!(pick("1.23rc1"), Some("1.23rc1"));
+        assert_eq!(pick("1.23"), None);
+    }
+
+    #[test]
+    fn unpinned_versions_come_from_go_mod() {
+        let registry = |content: Option<&str>| GoRegistry {
+            dl_url: DEFAULT_DL_URL.to_string(),
+            go_mod: content.map(parse_go_mod),
+        };
+        let go: ToolSpec = "go".parse().unwrap();
+
+        assert_eq!(registry(None).requested_version(&go), "latest");
+        assert_eq!(
+            registry(Some("module x\ngo 1.21\n")).requested_version(&go),
+            "1.21"
+        );
+        assert_eq!(
+            registry(Some("module x\ngo 1.21\ntoolchain go1.22.3\n")).requested_version(&go),
+            "1.22.3"
+        );
+        assert_eq!(
+            registry(Some("module x\ngo 1.21\n")).requested_version(&"go@1.22".parse().unwrap()),
+            "1.22"
+        );
+    }
+
+    #[test]
+    fn go_mod_does_not_replace_a_pinned_latest() {
+        use crate::specs::config::ConfigSources;
+
+        let root = Scratch::new("go-pinned");
+        std::fs::write(root.join("go.mod"), "module x\ngo 1.21\n").unwrap();
+        let registry = |manifest: &str| {
+            std::fs::write(root.join("still.toml"), manifest).unwrap();
+            let config = LayeredConfig::load_from(&ConfigSources {
+                global_file: None,
+                cwd: root.to_path_buf(),
+                env: Vec::new(),
+            })
+            .unwrap();
+            GoRegistry::from_config(&config).unwrap()
+        };
+        let go: ToolSpec = "go".parse().unwrap();
+
+        assert_eq!(registry("[tools]\n").requested_version(&go), "1.21");
+        assert_eq!(
+            registry("[tools]\ngo = \"latest\"\n").requested_version(&go),
+            "latest"
+        );
+    }
+
+    #[test]
+    fn installs_from_a_local_listing() {
+        let (os, arch) = platform(std::env::consts::OS, std::env::consts::ARCH).unwrap();
+        let tarball = fixture::tar_gz("go", &[("bin/go", b"#!/bin/sh\n"), ("bin/gofmt", b"")]);
+        let base = fixture::serve(vec![
+            (
+                "/?mode=json&include=all".into(),
+                listing(&format!("{os}-{arch}"), &Hashing::sha256(&tarball)).into_bytes(),
+            ),
+            (format!("/go1.22.10.{os}-{arch}.tar.gz"), tarball),
+        ]);
+        let registry = GoRegistry {
+            dl_url: base,
+            go_mod: Some(parse_go_mod("module x\ngo 1.22\n")),
+        };
+
+        let dest = Scratch::new("go");
+        fixture::block_on(async {
+            let release = registry.resolve(&"go".parse().unwrap()).await.unwrap();
+            assert_eq!(release.version, "1.22.10");
+            crate::registries::install_archive(&release, &dest)
+                .await
+                .unwrap();
+        });
+        assert!(dest.join("bin/go").is_file());
+        assert_eq!(env(&dest)[0].1, dest.to_string_lossy());
+    }
+}
@@ -1,4 +1,198 @@
+pub mod asdf;
+pub mod cargo;
+pub mod github;
+pub mod go;
 pub mod homebrew;
+pub mod node;
+pub mod npm;
+pub mod python;
+pub mod rust;
+pub mod url;
 
 // Re-export specs module for convenience
 pub use crate::specs;
+
+use crate::registries::asdf::AsdfRegistry;
+use crate::registries::cargo::CargoRegistry;
+use crate::registries::github::GithubRegistry;
+use crate::registries::go::GoRegistry;
+use crate::registries::node::NodeRegistry;
+use crate::registries::npm::NpmRegistry;
+use crate::registries::python::PythonRegistry;
+use crate::registries::rust::RustRegistry;
+use crate::registries::url::UrlRegistry;
+use crate::specs::config::LayeredConfig;
+use crate::specs::tool::{Backend, CORE_TOOLS, ToolSpec};
+use crate::utils::archive::ArchiveExtractor;
+use crate::utils::hashing::Hashing;
+use crate::utils::net::NetUtils;
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// A concrete build of a tool that a [`Registry`] resolved a spec to for this platform.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Release {
+    pub name: String,
+    pub version: String,
+    pub url: String,
+    pub sha256: Option<String>,
+    /// Subresource Integrity string (`sha512-...`) for registries that publish one.
+    pub integrity: Option<String>,
+    /// Download size in bytes, when the registry publishes it.
+    pub size: Option<u64>,
+    /// Executables to expose in `bin_dir`, relative to the install path.
+    pub bins: Vec<String>,
+    /// `url` is source to build (a `.crate`) rather than a prebuilt binary.
+    pub from_source: bool,
+}
+
+/// Registries that resolve versions from their own release listings,
+/// as opposed to Homebrew bottles from `formula.json`.
+#[derive(Debug, Clone)]
+pub enum Registry {
+    Node(NodeRegistry),
+    Python(PythonRegistry),
+    Rust(RustRegistry),
+    Go(GoRegistry),
+    Npm(NpmRegistry),
+    Cargo(CargoRegistry),
+    Github(GithubRegistry),
+    Asdf(AsdfRegistry),
+    Url(UrlRegistry),
+}
+
+impl Registry {
+    /// The registry that installs `tool`, or `None` when it comes from Homebrew.
+    pub fn for_tool(tool: &ToolSpec, config: &LayeredConfig) -> Result<Option<Self>> {
+        match tool.effective_backend() {
+            Backend::Homebrew => Ok(None),
+            Backend::Core => match tool.name.as_str() {
+                "node" => Ok(Some(Registry::Node(NodeRegistry::from_config(config)))),
+                "python" => Ok(Some(Registry::Python(PythonRegistry::from_config(config)))),
+                "rust" => Ok(Some(Registry::Rust(RustRegistry::from_config(config)?))),
+                "go" => Ok(Some(Registry::Go(GoRegistry::from_config(config)?))),
+                other => anyhow::bail!(
+                    "{} is not a core tool (expected one of: {})",
+                    other,
+                    CORE_TOOLS.join(", ")
+                ),
+            },
+            Backend::Rustup => Ok(Some(Registry::Rust(RustRegistry::from_config(config)?))),
+            Backend::Npm => Ok(Some(Registry::Npm(NpmRegistry::from_config(config)))),
+            Backend::Cargo => Ok(Some(Registry::Cargo(CargoRegistry::from_config(config)))),
+            Backend::Github => Ok(Some(Registry::Github(GithubRegistry::from_config(
+                config, tool,
+            )?))),
+            Backend::Asdf => Ok(Some(Registry::Asdf(AsdfRegistry::from_config(
+                config, tool,
+            )?))),
+            Backend::Url => Ok(Some(Registry::Url(UrlRegistry::from_config(config, tool)?))),
+        }
+    }
+
+    /// Resolve a (possibly partial or aliased) version to a concrete release.
+    pub async fn resolve(&self, tool: &ToolSpec) -> Result<Release> {
+        match self {
+            Registry::Node(node) => node.resolve(tool).await,
+            Registry::Python(python) => python.resolve(tool).await,
+            Registry::Rust(rust) => rust.resolve(tool).await,
+            Registry::Go(go) => go.resolve(tool).await,
+            Registry::Npm(npm) => npm.resolve(tool).await,
+            Registry::Cargo(cargo) => cargo.resolve(tool).await,
+            Registry::Github(github) => github.resolve(tool).await,
+            Registry::Asdf(asdf) => asdf.resolve(tool).await,
+            Registry::Url(url) => url.resolve(tool).await,
+        }
+    }
+
+    /// Download, verify and unpack `release` into `dest`.
+    pub async fn install(&self, release: &Release, dest: &Path) -> Result<()> {
+        match self {
+            Registry::Node(_) | Registry::Go(_) => install_archive(release, dest).await,
+            Registry::Python(python) => python.install(release, dest).await,
+            Registry::Rust(rust) => rust.install(release, dest).await,
+            Registry::Npm(npm) => npm.install(release, dest).await,
+            Registry::Cargo(cargo) => cargo.install(release, dest).await,
+            Registry::Github(github) => github.install(release, dest).await,
+            Registry::Asdf(asdf) => asdf.install(release, dest).await,
+            Registry::Url(url) => url.install(release, dest).await,
+        }
+    }
+}
+
+/// Variables an installed tool needs in its environment (`GOROOT` for go).
+pub fn tool_vars(name: &str, install_path: &Path) -> Vec<(String, String)> {
+    match name {
+        "go" => go::env(install_path),
+        _ => Vec::new(),
+    }
+}
+
+/// Download `release.url`, check it against `release.sha256` and unpack it into `dest`.
+pub(crate) async fn install_archive(release: &Release, dest: &Path) -> Result<()> {
+    println!("Downloading {} {}...", release.name, release.version);
+    let data = fetch_bytes(&release.url).await?;
+
+    if let Some(sha256) = &release.sha256 {
+        Hashing::verify_sha256(&data, sha256)
+            .map_err(|e| anyhow::anyhow!("Checksum verification failed: {e}"))?;
+        println!("Checksum verified for {}", release.name);
+    }
+
+    ArchiveExtractor::extract_tar_gz(&data, dest)
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to extract {}: {e}", release.url))
+}
+
+/// The target triple prebuilt releases use for an OS/arch pair
+/// (python-build-standalone, rustup).
+pub fn target_triple(os: &str, arch: &str) -> Result<&'static str> {
+    match (os, arch) {
+        ("macos", "aarch64") => Ok("aarch64-apple-darwin"),
+        ("macos", "x86_64") => Ok("x86_64-apple-darwin"),
+        ("linux", "aarch64") => Ok("aarch64-unknown-linux-gnu"),
+        ("linux", "x86_64") => Ok("x86_64-unknown-linux-gnu"),
+        _ => anyhow::bail!("No prebuilt releases are available for {}-{}", os, arch),
+    }
+}
+
+/// Find `filename` in a `sha256sum`-style listing (`<sha256>  <filename>` per line).
+pub(crate) fn checksum_for(sums: &str, filename: &str) -> Option<String> {
+    sums.lines().find_map(|line| {
+        let (hash, name) = line.split_once(char::is_whitespace)?;
+        let name = name.trim().trim_start_matches('*');
+        (name == filename).then(|| hash.to_ascii_lowercase())
+    })
+}
+
+pub(crate) async fn fetch_bytes(url: &str) -> Result<Vec<u8>> {
+    let response = NetUtils::client()
+        .get(url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to download {}", url))?;
+
+    if !response.status().is_success() {
+        anyhow::bail!("Failed to download {}: HTTP {}", url, response.status());
+    }
+
+    let bytes = response
+        .bytes()
+        .await
+        .with_context(|| format!("Failed to read {}", url))?;
+    Ok(bytes.to_vec())
+}
+
+/// Whether `url` answers a `HEAD` request successfully.
+pub(crate) async fn url_exists(url: &str) -> bool {
+    NetUtils::client()
+        .head(url)
+        .send()
+        .await
+        .is_ok_and(|response| response.status().is_success())
+}
+
+pub(crate) async fn fetch_text(url: &str) -> Result<String> {
+    let bytes = fetch_bytes(url).await?;
+    String::from_utf8(bytes).with_context(|| format!("{} is not valid UTF-8", url))
+}
@@ -0,0 +1,251 @@
+use crate::actions::install::version_matches;
+use crate::registries::{Release, checksum_for, fetch_text};
+use crate::specs::config::LayeredConfig;
+use crate::specs::tool::ToolSpec;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+
+/// Where official Node.js builds are published.
+pub const DEFAULT_DIST_URL: &str = "https://nodejs.org/dist";
+
+/// Node.js runtimes from the official distribution: `index.json` for the version
+/// listing, platform tarballs verified against `SHASUMS256.txt`.
+///
+/// The base URL comes from `settings.node.dist_url` (or `STILL_NODE__DIST_URL`),
+/// so a mirror or a local fixture server can stand in for nodejs.org.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NodeRegistry {
+    pub dist_url: String,
+}
+
+/// One entry of `index.json`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct NodeVersion {
+    /// `v22.11.0`
+    pub version: String,
+    #[serde(default)]
+    pub date: String,
+    /// Available builds, e.g. `linux-x64`, `osx-arm64-tar`.
+    #[serde(default)]
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub lts: Lts,
+}
+
+/// `"lts": false` or `"lts": "Iron"`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Lts {
+    Flag(bool),
+    Codename(String),
+}
+
+impl Default for Lts {
+    fn default() -> Self {
+        Lts::Flag(false)
+    }
+}
+
+impl NodeVersion {
+    pub fn number(&self) -> &str {
+        self.version.strip_prefix('v').unwrap_or(&self.version)
+    }
+
+    pub fn lts_codename(&self) -> Option<&str> {
+        match &self.lts {
+            Lts::Codename(codename) => Some(codename),
+            Lts::Flag(_) => None,
+        }
+    }
+}
+
+impl NodeRegistry {
+    pub fn new(dist_url: impl Into<String>) -> Self {
+        Self {
+            dist_url: dist_url.into().trim_end_matches('/').to_string(),
+        }
+    }
+
+    pub fn from_config(config: &LayeredConfig) -> Self {
+        Self::new(
+            config
+                .get_str("settings.node.dist_url")
+                .unwrap_or(DEFAULT_DIST_URL),
+        )
+    }
+
+    pub async fn versions(&self) -> Result<Vec<NodeVersion>> {
+        let url = format!("{}/index.json", self.dist_url);
+        let body = fetch_text(&url).await?;
+        let mut versions: Vec<NodeVersion> =
+            serde_json::from_str(&body).with_context(|| format!("Failed to parse {}", url))?;
+        versions.sort_by_cached_key(|v| std::cmp::Reverse(semver::Version::parse(v.number()).ok()));
+        Ok(versions)
+    }
+
+    pub async fn resolve(&self, tool: &ToolSpec) -> Result<Release> {
+        let (index_key, platform) = platform(std::env::consts::OS, std::env::consts::ARCH)?;
+        let versions = self.versions().await?;
+        let selected = select(&versions, &tool.version).ok_or_else(|| {
+            anyhow::anyhow!(
+                "No Node.js release matches {}. Latest LTS: {}",
+                tool.version,
+                select(&versions, "lts").map_or("unknown", |v| v.number())
+            )
+        })?;
+
+        if !selected.files.iter().any(|f| f == index_key) {
+            anyhow::bail!("Node.js {} has no {} build", selected.version, platform);
+        }
+
+        let filename = format!("node-{}-{}.tar.gz", selected.version, platform);
+        let release_url = format!("{}/{}", self.dist_url, selected.version);
+        let shasums = fetch_text(&format!("{}/SHASUMS256.txt", release_url)).await?;
+        let sha256 = checksum_for(&shasums, &filename)
+            .ok_or_else(|| anyhow::anyhow!("{} is not listed in SHASUMS256.txt", filename))?;
+
+        Ok(Release {
+            name: "node".to_string(),
+            version: selected.number().to_string(),
+            url: format!("{}/{}", release_url, filename),
+            sha256: Some(sha256),
+            integrity: None,
+            size: None,
+            bins: ["bin/node", "bin/npm", "bin/npx"]
+                .map(String::from)
+                .to_vec(),
+            from_source: false,
+        })
+    }
+}
+
+/// The `index.json` file key and tarball suffix for an OS/arch pair.
+pub fn platform(os: &str, arch: &str) -> Result<(&'static str, &'static str)> {
+    match (os, arch) {
+        ("macos", "aarch64") => Ok(("osx-arm64-tar", "darwin-arm64")),
+        ("macos", "x86_64") => Ok(("osx-x64-tar", "darwin-x64")),
+        ("linux", "aarch64") => Ok(("linux-arm64", "linux-arm64")),
+        ("linux", "x86_64") => Ok(("linux-x64", "linux-x64")),
+        _ => anyhow::bail!("Node.js builds are not available for {}-{}", os, arch),
+    }
+}
+
+/// Pick the newest release matching `requested`: `latest`, `lts`, `lts/<codename>`,
+/// a bare codename (`iron`) or a (partial) version. `versions` must be newest first.
+pub fn select<'a>(versions: &'a [NodeVersion], requested: &str) -> Option<&'a NodeVersion> {
+    let requested = requested.to_ascii_lowercase();
+    let codename = requested.strip_prefix("lts/").unwrap_or(&requested);
+
+    versions.iter().find(|v| match requested.as_str() {
+        "latest" => true,
+        "lts" => v.lts_codename().is_some(),
+        _ if codename.chars().all(|c| c.is_ascii_alphabetic()) => v
+            .lts_codename()
+            .is_some_and(|name| name.eq_ignore_ascii_case(codename)),
+        _ => version_matches(&requested, v.number()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::hashing::Hashing;
+    use crate::utils::net::fixture;
+    use crate::utils::scratch::Scratch;
+
+    fn index(platform_key: &str) -> String {
+        serde_json::json!([
+            { "version": "v20.18.1", "files": [platform_key], "lts": "Iron" },
+            { "version": "v23.3.0", "files": [platform_key], "lts": false },
+            { "version": "v22.12.0", "files": [platform_key], "lts": "Jod" },
+            { "version": "v20.9.0", "files": ["headers"], "lts": "Iron" },
+        ])
+        .to_string()
+    }
+
+    fn sorted(platform_key: &str) -> Vec<NodeVersion> {
+        let mut versions: Vec<NodeVersion> = serde_json::from_str(&index(platform_key)).unwrap();
+        versions.sort_by_cached_key(|v| std::cmp::Reverse(semver::Version::parse(v.number()).ok()));
+        versions
+    }
+
+    #[test]
+    fn selects_versions_and_lts_lines() {
+        let versions = sorted("linux-x64");
+        let pick = |requested: &str| select(&versions, requested).map(|v| v.number());
+
+        assert_eq!(pick("latest"), Some("23.3.0"));
+        assert_eq!(pick("lts"), Some("22.12.0"));
+        assert_eq!(pick("lts/iron"), Some("20.18.1"));
+        assert_eq!(pick("Iron"), Some("20.18.1"));
+        assert_eq!(pick("20"), Some("20.18.1"));
+        assert_eq!(pick("20.9"), Some("20.9.0"));
+        assert_eq!(pick("18"), None);
+        assert_eq!(pick("lts/hydrogen"), None);
+    }
+
+    #[test]
+    fn installs_from_a_local_dist_server() {
+        let (index_key, platform) = platform(std::env::consts::OS, std::env::consts::ARCH).unwrap();
+        let filename = format!("node-v20.18.1-{}.tar.gz", platform);
+        let tarball = fixture::tar_gz(
+            &format!("node-v20.18.1-{}", platform),
+            &[("bin/node", b"#!/bin/sh\necho v20.18.1\n")],
+        );
+        let shasums = format!(
+            "{}  {}\n{}  node-v20.18.1.tar.gz\n",
+            Hashing::sha256(&tarball),
+            filename,
+            "0".repeat(64)
+        );
+
+        let base = fixture::serve(vec![
+            ("/index.json".into(), index(index_key).into_bytes()),
+            ("/v20.18.1/SHASUMS256.txt".into(), shasums.into_bytes()),
+            (format!("/v20.18.1/{}", filename), tarball),
+        ]);
+        let registry = NodeRegistry::new(format!("{}/", base));
+        let tool: ToolSpec = "node@lts/iron".parse().unwrap();
+
+        let dest = Scratch::new("node");
+
+        fixture::block_on(async {
+            let release = registry.resolve(&tool).await.unwrap();
+            assert_eq!(release.version, "20.18.1");
+            assert_eq!(release.url, format!("{}/v20.18.1/{}", base, filename));
+            crate::registries::install_archive(&release, &dest)
+                .await
+                .unwrap();
+        });
+        assert!(dest.join("bin/node").is_file());
+    }
+
+    #[test]
+    fn rejects_tampered_downloads() {
+        let (index_key, platform) = platform(std::env::consts::OS, std::env::consts::ARCH).unwrap();
+        let filename = format!("node-v23.3.0-{}.tar.gz", platform);
+        let base = fixture::serve(vec![
+            ("/index.json".into(), index(index_key).into_bytes()),
+            (
+                "/v23.3.0/SHASUMS256.txt".into(),
+                format!("{}  {}\n", "0".repeat(64), filename).into_bytes(),
+            ),
+            (
+                format!("/v23.3.0/{}", filename),
+                fixture::tar_gz("node", &[("bin/node", b"")]),
+            ),
+        ]);
+        let registry = NodeRegistry::new(base);
+        let scratch = Scratch::new("node-bad");
+        let dest = scratch.join("node");
+
+        let err = fixture::block_on(async {
+            let release = registry.resolve(&"node".parse().unwrap()).await.unwrap();
+            crate::registries::install_archive(&release, &dest)
+                .await
+                .unwrap_err()
+        });
+        assert!(format!("{err:#}").contains("Checksum"), "{err:#}");
+        assert!(!dest.exists());
+    }
+}
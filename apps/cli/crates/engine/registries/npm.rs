@@ -0,0 +1,454 @@
+use crate::actions::env::find_installed;
+use crate::actions::install::version_matches;
+use crate::registries::{Release, fetch_bytes, fetch_text};
+use crate::specs::config::LayeredConfig;
+use crate::specs::tool::ToolSpec;
+use crate::system::System;
+use crate::utils::hashing::Hashing;
+use crate::utils::paths::PathOps;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// The public npm registry.
+pub const DEFAULT_REGISTRY_URL: &str = "https://registry.npmjs.org";
+
+/// npm packages installed as tools, each into its own prefix
+/// (`tool_dir/npm-<package>/<version>`) by the npm of a Still-managed Node.js.
+///
+/// The tarball is checked against the packument's `dist.integrity` before npm sees it;
+/// npm then installs it with its dependencies from the same registry
+/// (`settings.npm.registry`). Every `bin` entry gets a shim that runs it with that
+/// Node.js, whatever `node` happens to be on `PATH`.
+///
+/// The Node.js is looked up when the package is installed, so it may come from the
+/// same `still install`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NpmRegistry {
+    pub registry_url: String,
+    /// Where installed Node.js versions are looked up.
+    pub tool_dir: PathBuf,
+    /// The `node` version that runs npm and the package's bins.
+    pub node_version: String,
+    /// A Node.js installed by the same plan and not committed yet: where it is
+    /// staged, and the install path it will have.
+    pub staged_node: Option<(PathBuf, PathBuf)>,
+}
+
+/// Registry metadata for a package (`GET /<name>`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct Packument {
+    pub name: String,
+    #[serde(default, rename = "dist-tags")]
+    pub dist_tags: BTreeMap<String, String>,
+    #[serde(default)]
+    pub versions: BTreeMap<String, PackageVersion>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PackageVersion {
+    pub version: String,
+    #[serde(default)]
+    pub bin: Option<Bin>,
+    pub dist: Dist,
+}
+
+/// `"bin": "cli.js"` or `"bin": { "name": "cli.js" }`.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Bin {
+    Single(String),
+    Map(BTreeMap<String, String>),
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Dist {
+    pub tarball: String,
+    #[serde(default)]
+    pub integrity: Option<String>,
+}
+
+impl Bin {
+    /// Executable name to script path. A single `bin` is named after the
+    /// package without its scope.
+    pub fn entries(&self, package: &str) -> BTreeMap<String, String> {
+        match self {
+            Bin::Map(bins) => bins.clone(),
+            Bin::Single(script) => {
+                let name = package.rsplit('/').next().unwrap_or(package);
+                BTreeMap::from([(name.to_string(), script.clone())])
+            }
+        }
+    }
+}
+
+impl PackageVersion {
+    pub fn bins(&self, package: &str) -> BTreeMap<String, String> {
+        self.bin
+            .as_ref()
+            .map(|bin| bin.entries(package))
+            .unwrap_or_default()
+    }
+}
+
+impl NpmRegistry {
+    pub fn new(
+        registry_url: impl Into<String>,
+        tool_dir: PathBuf,
+        node_version: impl Into<String>,
+    ) -> Self {
+        Self {
+            registry_url: registry_url.into().trim_end_matches('/').to_string(),
+            tool_dir,
+            node_version: node_version.into(),
+            staged_node: None,
+        }
+    }
+
+    /// Uses the installed Node.js matching the configured `node` version,
+    /// or the newest installed one when `node` is not pinned.
+    pub fn from_config(config: &LayeredConfig) -> Self {
+        let node_version = config
+            .tool_versions()
+            .get("node")
+            .map_or_else(|| "latest".to_string(), |v| v.to_string());
+
+        Self::new(
+            config
+                .get_str("settings.npm.registry")
+                .unwrap_or(DEFAULT_REGISTRY_URL),
+            System::tool_dir(),
+            node_version,
+        )
+    }
+
+    /// The Node.js to run npm with, and the install path the shims refer to.
+    /// An installed one that matches `node_version` wins over a staged one.
+    fn node(&self) -> Option<(PathBuf, PathBuf)> {
+        find_installed(&self.tool_dir, "node", &self.node_version)
+            .map(|(_, path)| (path.clone(), path))
+            .or_else(|| self.staged_node.clone())
+    }
+
+    pub async fn packument(&self, package: &str) -> Result<Packument> {
+        let url = format!("{}/{}", self.registry_url, package.replace('/', "%2F"));
+        let body = fetch_text(&url).await?;
+        serde_json::from_str(&body).with_context(|| format!("Failed to parse {}", url))
+    }
+
+    pub async fn resolve(&self, tool: &ToolSpec) -> Result<Release> {
+        let packument = self.packument(&tool.name).await?;
+        let selected = select(&packument, &tool.version).ok_or_else(|| {
+            anyhow::anyhow!(
+                "No version of {} matches {}. Dist-tags: {}",
+                tool.name,
+                tool.version,
+                packument
+                    .dist_tags
+                    .iter()
+                    .map(|(tag, version)| format!("{}={}", tag, version))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?;
+
+        Ok(Release {
+            name: tool.name.clone(),
+            version: selected.version.clone(),
+            url: selected.dist.tarball.clone(),
+            sha256: None,
+            integrity: selected.dist.integrity.clone(),
+            size: None,
+            bins: selected
+                .bins(&tool.name)
+                .keys()
+                .map(|bin| format!("bin/{}", bin))
+                .collect(),
+            from_source: false,
+        })
+    }
+
+    /// Verify the tarball, let npm install it under `dest`, then replace npm's
+    /// bin links with shims pinned to the Still-managed Node.js.
+    pub async fn install(&self, release: &Release, dest: &Path) -> Result<()> {
+        let (node, node_path) = self.node().ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} needs a Still-managed Node.js. Run `still install node` first",
+                release.name
+            )
+        })?;
+
+        println!("Downloading {} {}...", release.name, release.version);
+        let data = fetch_bytes(&release.url).await?;
+        let integrity = release
+            .integrity
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("{} publishes no integrity hash", release.url))?;
+        Hashing::verify_integrity(&data, integrity)
+            .map_err(|e| anyhow::anyhow!("Integrity verification failed: {e}"))?;
+        println!("Integrity verified for {}", release.name);
+
+        let tarball = dest.join(".package.tgz");
+        tokio::fs::write(&tarball, &data)
+            .await
+            .with_context(|| format!("Failed to write {}", tarball.display()))?;
+        let installed = self.npm_install(&node, dest, &tarball).await;
+        let _ = tokio::fs::remove_file(&tarball).await;
+        installed?;
+
+        let package_dir = dest.join("lib").join("node_modules").join(&release.name);
+        let manifest_path = package_dir.join("package.json");
+        let manifest = tokio::fs::read_to_string(&manifest_path)
+            .await
+            .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+        let manifest: serde_json::Value = serde_json::from_str(&manifest)
+            .with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+        let bin: Option<Bin> = manifest
+            .get("bin")
+            .and_then(|bin| serde_json::from_value(bin.clone()).ok());
+        let bins = bin
+            .map(|bin| bin.entries(&release.name))
+            .unwrap_or_default();
+
+        let bin_dir = dest.join("bin");
+        tokio::fs::create_dir_all(&bin_dir).await?;
+        for (name, script) in &bins {
+            let shim = bin_dir.join(name);
+            if shim.exists() || shim.is_symlink() {
+                tokio::fs::remove_file(&shim).await?;
+            }
+            let contents = shim_script(&node_path, &release.name, script, &release.version);
+            tokio::fs::write(&shim, contents)
+                .await
+                .with_context(|| format!("Failed to write shim {}", shim.display()))?;
+            tokio::fs::set_permissions(&shim, std::fs::Permissions::from_mode(0o755)).await?;
+        }
+        Ok(())
+    }
+
+    async fn npm_install(&self, node: &Path, prefix: &Path, tarball: &Path) -> Result<()> {
+        let node_bin = node.join("bin");
+        let path = std::env::var_os("PATH").unwrap_or_default();
+        let path = std::env::join_paths(
+            std::iter::once(node_bin.clone()).chain(std::env::split_paths(&path)),
+        )?;
+
+        let status = tokio::process::Command::new(node_bin.join("npm"))
+            .args(["install", "--global", "--no-audit", "--no-fund"])
+            .args(["--loglevel", "error", "--registry", &self.registry_url])
+            .arg("--prefix")
+            .arg(prefix)
+            .arg(tarball)
+            .env("PATH", path)
+            .env(
+                "npm_config_cache",
+                System::cache_dir().join("still").join("npm"),
+            )
+            .env("npm_config_update_notifier", "false")
+            .status()
+            .await
+            .context("Failed to run npm")?;
+        if !status.success() {
+            anyhow::bail!("npm install failed ({})", status);
+        }
+        Ok(())
+    }
+}
+
+/// The version `requested` names: a dist-tag (`latest`, `next`), an exact version,
+/// or the newest stable version with that prefix.
+pub fn select<'a>(packument: &'a Packument, requested: &str) -> Option<&'a PackageVersion> {
+    if let Some(tagged) = packument.dist_tags.get(requested) {
+        return packument.versions.get(tagged);
+    }
+    if let Some(exact) = packument.versions.get(requested) {
+        return Some(exact);
+    }
+
+    packument
+        .versions
+        .values()
+        .filter(|v| !v.version.contains('-'))
+        .filter(|v| requested == "latest" || version_matches(requested, &v.version))
+        .max_by_key(|v| semver::Version::parse(&v.version).ok())
+}
+
+/// A POSIX shell shim that runs `script` of `package` with the given Node.js.
+/// The package is found relative to the shim (following symlinks such as the one
+/// in `bin_dir`, relative ones against the link's directory), so the shim keeps
+/// working after the staged install is moved into place.
+fn shim_script(node: &Path, package: &str, script: &str, version: &str) -> String {
+    format!(
+        "#!/bin/sh\n\
+         # Generated by still for {package}@{version}\n\
+         shim=\"$0\"\n\
+         while [ -L \"$shim\" ]; do\n\
+         target=\"$(readlink \"$shim\")\"\n\
+         case \"$target\" in\n\
+         /*) shim=\"$target\" ;;\n\
+         *) shim=\"$(dirname \"$shim\")/$target\" ;;\n\
+         esac\n\
+         done\n\
+         exec \"{node}\" \"$(dirname \"$shim\")/../lib/node_modules/{package}/{script}\" \"$@\"\n",
+        node = node.join("bin").join("node").display(),
+        script = script.trim_start_matches("./"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::net::fixture;
+    use crate::utils::scratch::Scratch;
+
+    fn packument(base: &str, integrity: &str) -> serde_json::Value {
+        let version = |version: &str| {
+            serde_json::json!({
+                "name": "hello-still",
+                "version": version,
+                "bin": { "hello-still": "./cli.js" },
+                "dist": {
+                    "tarball": format!("{base}/hello-still/-/hello-still-{version}.tgz"),
+                    "integrity": integrity,
+                },
+            })
+        };
+        serde_json::json!({
+            "name": "hello-still",
+            "dist-tags": { "latest": "1.2.0", "next": "2.0.0-beta.1" },
+            "versions": {
+                "1.1.0": version("1.1.0"),
+                "1.2.0": version("1.2.0"),
+                "2.0.0-beta.1": version("2.0.0-beta.1"),
+            },
+        })
+    }
+
+    /// A Still-managed Node.js under `tool_dir` whose `npm` only unpacks the
+    /// package into the prefix and whose `node` runs scripts with `sh`.
+    fn fake_node(tool_dir: &Path) -> PathBuf {
+        use std::os::unix::fs::PermissionsExt;
+
+        let node = tool_dir.join("node").join("22.11.0");
+        let bin = node.join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        for (name, script) in [
+            ("node", "#!/bin/sh\nexec sh \"$@\"\n"),
+            (
+                "npm",
+                "#!/bin/sh\n\
+                 while [ $# -gt 1 ]; do [ \"$1\" = --prefix ] && prefix=\"$2\"; shift; done\n\
+                 package=\"$prefix/lib/node_modules/hello-still\"\n\
+                 mkdir -p \"$package\"\n\
+                 tar xzf \"$1\" -C \"$package\" --strip-components=1\n",
+            ),
+        ] {
+            std::fs::write(bin.join(name), script).unwrap();
+            std::fs::set_permissions(bin.join(name), std::fs::Permissions::from_mode(0o755))
+                .unwrap();
+        }
+        node
+    }
+
+    #[test]
+    fn selects_dist_tags_and_version_prefixes() {
+        let packument: Packument =
+            serde_json::from_value(packument("https://example.com", "sha512-x")).unwrap();
+        let pick = |requested: &str| select(&packument, requested).map(|v| v.version.as_str());
+
+        assert_eq!(pick("latest"), Some("1.2.0"));
+        assert_eq!(pick("next"), Some("2.0.0-beta.1"));
+        assert_eq!(pick("1"), Some("1.2.0"));
+        assert_eq!(pick("1.1"), Some("1.1.0"));
+        assert_eq!(pick("2"), None);
+        assert_eq!(
+            packument.versions["1.2.0"]
+                .bins("hello-still")
+                .keys()
+                .collect::<Vec<_>>(),
+            ["hello-still"]
+        );
+    }
+
+    #[test]
+    fn installs_from_a_local_registry() {
+        let root = Scratch::new("npm");
+        let tool_dir = root.join("tools");
+
+        let package_json = serde_json::json!({
+            "name": "hello-still",
+            "version": "1.2.0",
+            "bin": { "hello-still": "./cli.js" },
+        })
+        .to_string();
+        let tarball = fixture::tar_gz(
+            "package",
+            &[
+                ("package.json", package_json.as_bytes()),
+                ("cli.js", b"echo \"hello $1\"\n"),
+            ],
+        );
+        let integrity = Hashing::sha512_integrity(&tarball);
+        let base = fixture::serve_with(|base| {
+            vec![
+                (
+                    "/hello-still".into(),
+                    packument(base, &integrity).to_string().into_bytes(),
+                ),
+                (
+                    "/hello-still/-/hello-still-1.2.0.tgz".into(),
+                    tarball.clone(),
+                ),
+            ]
+        });
+        let mut registry = NpmRegistry::new(base, tool_dir.clone(), "22");
+
+        let dest = tool_dir.join("npm-hello-still").join("1.2.0");
+        std::fs::create_dir_all(&dest).unwrap();
+        let release =
+            fixture::block_on(registry.resolve(&"npm:hello-still@1".parse().unwrap())).unwrap();
+        assert_eq!(release.version, "1.2.0");
+        assert_eq!(release.integrity.as_deref(), Some(integrity.as_str()));
+        assert_eq!(release.bins, ["bin/hello-still"]);
+
+        // Without a Node.js there is nothing to run npm with.
+        let err = fixture::block_on(registry.install(&release, &dest)).unwrap_err();
+        assert!(err.to_string().contains("still install node"), "{err:#}");
+
+        // A Node.js staged by the same install is used under its final path.
+        let node = fake_node(&root.join("staging"));
+        registry.staged_node = Some((node, tool_dir.join("node/22.11.0")));
+        fixture::block_on(registry.install(&release, &dest)).unwrap();
+        let shim = std::fs::read_to_string(dest.join("bin/hello-still")).unwrap();
+        assert!(
+            shim.contains(&tool_dir.join("node/22.11.0/bin/node").display().to_string()),
+            "{shim}"
+        );
+
+        // Run the shim through a chain of symlinks, one of them relative.
+        fake_node(&tool_dir);
+        let bin = root.join("bin");
+        std::fs::create_dir_all(&bin).unwrap();
+        std::os::unix::fs::symlink(
+            "../tools/npm-hello-still/1.2.0/bin/hello-still",
+            bin.join("hello"),
+        )
+        .unwrap();
+        std::os::unix::fs::symlink(bin.join("hello"), root.join("hello-still")).unwrap();
+        let output = std::process::Command::new(root.join("hello-still"))
+            .arg("still")
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "hello still\n");
+
+        let tampered = Release {
+            integrity: Some(Hashing::sha512_integrity(b"something else")),
+            ..release
+        };
+        let err =
+            fixture::block_on(registry.install(&tampered, &root.join("tampered"))).unwrap_err();
+        assert!(format!("{err:#}").contains("Integrity"), "{err:#}");
+    }
+}
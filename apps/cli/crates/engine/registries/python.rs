@@ -0,0 +1,266 @@
+use crate::actions::install::version_matches;
+use crate::registries::{Release, checksum_for, fetch_text, install_archive, target_triple};
+use crate::specs::config::LayeredConfig;
+use crate::specs::tool::ToolSpec;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::path::Path;
+
+/// The newest release of python-build-standalone, as GitHub API JSON.
+pub const DEFAULT_MANIFEST_URL: &str =
+    "https://api.github.com/repos/astral-sh/python-build-standalone/releases/latest";
+
+/// Prebuilt, relocatable CPython from python-build-standalone (`install_only` builds).
+///
+/// The manifest is a release in GitHub API format (`tag_name` plus `assets` with names
+/// and download URLs). Checksums come from the release's `SHA256SUMS` asset, or a
+/// per-asset `.sha256` file on older releases. The manifest location is read from
+/// `settings.python.manifest_url` so local fixtures can replace GitHub.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PythonRegistry {
+    pub manifest_url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReleaseManifest {
+    #[serde(default)]
+    pub tag_name: String,
+    pub assets: Vec<ManifestAsset>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManifestAsset {
+    pub name: String,
+    pub browser_download_url: String,
+    #[serde(default)]
+    pub size: Option<u64>,
+}
+
+/// A build parsed from an asset name like
+/// `cpython-3.12.8+20241219-aarch64-apple-darwin-install_only.tar.gz`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StandaloneBuild {
+    pub version: String,
+    pub triple: String,
+}
+
+impl StandaloneBuild {
+    pub fn parse(asset_name: &str) -> Option<Self> {
+        let rest = asset_name
+            .strip_prefix("cpython-")?
+            .strip_suffix("-install_only.tar.gz")?;
+        let (version, rest) = rest.split_once('+')?;
+        let (_build_tag, triple) = rest.split_once('-')?;
+        Some(Self {
+            version: version.to_string(),
+            triple: triple.to_string(),
+        })
+    }
+}
+
+impl PythonRegistry {
+    pub fn new(manifest_url: impl Into<String>) -> Self {
+        Self {
+            manifest_url: manifest_url.into(),
+        }
+    }
+
+    pub fn from_config(config: &LayeredConfig) -> Self {
+        Self::new(
+            config
+                .get_str("settings.python.manifest_url")
+                .unwrap_or(DEFAULT_MANIFEST_URL),
+        )
+    }
+
+    pub async fn manifest(&self) -> Result<ReleaseManifest> {
+        let body = fetch_text(&self.manifest_url).await?;
+        serde_json::from_str(&body)
+            .with_context(|| format!("Failed to parse release manifest {}", self.manifest_url))
+    }
+
+    pub async fn resolve(&self, tool: &ToolSpec) -> Result<Release> {
+        let triple = target_triple(std::env::consts::OS, std::env::consts::ARCH)?;
+        let manifest = self.manifest().await?;
+        let (asset, build) = select(&manifest, triple, &tool.version).ok_or_else(|| {
+            anyhow::anyhow!(
+                "No standalone Python build matches {} for {} in release {}. Available: {}",
+                tool.version,
+                triple,
+                manifest.tag_name,
+                available(&manifest, triple).join(", ")
+            )
+        })?;
+
+        let sha256 = self
+            .checksum(&manifest, &asset.name)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("No checksum published for {}", asset.name))?;
+
+        Ok(Release {
+            name: "python".to_string(),
+            version: build.version,
+            url: asset.browser_download_url.clone(),
+            sha256: Some(sha256),
+            integrity: None,
+            size: asset.size,
+            bins: ["bin/python", "bin/python3", "bin/pip"]
+                .map(String::from)
+                .to_vec(),
+            from_source: false,
+        })
+    }
+
+    async fn checksum(
+        &self,
+        manifest: &ReleaseManifest,
+        asset_name: &str,
+    ) -> Result<Option<String>> {
+        let find = |name: &str| manifest.assets.iter().find(|a| a.name == name);
+
+        if let Some(sums) = find("SHA256SUMS") {
+            let sums = fetch_text(&sums.browser_download_url).await?;
+            return Ok(checksum_for(&sums, asset_name));
+        }
+        if let Some(sidecar) = find(&format!("{}.sha256", asset_name)) {
+            let sum = fetch_text(&sidecar.browser_download_url).await?;
+            return Ok(sum.split_whitespace().next().map(str::to_ascii_lowercase));
+        }
+        Ok(None)
+    }
+
+    /// Unpack the build, then add the unversioned `python` and `pip` entry points
+    /// next to `python3` and `pip3` where the build does not ship them.
+    pub async fn install(&self, release: &Release, dest: &Path) -> Result<()> {
+        install_archive(release, dest).await?;
+
+        let bin = dest.join("bin");
+        for (entry_point, target) in [("python", "python3"), ("pip", "pip3")] {
+            let link = bin.join(entry_point);
+            if !link.exists() && bin.join(target).exists() {
+                std::os::unix::fs::symlink(target, &link)
+                    .with_context(|| format!("Failed to create {}", link.display()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The newest `install_only` build for `triple` whose version matches `requested`.
+pub fn select<'a>(
+    manifest: &'a ReleaseManifest,
+    triple: &str,
+    requested: &str,
+) -> Option<(&'a ManifestAsset, StandaloneBuild)> {
+    manifest
+        .assets
+        .iter()
+        .filter_map(|asset| Some((asset, StandaloneBuild::parse(&asset.name)?)))
+        .filter(|(_, build)| build.triple == triple)
+        .filter(|(_, build)| {
+            requested.eq_ignore_ascii_case("latest") || version_matches(requested, &build.version)
+        })
+        .max_by_key(|(_, build)| semver::Version::parse(&build.version).ok())
+}
+
+fn available(manifest: &ReleaseManifest, triple: &str) -> Vec<String> {
+    manifest
+        .assets
+        .iter()
+        .filter_map(|asset| StandaloneBuild::parse(&asset.name))
+        .filter(|build| build.triple == triple)
+        .map(|build| build.version)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::hashing::Hashing;
+    use crate::utils::net::fixture;
+    use crate::utils::scratch::Scratch;
+
+    fn asset(name: &str) -> serde_json::Value {
+        serde_json::json!({
+            "name": name,
+            "browser_download_url": format!("https://example.com/{name}"),
+        })
+    }
+
+    #[test]
+    fn selects_the_newest_matching_minor() {
+        let manifest: ReleaseManifest = serde_json::from_value(serde_json::json!({
+            "tag_name": "20241219",
+            "assets": [
+                asset("cpython-3.12.8+20241219-x86_64-unknown-linux-gnu-install_only.tar.gz"),
+                asset("cpython-3.12.8+20241219-x86_64-unknown-linux-gnu-pgo+lto-full.tar.zst"),
+                asset("cpython-3.11.11+20241219-x86_64-unknown-linux-gnu-install_only.tar.gz"),
+                asset("cpython-3.13.1+20241219-x86_64-unknown-linux-gnu-install_only.tar.gz"),
+                asset("cpython-3.13.1+20241219-aarch64-apple-darwin-install_only.tar.gz"),
+                asset("SHA256SUMS"),
+            ],
+        }))
+        .unwrap();
+
+        let pick = |requested: &str| {
+            select(&manifest, "x86_64-unknown-linux-gnu", requested).map(|(_, b)| b.version)
+        };
+        assert_eq!(pick("3.12").as_deref(), Some("3.12.8"));
+        assert_eq!(pick("3").as_deref(), Some("3.13.1"));
+        assert_eq!(pick("latest").as_deref(), Some("3.13.1"));
+        assert_eq!(pick("3.11.11").as_deref(), Some("3.11.11"));
+        assert_eq!(pick("3.10"), None);
+        assert_eq!(
+            select(&manifest, "aarch64-apple-darwin", "3.12").map(|(_, b)| b.version),
+            None
+        );
+    }
+
+    #[test]
+    fn installs_from_a_local_manifest() {
+        let triple = target_triple(std::env::consts::OS, std::env::consts::ARCH).unwrap();
+        let name = format!("cpython-3.12.8+20241219-{}-install_only.tar.gz", triple);
+        let tarball = fixture::tar_gz(
+            "python",
+            &[
+                ("bin/python3", b"#!/bin/sh\n"),
+                ("bin/pip3", b"#!/bin/sh\n"),
+            ],
+        );
+        let sums = format!("{}  {}\n", Hashing::sha256(&tarball), name);
+
+        // The manifest links to files on the same server.
+        let server = fixture::serve_with(|base| {
+            let manifest = serde_json::json!({
+                "tag_name": "20241219",
+                "assets": [
+                    { "name": name, "browser_download_url": format!("{base}/{name}") },
+                    { "name": "SHA256SUMS", "browser_download_url": format!("{base}/SHA256SUMS") },
+                ],
+            });
+            vec![
+                ("/release.json".into(), manifest.to_string().into_bytes()),
+                ("/SHA256SUMS".into(), sums.clone().into_bytes()),
+                (format!("/{}", name), tarball.clone()),
+            ]
+        });
+        let registry = PythonRegistry::new(format!("{}/release.json", server));
+
+        let dest = Scratch::new("python");
+
+        fixture::block_on(async {
+            let release = registry
+                .resolve(&"python@3.12".parse().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(release.version, "3.12.8");
+            registry.install(&release, &dest).await.unwrap();
+        });
+        assert!(dest.join("bin/python3").is_file());
+        assert_eq!(
+            std::fs::read_link(dest.join("bin/python")).unwrap(),
+            Path::new("python3")
+        );
+        assert!(dest.join("bin/pip").exists());
+    }
+}
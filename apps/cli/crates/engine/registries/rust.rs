@@ -0,0 +1,562 @@
+use crate::registries::{Release, checksum_for, fetch_bytes, fetch_text, target_triple};
+use crate::specs::config::LayeredConfig;
+use crate::specs::toml::ToolOptions;
+use crate::specs::tool::ToolSpec;
+use crate::system::System;
+use crate::utils::hashing::Hashing;
+use crate::utils::paths::PathOps;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+
+/// Where rustup and Rust toolchains are published.
+pub const DEFAULT_DIST_SERVER: &str = "https://static.rust-lang.org";
+
+/// Toolchain files rustup reads, in the order it prefers them.
+pub const TOOLCHAIN_FILES: &[&str] = &["rust-toolchain.toml", "rust-toolchain"];
+
+/// Rust toolchains managed by a private rustup.
+///
+/// rustup and its toolchains live in `root_dir/rustup` and `root_dir/cargo`
+/// (`RUSTUP_HOME` / `CARGO_HOME`), separate from any user installation. The install
+/// path under `tool_dir` only holds links to the toolchain's binaries.
+///
+/// Components and targets are the union of `[tools.rust]` and the nearest
+/// `rust-toolchain.toml`, which also supplies the channel when none is pinned.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RustRegistry {
+    pub dist_server: String,
+    pub components: Vec<String>,
+    pub targets: Vec<String>,
+    /// Channel from the nearest toolchain file, used for `rust@latest`.
+    pub toolchain_file_channel: Option<String>,
+}
+
+pub fn rustup_home() -> PathBuf {
+    System::root_dir().join("rustup")
+}
+
+pub fn cargo_home() -> PathBuf {
+    System::root_dir().join("cargo")
+}
+
+impl RustRegistry {
+    pub fn from_config(config: &LayeredConfig) -> Result<Self> {
+        let dist_server = config
+            .get_str("settings.rust.dist_server")
+            .unwrap_or(DEFAULT_DIST_SERVER)
+            .trim_end_matches('/')
+            .to_string();
+
+        let manifest_options = match config.get("tools.rust").map(|v| &v.value) {
+            Some(toml::Value::Table(table)) => Some(
+                ToolOptions::deserialize(table.clone()).context("Invalid [tools.rust] options")?,
+            ),
+            _ => None,
+        };
+
+        let start = match &config.project_root {
+            Some(root) => root.clone(),
+            None => std::env::current_dir().context("Failed to read current directory")?,
+        };
+        let file_options = find_toolchain_file(&start)?.map(|(_, options)| options);
+
+        let mut registry = Self::merge(
+            dist_server,
+            manifest_options.as_ref(),
+            file_options.as_ref(),
+        );
+        // The toolchain file only picks the channel when Still pins none, not even
+        // `latest`; its components and targets still apply.
+        if config.get("tools.rust").is_some() {
+            registry.toolchain_file_channel = None;
+        }
+        Ok(registry)
+    }
+
+    fn merge(
+        dist_server: String,
+        manifest: Option<&ToolOptions>,
+        file: Option<&ToolOptions>,
+    ) -> Self {
+        let mut components = Vec::new();
+        let mut targets = Vec::new();
+        for options in manifest.into_iter().chain(file) {
+            for component in &options.components {
+                if !components.contains(component) {
+                    components.push(component.clone());
+                }
+            }
+            for target in &options.targets {
+                if !targets.contains(target) {
+                    targets.push(target.clone());
+                }
+            }
+        }
+
+        Self {
+            dist_server,
+            components,
+            targets,
+            toolchain_file_channel: file.map(|options| options.version.clone()),
+        }
+    }
+
+    /// The rustup toolchain name for a spec: the requested version, else the
+    /// toolchain file's channel, else `stable`.
+    pub fn toolchain(&self, tool: &ToolSpec) -> String {
+        if !tool.is_latest() {
+            return tool.version.clone();
+        }
+        self.toolchain_file_channel
+            .clone()
+            .unwrap_or_else(|| "stable".to_string())
+    }
+
+    pub async fn resolve(&self, tool: &ToolSpec) -> Result<Release> {
+        if tool.name != "rust" {
+            anyhow::bail!("The rustup backend only installs `rust`, not {}", tool.name);
+        }
+
+        let toolchain = self.toolchain(tool);
+        Ok(Release {
+            name: "rust".to_string(),
+            url: format!("{}/dist/channel-rust-{}.toml", self.dist_server, toolchain),
+            sha256: None,
+            integrity: None,
+            size: None,
+            bins: toolchain_bins(&self.components),
+            version: toolchain,
+            from_source: false,
+        })
+    }
+
+    /// Install the toolchain with rustup, bring its components and targets in sync,
+    /// and link its binaries into `dest/bin`.
+    pub async fn install(&self, release: &Release, dest: &Path) -> Result<()> {
+        self.ensure_rustup().await?;
+
+        let toolchain = &release.version;
+        println!("Installing Rust toolchain {}...", toolchain);
+        self.rustup(&toolchain_install_args(
+            toolchain,
+            &self.components,
+            &self.targets,
+        ))
+        .await?;
+
+        // Toolchains that were already installed keep their old components otherwise.
+        if !self.components.is_empty() {
+            let mut args = vec!["component", "add", "--toolchain", toolchain];
+            args.extend(self.components.iter().map(String::as_str));
+            self.rustup(&args).await?;
+        }
+        if !self.targets.is_empty() {
+            let mut args = vec!["target", "add", "--toolchain", toolchain];
+            args.extend(self.targets.iter().map(String::as_str));
+            self.rustup(&args).await?;
+        }
+
+        // ...and the ones that are no longer listed.
+        let host = target_triple(std::env::consts::OS, std::env::consts::ARCH)?;
+        let installed = self
+            .rustup_output(&["component", "list", "--installed", "--toolchain", toolchain])
+            .await?;
+        let stale = stale_components(&installed, host, &self.components);
+        if !stale.is_empty() {
+            let mut args = vec!["component", "remove", "--toolchain", toolchain];
+            args.extend(stale.iter().map(String::as_str));
+            self.rustup(&args).await?;
+        }
+        let installed = self
+            .rustup_output(&["target", "list", "--installed", "--toolchain", toolchain])
+            .await?;
+        let stale = stale_targets(&installed, host, &self.targets);
+        if !stale.is_empty() {
+            let mut args = vec!["target", "remove", "--toolchain", toolchain];
+            args.extend(stale.iter().map(String::as_str));
+            self.rustup(&args).await?;
+        }
+
+        link_toolchain(&installed_toolchain_dir(toolchain)?, dest)
+    }
+
+    fn rustup_path(&self) -> PathBuf {
+        cargo_home().join("bin").join("rustup")
+    }
+
+    /// Download and run `rustup-init` into the private homes, once.
+    async fn ensure_rustup(&self) -> Result<()> {
+        if self.rustup_path().exists() {
+            return Ok(());
+        }
+
+        let triple = target_triple(std::env::consts::OS, std::env::consts::ARCH)?;
+        let url = format!("{}/rustup/dist/{}/rustup-init", self.dist_server, triple);
+        println!("Downloading rustup...");
+        let data = fetch_bytes(&url).await?;
+        let sums = fetch_text(&format!("{}.sha256", url)).await?;
+        let sha256 = checksum_for(&sums, "rustup-init")
+            .or_else(|| sums.split_whitespace().next().map(str::to_ascii_lowercase))
+            .ok_or_else(|| anyhow::anyhow!("Empty checksum file for {}", url))?;
+        Hashing::verify_sha256(&data, &sha256)
+            .map_err(|e| anyhow::anyhow!("Checksum verification failed: {e}"))?;
+
+        let init = std::env::temp_dir().join(format!("still-rustup-init-{}", std::process::id()));
+        tokio::fs::write(&init, &data).await?;
+        tokio::fs::set_permissions(&init, std::fs::Permissions::from_mode(0o755)).await?;
+
+        let status = self
+            .command(&init)
+            .args([
+                "-y",
+                "--no-modify-path",
+                "--default-toolchain",
+                "none",
+                "--profile",
+                "minimal",
+            ])
+            .status()
+            .await
+            .context("Failed to run rustup-init");
+        let _ = tokio::fs::remove_file(&init).await;
+        if !status?.success() {
+            anyhow::bail!("rustup-init failed");
+        }
+        Ok(())
+    }
+
+    async fn rustup(&self, args: &[&str]) -> Result<()> {
+        let status = self
+            .command(&self.rustup_path())
+            .args(args)
+            .status()
+            .await
+            .context("Failed to run rustup")?;
+        if !status.success() {
+            anyhow::bail!("rustup {} failed", args.join(" "));
+        }
+        Ok(())
+    }
+
+    async fn rustup_output(&self, args: &[&str]) -> Result<String> {
+        let output = self
+            .command(&self.rustup_path())
+            .args(args)
+            .output()
+            .await
+            .context("Failed to run rustup")?;
+        if !output.status.success() {
+            anyhow::bail!("rustup {} failed", args.join(" "));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    fn command(&self, program: &Path) -> tokio::process::Command {
+        let mut command = tokio::process::Command::new(program);
+        command
+            .env("RUSTUP_HOME", rustup_home())
+            .env("CARGO_HOME", cargo_home())
+            .env("RUSTUP_INIT_SKIP_PATH_CHECK", "yes");
+        if self.dist_server != DEFAULT_DIST_SERVER {
+            command.env("RUSTUP_DIST_SERVER", &self.dist_server);
+        }
+        command
+    }
+}
+
+/// Arguments for `rustup toolchain install`.
+pub fn toolchain_install_args<'a>(
+    toolchain: &'a str,
+    components: &'a [String],
+    targets: &'a [String],
+) -> Vec<&'a str> {
+    let mut args = vec![
+        "toolchain",
+        "install",
+        toolchain,
+        "--profile",
+        "minimal",
+        "--no-self-update",
+    ];
+    for component in components {
+        args.extend(["--component", component]);
+    }
+    for target in targets {
+        args.extend(["--target", target]);
+    }
+    args
+}
+
+/// Components of the minimal profile, which every toolchain keeps.
+const PROFILE_COMPONENTS: &[&str] = &["cargo", "rustc", "rust-std"];
+
+/// Installed components (`rustup component list --installed`) that are neither in
+/// the minimal profile nor in `wanted`. rustup lists them with the host triple
+/// (`clippy-x86_64-unknown-linux-gnu`); the standard libraries of other targets
+/// are left to [`stale_targets`].
+pub fn stale_components(installed: &str, host: &str, wanted: &[String]) -> Vec<String> {
+    let suffix = format!("-{}", host);
+    installed
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with("rust-std-"))
+        .map(|line| line.strip_suffix(&suffix).unwrap_or(line))
+        .filter(|name| !PROFILE_COMPONENTS.contains(name))
+        .filter(|name| !wanted.iter().any(|w| w == name))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Installed targets (`rustup target list --installed`) other than the host and `wanted`.
+pub fn stale_targets(installed: &str, host: &str, wanted: &[String]) -> Vec<String> {
+    installed
+        .lines()
+        .map(str::trim)
+        .filter(|target| !target.is_empty() && *target != host)
+        .filter(|target| !wanted.iter().any(|w| w == target))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Binaries to expose for a toolchain with `components`.
+pub fn toolchain_bins(components: &[String]) -> Vec<String> {
+    let mut bins = vec!["bin/cargo", "bin/rustc", "bin/rustdoc"];
+    for component in components {
+        match component.as_str() {
+            "rustfmt" => bins.extend(["bin/rustfmt", "bin/cargo-fmt"]),
+            "clippy" => bins.extend(["bin/cargo-clippy", "bin/clippy-driver"]),
+            "rust-analyzer" => bins.push("bin/rust-analyzer"),
+            _ => {}
+        }
+    }
+    bins.into_iter().map(String::from).collect()
+}
+
+/// rustup names installed toolchains with the host triple (`1.78-aarch64-apple-darwin`).
+fn installed_toolchain_dir(toolchain: &str) -> Result<PathBuf> {
+    let toolchains = rustup_home().join("toolchains");
+    let exact = toolchains.join(toolchain);
+    if exact.is_dir() {
+        return Ok(exact);
+    }
+
+    let prefix = format!("{}-", toolchain);
+    for entry in std::fs::read_dir(&toolchains)
+        .with_context(|| format!("Failed to read {}", toolchains.display()))?
+    {
+        let entry = entry?;
+        if entry.file_name().to_string_lossy().starts_with(&prefix) {
+            return Ok(entry.path());
+        }
+    }
+    anyhow::bail!(
+        "Toolchain {} not found in {}",
+        toolchain,
+        toolchains.display()
+    )
+}
+
+fn link_toolchain(toolchain_dir: &Path, dest: &Path) -> Result<()> {
+    let bin = dest.join("bin");
+    std::fs::create_dir_all(&bin)?;
+    for entry in std::fs::read_dir(toolchain_dir.join("bin"))? {
+        let entry = entry?;
+        std::os::unix::fs::symlink(entry.path(), bin.join(entry.file_name()))
+            .with_context(|| format!("Failed to link {}", entry.path().display()))?;
+    }
+    Ok(())
+}
+
+/// The nearest `rust-toolchain.toml` or `rust-toolchain` at or above `start`.
+pub fn find_toolchain_file(start: &Path) -> Result<Option<(PathBuf, ToolOptions)>> {
+    for dir in start.ancestors() {
+        for name in TOOLCHAIN_FILES {
+            let path = dir.join(name);
+            if !path.is_file() {
+                continue;
+            }
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let options = if *name == "rust-toolchain.toml" || content.contains("[toolchain]") {
+                parse_toolchain_toml(&content)?
+            } else {
+                parse_toolchain_channel(&content)
+            };
+            if let Some(options) = options {
+                return Ok(Some((path, options)));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// `[toolchain]` with `channel`, `components` and `targets`.
+pub fn parse_toolchain_toml(content: &str) -> Result<Option<ToolOptions>> {
+    let value: toml::Value =
+        toml::from_str(content).context("Failed to parse rust-toolchain.toml")?;
+    let Some(toolchain) = value.get("toolchain") else {
+        return Ok(None);
+    };
+
+    let strings = |key: &str| -> Vec<String> {
+        toolchain
+            .get(key)
+            .and_then(|v| v.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|v| v.as_str().map(str::to_string))
+            .collect()
+    };
+
+    let channel = toolchain
+        .get("channel")
+        .and_then(|c| c.as_str())
+        .unwrap_or("stable")
+        .to_string();
+    Ok(Some(ToolOptions {
+        version: channel,
+        backend: None,
+        components: strings("components"),
+        targets: strings("targets"),
+        ..Default::default()
+    }))
+}
+
+/// The legacy `rust-toolchain` file: just a channel name.
+fn parse_toolchain_channel(content: &str) -> Option<ToolOptions> {
+    let channel = content
+        .lines()
+        .map(str::trim)
+        .find(|l| !l.is_empty() && !l.starts_with('#'))?;
+    Some(ToolOptions {
+        version: channel.to_string(),
+        ..Default::default()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch::Scratch;
+
+    fn options(version: &str, components: &[&str], targets: &[&str]) -> ToolOptions {
+        ToolOptions {
+            version: version.to_string(),
+            backend: None,
+            components: components.iter().map(|c| c.to_string()).collect(),
+            targets: targets.iter().map(|t| t.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn toolchain_file_fills_in_unpinned_versions() {
+        let registry = RustRegistry::merge(
+            DEFAULT_DIST_SERVER.to_string(),
+            Some(&options("1.80", &["rustfmt", "clippy"], &[])),
+            Some(&options(
+                "1.78",
+                &["clippy", "rust-src"],
+                &["wasm32-unknown-unknown"],
+            )),
+        );
+
+        assert_eq!(registry.toolchain(&"rust".parse().unwrap()), "1.78");
+        assert_eq!(registry.toolchain(&"rust@1.80".parse().unwrap()), "1.80");
+        assert_eq!(registry.components, ["rustfmt", "clippy", "rust-src"]);
+        assert_eq!(registry.targets, ["wasm32-unknown-unknown"]);
+
+        let bare = RustRegistry::merge(DEFAULT_DIST_SERVER.to_string(), None, None);
+        assert_eq!(bare.toolchain(&"rust".parse().unwrap()), "stable");
+        assert_eq!(
+            bare.toolchain(&"rustup:rust@nightly".parse().unwrap()),
+            "nightly"
+        );
+    }
+
+    #[test]
+    fn toolchain_file_does_not_replace_a_pinned_latest() {
+        use crate::specs::config::ConfigSources;
+
+        let root = Scratch::new("rust-pinned");
+        std::fs::write(
+            root.join("rust-toolchain.toml"),
+            "[toolchain]\nchannel = \"1.78\"\ncomponents = [\"clippy\"]\n",
+        )
+        .unwrap();
+        let registry = |manifest: &str| {
+            std::fs::write(root.join("still.toml"), manifest).unwrap();
+            let config = LayeredConfig::load_from(&ConfigSources {
+                global_file: None,
+                cwd: root.to_path_buf(),
+                env: Vec::new(),
+            })
+            .unwrap();
+            RustRegistry::from_config(&config).unwrap()
+        };
+        let rust: ToolSpec = "rust".parse().unwrap();
+
+        assert_eq!(registry("[tools]\n").toolchain(&rust), "1.78");
+        let pinned = registry("[tools]\nrust = \"latest\"\n");
+        assert_eq!(pinned.toolchain(&rust), "stable");
+        assert_eq!(pinned.components, ["clippy"]);
+    }
+
+    #[test]
+    fn installs_components_and_targets() {
+        let components = vec!["clippy".to_string()];
+        let targets = vec!["wasm32-unknown-unknown".to_string()];
+        assert_eq!(
+            toolchain_install_args("1.78", &components, &targets).join(" "),
+            "toolchain install 1.78 --profile minimal --no-self-update --component clippy --target wasm32-unknown-unknown"
+        );
+        assert!(toolchain_bins(&components).contains(&"bin/cargo-clippy".to_string()));
+    }
+
+    #[test]
+    fn removes_components_and_targets_that_are_no_longer_listed() {
+        let host = "x86_64-unknown-linux-gnu";
+        let components = "cargo-x86_64-unknown-linux-gnu\nclippy-x86_64-unknown-linux-gnu\nrust-src\nrust-std-wasm32-unknown-unknown\nrust-std-x86_64-unknown-linux-gnu\nrustc-x86_64-unknown-linux-gnu\nrustfmt-x86_64-unknown-linux-gnu\n";
+        assert_eq!(
+            stale_components(components, host, &["clippy".to_string()]),
+            ["rust-src", "rustfmt"]
+        );
+        assert!(
+            stale_components(
+                components,
+                host,
+                &["clippy".into(), "rustfmt".into(), "rust-src".into()]
+            )
+            .is_empty()
+        );
+
+        let targets = "wasm32-unknown-unknown\nx86_64-unknown-linux-gnu\naarch64-apple-darwin\n";
+        assert_eq!(
+            stale_targets(targets, host, &["aarch64-apple-darwin".to_string()]),
+            ["wasm32-unknown-unknown"]
+        );
+    }
+
+    #[test]
+    fn finds_toolchain_files_in_parents() {
+        let root = Scratch::new("rust");
+        let nested = root.join("crates").join("core");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(root.join("rust-toolchain"), "nightly-2024-06-01\n").unwrap();
+
+        let (path, found) = find_toolchain_file(&nested).unwrap().unwrap();
+        assert_eq!(path, root.join("rust-toolchain"));
+        assert_eq!(found.version, "nightly-2024-06-01");
+
+        std::fs::write(
+            nested.join("rust-toolchain.toml"),
+            "[toolchain]\nchannel = \"1.78\"\ntargets = [\"wasm32-unknown-unknown\"]\n",
+        )
+        .unwrap();
+        let (_, found) = find_toolchain_file(&nested).unwrap().unwrap();
+        assert_eq!(found.version, "1.78");
+        assert_eq!(found.targets, ["wasm32-unknown-unknown"]);
+    }
+}
@@ -0,0 +1,282 @@
+use crate::registries::cargo::PkgFmt;
+use crate::registries::{Release, fetch_bytes};
+use crate::specs::config::LayeredConfig;
+use crate::specs::tool::ToolSpec;
+use crate::utils::archive::{ArchiveExtractor, ArchiveFormat};
+use crate::utils::hashing::Hashing;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// Tools declared entirely in `still.toml`, with no registry behind them:
+///
+/// ```toml
+/// [tools."url:deploy-cli"]
+/// version = "2.4.1"
+/// url = "https://downloads.example.com/deploy-cli/{version}/deploy-cli-{os}-{arch}.tar.gz"
+/// sha256 = { macos-aarch64 = "…", linux-x86_64 = "…" }
+/// bins = ["deploy-cli"]
+/// ```
+///
+/// `{os}` is `macos` or `linux` and `{arch}` is `aarch64` or `x86_64`. The sha256
+/// for this platform is required, and it only vouches for the table's `version`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlRegistry {
+    pub url: String,
+    pub sha256: BTreeMap<String, String>,
+    pub format: Option<PkgFmt>,
+    pub bins: Vec<String>,
+    /// The `version` from `still.toml`, used when none is requested.
+    pub version: Option<String>,
+}
+
+impl UrlRegistry {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            sha256: BTreeMap::new(),
+            format: None,
+            bins: Vec::new(),
+            version: None,
+        }
+    }
+
+    pub fn from_config(config: &LayeredConfig, tool: &ToolSpec) -> Result<Self> {
+        let options = config.tool_options(tool)?.ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} has no [tools.\"{}\"] table in still.toml to download it from",
+                tool.name,
+                tool.key()
+            )
+        })?;
+        let url = options
+            .url
+            .ok_or_else(|| anyhow::anyhow!("[tools.\"{}\"] has no url", tool.key()))?;
+
+        let mut registry = Self::new(url);
+        registry.format = options
+            .format
+            .map(|format| {
+                PkgFmt::parse(&format).ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "Unknown format \"{}\" for {} (expected tar.gz, zip or bin)",
+                        format,
+                        tool.key()
+                    )
+                })
+            })
+            .transpose()?;
+        registry.sha256 = options.sha256;
+        registry.bins = options.bins;
+        registry.version = Some(options.version).filter(|v| !v.is_empty());
+        Ok(registry)
+    }
+
+    pub async fn resolve(&self, tool: &ToolSpec) -> Result<Release> {
+        let version = if tool.is_latest() {
+            self.version.clone().filter(|v| v != "latest")
+        } else {
+            Some(tool.version.clone())
+        }
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} has no version listing; pin a version for it in still.toml",
+                tool.key()
+            )
+        })?;
+
+        let (os, arch) = (std::env::consts::OS, std::env::consts::ARCH);
+        let platform = format!("{}-{}", os, arch);
+        if let Some(pinned) = self.version.as_ref().filter(|pinned| **pinned != version) {
+            anyhow::bail!(
+                "The sha256 of [tools.\"{}\"] is for version {}, not {}; update both in still.toml",
+                tool.key(),
+                pinned,
+                version
+            );
+        }
+        let sha256 = self
+            .sha256
+            .get(&platform)
+            .map(|sha256| sha256.trim().to_ascii_lowercase())
+            .filter(|sha256| !sha256.is_empty())
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "[tools.\"{}\"] has no sha256 for {}; add it to still.toml",
+                    tool.key(),
+                    platform
+                )
+            })?;
+
+        let bins = if self.bins.is_empty() {
+            vec![format!("bin/{}", tool.short_name())]
+        } else {
+            self.bins
+                .iter()
+                .filter_map(|bin| Path::new(bin).file_name())
+                .map(|name| format!("bin/{}", name.to_string_lossy()))
+                .collect()
+        };
+
+        Ok(Release {
+            name: tool.name.clone(),
+            url: render(&self.url, &version, os, arch),
+            version,
+            sha256: Some(sha256),
+            integrity: None,
+            size: None,
+            bins,
+            from_source: false,
+        })
+    }
+
+    /// Download and verify `release`, unpack it into `dest` and link the declared
+    /// files into `dest/bin`. A bare executable goes straight to `dest/bin`.
+    pub async fn install(&self, release: &Release, dest: &Path) -> Result<()> {
+        println!("Downloading {} {}...", release.name, release.version);
+        let sha256 = release.sha256.as_deref().ok_or_else(|| {
+            anyhow::anyhow!(
+                "{} {} has no sha256 to verify",
+                release.name,
+                release.version
+            )
+        })?;
+        let data = fetch_bytes(&release.url).await?;
+        Hashing::verify_sha256(&data, sha256)
+            .map_err(|e| anyhow::anyhow!("Checksum verification failed: {e}"))?;
+        println!("Checksum verified for {}", release.name);
+
+        let format = self
+            .format
+            .or_else(|| match ArchiveFormat::from_name(&release.url) {
+                Some(ArchiveFormat::TarGz) => Some(PkgFmt::Tgz),
+                Some(ArchiveFormat::Zip) => Some(PkgFmt::Zip),
+                None => None,
+            })
+            .unwrap_or(match ArchiveFormat::detect(&data) {
+                Some(ArchiveFormat::TarGz) => PkgFmt::Tgz,
+                Some(ArchiveFormat::Zip) => PkgFmt::Zip,
+                None => PkgFmt::Bin,
+            });
+
+        let bin_dir = dest.join("bin");
+        tokio::fs::create_dir_all(&bin_dir).await?;
+        let extracted = match format {
+            PkgFmt::Bin => {
+                let bin = release.bins.first().map_or("bin", String::as_str);
+                let path = dest.join(bin);
+                tokio::fs::write(&path, &data).await?;
+                tokio::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).await?;
+                return Ok(());
+            }
+            PkgFmt::Tgz => ArchiveExtractor::extract_tar_gz(&data, dest).await,
+            PkgFmt::Zip => ArchiveExtractor::extract_zip(&data, dest).await,
+        };
+        extracted.map_err(|e| anyhow::anyhow!("Failed to extract {}: {e}", release.url))?;
+
+        let files = if self.bins.is_empty() {
+            release.bins.clone()
+        } else {
+            self.bins
+                .iter()
+                .map(|bin| {
+                    render(
+                        bin,
+                        &release.version,
+                        std::env::consts::OS,
+                        std::env::consts::ARCH,
+                    )
+                })
+                .collect()
+        };
+        for file in files {
+            let path = dest.join(&file);
+            if !path.is_file() {
+                anyhow::bail!("{} does not contain {}", release.url, file);
+            }
+            tokio::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755)).await?;
+
+            let link = bin_dir.join(path.file_name().unwrap_or_default());
+            if link != path && !link.exists() {
+                std::os::unix::fs::symlink(Path::new("..").join(&file), &link)
+                    .with_context(|| format!("Failed to link {}", link.display()))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fill in `{version}`, `{os}` and `{arch}` in a URL or path template.
+pub fn render(template: &str, version: &str, os: &str, arch: &str) -> String {
+    template
+        .replace("{version}", version)
+        .replace("{os}", os)
+        .replace("{arch}", arch)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::net::fixture;
+    use crate::utils::scratch::Scratch;
+
+    #[test]
+    fn installs_a_declared_archive() {
+        let archive = fixture::tar_gz(
+            "deploy-cli-2.4.1",
+            &[("libexec/deploy-cli", b"#!/bin/sh\necho deployed\n")],
+        );
+        let base = fixture::serve(vec![(
+            format!(
+                "/deploy-cli-2.4.1-{}-{}.tar.gz",
+                std::env::consts::OS,
+                std::env::consts::ARCH
+            ),
+            archive.clone(),
+        )]);
+
+        let mut registry = UrlRegistry::new(format!(
+            "{}/deploy-cli-{{version}}-{{os}}-{{arch}}.tar.gz",
+            base
+        ));
+        registry.version = Some("2.4.1".into());
+        registry.bins = vec!["libexec/deploy-cli".into()];
+        registry.sha256.insert(
+            format!("{}-{}", std::env::consts::OS, std::env::consts::ARCH),
+            Hashing::sha256(&archive),
+        );
+        registry.sha256.insert("plan9-mips".into(), "0".repeat(64));
+
+        let root = Scratch::new("url");
+        let dest = root.join("deploy-cli/2.4.1");
+        std::fs::create_dir_all(&dest).unwrap();
+        fixture::block_on(async {
+            let release = registry
+                .resolve(&"url:deploy-cli".parse().unwrap())
+                .await
+                .unwrap();
+            assert_eq!(release.version, "2.4.1");
+            assert_eq!(release.bins, vec!["bin/deploy-cli".to_string()]);
+            registry.install(&release, &dest).await.unwrap();
+
+            // The checksums only cover the declared version and platforms.
+            let err = registry
+                .resolve(&"url:deploy-cli@2.5.0".parse().unwrap())
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("for version 2.4.1"), "{err}");
+            registry.sha256.clear();
+            let err = registry
+                .resolve(&"url:deploy-cli".parse().unwrap())
+                .await
+                .unwrap_err();
+            assert!(err.to_string().contains("no sha256"), "{err}");
+        });
+
+        let output = std::process::Command::new(dest.join("bin/deploy-cli"))
+            .output()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "deployed\n");
+    }
+}
@@ -0,0 +1,500 @@
+use crate::specs::toml::{LOCAL_MANIFEST_NAME, MANIFEST_NAME, ToolOptions, find_project_root};
+use crate::specs::tool::ToolSpec;
+use crate::system::System;
+use crate::utils::paths::PathOps;
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+/// Prefix for environment overrides (`STILL_JOBS=4`, `STILL_NODE_VERSION=20`).
+pub const ENV_PREFIX: &str = "STILL_";
+
+/// Where a configuration value came from, lowest precedence first.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Origin {
+    Default,
+    Global(PathBuf),
+    Project(PathBuf),
+    Local(PathBuf),
+    Env(String),
+    /// A command-line override such as `still run --tool node@18`.
+    Override(String),
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::Default => write!(f, "default"),
+            Origin::Global(path) | Origin::Project(path) | Origin::Local(path) => {
+                write!(f, "{}", path.display())
+            }
+            Origin::Env(var) => write!(f, "${}", var),
+            Origin::Override(arg) => write!(f, "{}", arg),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ConfigValue {
+    pub value: toml::Value,
+    pub origin: Origin,
+}
+
+impl fmt::Display for ConfigValue {
+    /// Strings are shown bare, everything else as inline TOML.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            toml::Value::String(s) => write!(f, "{}", s),
+            other => write!(f, "{}", other),
+        }
+    }
+}
+
+/// Which file `still config set` writes to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConfigScope {
+    Global,
+    Project,
+    Local,
+}
+
+/// Inputs for [`LayeredConfig::load_from`]; [`LayeredConfig::load`] fills these from the system.
+#[derive(Debug, Clone)]
+pub struct ConfigSources {
+    pub global_file: Option<PathBuf>,
+    pub cwd: PathBuf,
+    pub env: Vec<(String, String)>,
+}
+
+/// Configuration merged from, in order of increasing precedence: built-in defaults,
+/// the global config, the nearest `still.toml`, `still.local.toml` next to it,
+/// and `STILL_*` environment variables.
+///
+/// Keys are dotted paths. Everything under `[settings]` is flattened to its leaves
+/// (`settings.jobs`); each `[tools]` entry is a single value (`tools.node`,
+/// `tools.rust`) so a layer replaces a tool pin as a whole.
+#[derive(Debug, Clone, Default)]
+pub struct LayeredConfig {
+    values: BTreeMap<String, ConfigValue>,
+    pub project_root: Option<PathBuf>,
+    pub layers: Vec<Origin>,
+}
+
+impl LayeredConfig {
+    pub fn load() -> Result<Self> {
+        let cwd = std::env::current_dir().context("Failed to read current directory")?;
+        Self::load_from(&ConfigSources {
+            global_file: Some(System::config_file()),
+            cwd,
+            env: std::env::vars().collect(),
+        })
+    }
+
+    pub fn load_from(sources: &ConfigSources) -> Result<Self> {
+        let mut config = Self::default();
+        config.apply_table(&defaults(), &Origin::Default);
+
+        if let Some(global) = sources.global_file.as_deref().filter(|p| p.is_file()) {
+            config.apply_file(global, Origin::Global(global.to_path_buf()))?;
+        }
+
+        config.project_root = find_project_root(&sources.cwd);
+        if let Some(root) = config.project_root.clone() {
+            let manifest = root.join(MANIFEST_NAME);
+            config.apply_file(&manifest, Origin::Project(manifest.clone()))?;
+
+            let local = root.join(LOCAL_MANIFEST_NAME);
+            if local.is_file() {
+                config.apply_file(&local, Origin::Local(local.clone()))?;
+            }
+        }
+
+        let mut env: Vec<&(String, String)> = sources.env.iter().collect();
+        env.sort();
+        for (var, raw) in env {
+            match env_key(var) {
+                Some(key) if key.starts_with("tools.") => {
+                    config.set_version(key, raw, Origin::Env(var.clone()));
+                }
+                Some(key) => {
+                    let value = parse_value(&key, raw);
+                    config.set_value(key, value, Origin::Env(var.clone()));
+                }
+                None => {}
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&ConfigValue> {
+        self.values.get(key)
+    }
+
+    pub fn get_str(&self, key: &str) -> Option<&str> {
+        self.get(key).and_then(|v| v.value.as_str())
+    }
+
+    pub fn get_bool(&self, key: &str) -> Option<bool> {
+        self.get(key).and_then(|v| v.value.as_bool())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &ConfigValue)> {
+        self.values.iter()
+    }
+
+    /// Every pinned tool and its requested version: `tools.node = "20"` and
+    /// `[tools.rust] version = "1.78"` alike.
+    pub fn tool_versions(&self) -> BTreeMap<String, ConfigValue> {
+        self.values
+            .iter()
+            .filter_map(|(key, value)| {
+                let name = key.strip_prefix("tools.")?;
+                let version = match &value.value {
+                    toml::Value::Table(table) => table.get("version")?.clone(),
+                    other => other.clone(),
+                };
+                Some((
+                    name.to_string(),
+                    ConfigValue {
+                        value: version,
+                        origin: value.origin.clone(),
+                    },
+                ))
+            })
+            .collect()
+    }
+
+    /// The `[tools.<name>]` table for `tool`, looked up by its lockfile key
+    /// (`tools."github:owner/repo"`) and then by its bare name.
+    pub fn tool_options(&self, tool: &ToolSpec) -> Result<Option<ToolOptions>> {
+        for key in [tool.key(), tool.name.clone()] {
+            let key = format!("tools.{}", key);
+            if let Some(toml::Value::Table(table)) = self.get(&key).map(|v| &v.value) {
+                let options = serde::Deserialize::deserialize(table.clone())
+                    .with_context(|| format!("Invalid [{}] options", key))?;
+                return Ok(Some(options));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Pin `tool` above every layer for this process only. A `[tools.<name>]`
+    /// table keeps its options and gets the new version.
+    pub fn override_tool(&mut self, tool: &ToolSpec) {
+        let key = format!("tools.{}", tool.key());
+        self.set_version(
+            key,
+            &tool.version,
+            Origin::Override(format!("--tool {}", tool)),
+        );
+    }
+
+    /// Set the tool `key` to `version`, or its `version` when it is a table.
+    fn set_version(&mut self, key: String, version: &str, origin: Origin) {
+        let version = toml::Value::String(version.to_string());
+        let value = match self.get(&key).map(|v| v.value.clone()) {
+            Some(toml::Value::Table(mut table)) => {
+                table.insert("version".to_string(), version);
+                toml::Value::Table(table)
+            }
+            _ => version,
+        };
+        self.set_value(key, value, origin);
+    }
+
+    /// Path of the file `still config set` writes for `scope`.
+    pub fn scope_path(&self, scope: ConfigScope) -> Result<PathBuf> {
+        match scope {
+            ConfigScope::Global => Ok(System::config_file()),
+            ConfigScope::Project | ConfigScope::Local => {
+                let root = self.project_root.as_ref().ok_or_else(|| {
+                    anyhow::anyhow!(
+                        "No {} found in this directory or its parents",
+                        MANIFEST_NAME
+                    )
+                })?;
+                Ok(root.join(if scope == ConfigScope::Project {
+                    MANIFEST_NAME
+                } else {
+                    LOCAL_MANIFEST_NAME
+                }))
+            }
+        }
+    }
+
+    fn apply_file(&mut self, path: &Path, origin: Origin) -> Result<()> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let table: toml::Table = toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        self.apply_table(&table, &origin);
+        self.layers.push(origin);
+        Ok(())
+    }
+
+    fn apply_table(&mut self, table: &toml::Table, origin: &Origin) {
+        if let Some(toml::Value::Table(settings)) = table.get("settings") {
+            let mut leaves = Vec::new();
+            flatten("settings", settings, &mut leaves);
+            for (key, value) in leaves {
+                self.set_value(key, value, origin.clone());
+            }
+        }
+
+        if let Some(toml::Value::Table(tools)) = table.get("tools") {
+            for (name, value) in tools {
+                self.set_value(format!("tools.{}", name), value.clone(), origin.clone());
+            }
+        }
+    }
+
+    fn set_value(&mut self, key: String, value: toml::Value, origin: Origin) {
+        self.values.insert(key, ConfigValue { value, origin });
+    }
+}
+
+/// Every key under `[settings]` that Still reads.
+pub const SETTINGS: &[&str] = &[
+    "settings.auto_install",
+    "settings.jobs",
+    "settings.asdf.index_url",
+    "settings.cargo.api_url",
+    "settings.cargo.locked",
+    "settings.github.api_url",
+    "settings.go.dl_url",
+    "settings.node.dist_url",
+    "settings.npm.registry",
+    "settings.python.manifest_url",
+    "settings.rust.dist_server",
+];
+
+/// Built-in defaults, shown with origin `default`.
+fn defaults() -> toml::Table {
+    toml::toml! {
+        [settings]
+        auto_install = false
+        jobs = 8
+    }
+}
+
+fn flatten(prefix: &str, table: &toml::Table, out: &mut Vec<(String, toml::Value)>) {
+    for (key, value) in table {
+        let path = format!("{}.{}", prefix, key);
+        match value {
+            toml::Value::Table(inner) => flatten(&path, inner, out),
+            other => out.push((path, other.clone())),
+        }
+    }
+}
+
+/// `STILL_NODE_VERSION` -> `tools.node`, `STILL_AUTO_INSTALL` -> `settings.auto_install`,
+/// `STILL_NODE__DIST_URL` -> `settings.node.dist_url`. Other `STILL_*` variables, such as
+/// the ones Still sets for the processes it runs, are not configuration.
+fn env_key(var: &str) -> Option<String> {
+    let rest = var.strip_prefix(ENV_PREFIX)?.to_ascii_lowercase();
+    if rest.is_empty() {
+        return None;
+    }
+
+    if let Some(tool) = rest.strip_suffix("_version").filter(|t| !t.is_empty()) {
+        return Some(format!("tools.{}", tool.replace('_', "-")));
+    }
+    let key = format!("settings.{}", rest.replace("__", "."));
+    SETTINGS.contains(&key.as_str()).then_some(key)
+}
+
+/// Parse a value from the command line or environment as TOML (`true`, `4`, `["a"]`),
+/// falling back to a plain string. Tool versions are always strings (`tools.node = "20"`).
+pub fn parse_value(key: &str, raw: &str) -> toml::Value {
+    if key.starts_with("tools.") {
+        return toml::Value::String(raw.to_string());
+    }
+    toml::from_str::<toml::Table>(&format!("v = {}", raw))
+        .ok()
+        .and_then(|mut t| t.remove("v"))
+        .unwrap_or_else(|| toml::Value::String(raw.to_string()))
+}
+
+/// Set `key` in the TOML file at `path`, preserving its formatting and comments.
+pub fn set_in_file(path: &Path, key: &str, value: toml::Value) -> Result<()> {
+    let content = if path.exists() {
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+    } else {
+        String::new()
+    };
+    let mut doc: toml_edit::DocumentMut = content
+        .parse()
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    let parts: Vec<&str> = key.split('.').collect();
+    let (last, parents) = parts
+        .split_last()
+        .ok_or_else(|| anyhow::anyhow!("Config key cannot be empty"))?;
+
+    let mut table = doc.as_table_mut();
+    for part in parents {
+        let entry = table
+            .entry(part)
+            .or_insert_with(|| toml_edit::Item::Table(toml_edit::Table::new()));
+        table = entry
+            .as_table_mut()
+            .ok_or_else(|| anyhow::anyhow!("'{}' in '{}' is not a table", part, key))?;
+    }
+    table.insert(last, toml_edit::value(to_edit_value(value)?));
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, doc.to_string())
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Remove `key` from the TOML file at `path`, preserving everything else.
+/// Returns whether the key was there.
+pub fn remove_in_file(path: &Path, key: &str) -> Result<bool> {
+    if !path.exists() {
+        return Ok(false);
+    }
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    let mut doc: toml_edit::DocumentMut = content
+        .parse()
+        .with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    let parts: Vec<&str> = key.split('.').collect();
+    let (last, parents) = parts
+        .split_last()
+        .ok_or_else(|| anyhow::anyhow!("Config key cannot be empty"))?;
+
+    let mut table: Option<&mut dyn toml_edit::TableLike> = Some(doc.as_table_mut());
+    for part in parents {
+        table = table
+            .and_then(|t| t.get_mut(part))
+            .and_then(|item| item.as_table_like_mut());
+    }
+    if table.and_then(|t| t.remove(last)).is_none() {
+        return Ok(false);
+    }
+
+    std::fs::write(path, doc.to_string())
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+    Ok(true)
+}
+
+fn to_edit_value(value: toml::Value) -> Result<toml_edit::Value> {
+    let mut table = toml::Table::new();
+    table.insert("v".to_string(), value);
+    let rendered = toml::to_string(&table)?;
+    let doc: toml_edit::DocumentMut = rendered.parse()?;
+    doc.get("v")
+        .and_then(|item| item.as_value())
+        .cloned()
+        .ok_or_else(|| anyhow::anyhow!("Unsupported config value"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch::Scratch;
+
+    #[test]
+    fn layers_override_in_order() {
+        let root = Scratch::new("config-layers");
+        let global = root.join("config.toml");
+        std::fs::write(
+            &global,
+            "[settings]\njobs = 2\nauto_install = true\n[tools]\nnode = \"18\"\npython = \"3.11\"\n",
+        )
+        .unwrap();
+
+        let project = root.join("project");
+        let nested = project.join("src").join("deep");
+        std::fs::create_dir_all(&nested).unwrap();
+        std::fs::write(
+            project.join(MANIFEST_NAME),
+            "[tools]\nnode = \"20\"\ngo = \"1.22\"\n[tools.rust]\nversion = \"1.78\"\ncomponents = [\"clippy\"]\n[tasks]\nfmt = \"cargo fmt\"\n",
+        )
+        .unwrap();
+        std::fs::write(
+            project.join(LOCAL_MANIFEST_NAME),
+            "[tools]\ngo = \"1.23\"\n",
+        )
+        .unwrap();
+
+        let config = LayeredConfig::load_from(&ConfigSources {
+            global_file: Some(global.clone()),
+            cwd: nested,
+            env: vec![
+                ("STILL_PYTHON_VERSION".into(), "3.13".into()),
+                ("STILL_RUST_VERSION".into(), "1.80".into()),
+                ("STILL_JOBS".into(), "16".into()),
+                ("HOME".into(), "/home/x".into()),
+            ],
+        })
+        .unwrap();
+
+        assert_eq!(config.project_root.as_deref(), Some(project.as_path()));
+
+        let jobs = config.get("settings.jobs").unwrap();
+        assert_eq!(jobs.value, toml::Value::Integer(16));
+        assert_eq!(jobs.origin, Origin::Env("STILL_JOBS".into()));
+
+        assert_eq!(config.get_bool("settings.auto_install"), Some(true));
+        assert_eq!(
+            config.get("settings.auto_install").unwrap().origin,
+            Origin::Global(global)
+        );
+        assert_eq!(config.get_str("tools.node"), Some("20"));
+        assert_eq!(config.get_str("tools.go"), Some("1.23"));
+        assert!(matches!(
+            config.get("tools.go").unwrap().origin,
+            Origin::Local(_)
+        ));
+        assert_eq!(config.get_str("tools.python"), Some("3.13"));
+        // A `[tools.rust]` table keeps its options under an overridden version.
+        let rust = &config.get("tools.rust").unwrap().value;
+        assert_eq!(rust.get("version").and_then(|v| v.as_str()), Some("1.80"));
+        assert_eq!(rust.get("components").unwrap().as_array().unwrap().len(), 1);
+        assert!(config.get("tasks.fmt").is_none());
+    }
+
+    #[test]
+    fn env_keys() {
+        assert_eq!(env_key("STILL_NODE_VERSION").as_deref(), Some("tools.node"));
+        assert_eq!(
+            env_key("STILL_AUTO_INSTALL").as_deref(),
+            Some("settings.auto_install")
+        );
+        assert_eq!(
+            env_key("STILL_NODE__DIST_URL").as_deref(),
+            Some("settings.node.dist_url")
+        );
+        assert_eq!(env_key("PATH"), None);
+        assert_eq!(env_key("STILL_SERVICE_DATA"), None);
+        assert_eq!(env_key("STILL_"), None);
+    }
+
+    #[test]
+    fn set_preserves_existing_content() {
+        let root = Scratch::new("config-set");
+        let path = root.join("config.toml");
+        std::fs::write(&path, "# my settings\n[settings]\njobs = 2 # keep\n").unwrap();
+
+        set_in_file(
+            &path,
+            "settings.auto_install",
+            parse_value("settings.auto_install", "true"),
+        )
+        .unwrap();
+        set_in_file(&path, "tools.node", parse_value("tools.node", "20")).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.contains("# my settings"), "{content}");
+        assert!(content.contains("jobs = 2 # keep"), "{content}");
+        assert!(content.contains("auto_install = true"), "{content}");
+        assert!(content.contains("node = \"20\""), "{content}");
+    }
+}
@@ -0,0 +1,114 @@
+use crate::specs::toml::find_project_root;
+use crate::specs::tool::ToolSpec;
+use crate::utils::transaction::FsTransaction;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub const LOCKFILE_NAME: &str = "still.lock";
+
+/// `still.lock`: the exact versions a project resolved to.
+/// Tools are keyed by [`ToolSpec::key`] (`ripgrep`, `npm:prettier`, `github:owner/repo`).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Lockfile {
+    #[serde(default)]
+    pub tools: BTreeMap<String, LockedTool>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockedTool {
+    pub version: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+
+    /// Subresource Integrity string, for packages whose registry publishes one (npm).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<String>,
+}
+
+impl Lockfile {
+    /// Load a lockfile, treating a missing file as empty.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read lockfile at {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse lockfile at {}", path.display()))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string_pretty(self).context("Failed to serialize lockfile")?;
+        std::fs::write(path, content)
+            .with_context(|| format!("Failed to write lockfile at {}", path.display()))
+    }
+
+    /// Write the lockfile to `path` when `transaction` commits.
+    pub fn stage(&self, transaction: &mut FsTransaction, path: &Path) -> Result<()> {
+        let content = toml::to_string_pretty(self).context("Failed to serialize lockfile")?;
+        transaction.stage_write(path, content)
+    }
+
+    pub fn get(&self, tool: &ToolSpec) -> Option<&LockedTool> {
+        self.tools.get(&tool.key())
+    }
+
+    pub fn insert(&mut self, tool: &ToolSpec, locked: LockedTool) {
+        self.tools.insert(tool.key(), locked);
+    }
+
+    pub fn remove(&mut self, tool: &ToolSpec) -> Option<LockedTool> {
+        self.tools.remove(&tool.key())
+    }
+}
+
+/// The lockfile next to the nearest `still.toml`, if there is one.
+pub fn project_lockfile_path() -> Option<PathBuf> {
+    let cwd = std::env::current_dir().ok()?;
+    find_project_root(&cwd).map(|root| root.join(LOCKFILE_NAME))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keyed_by_backend_and_name() {
+        let mut lock = Lockfile::default();
+        let prettier: ToolSpec = "npm:prettier@3".parse().unwrap();
+        let rg: ToolSpec = "ripgrep".parse().unwrap();
+
+        lock.insert(
+            &prettier,
+            LockedTool {
+                version: "3.3.3".into(),
+                url: None,
+                sha256: None,
+                integrity: Some("sha512-abc".into()),
+            },
+        );
+        lock.insert(
+            &rg,
+            LockedTool {
+                version: "14.1.1".into(),
+                url: None,
+                sha256: Some("abc".into()),
+                integrity: None,
+            },
+        );
+
+        let text = toml::to_string_pretty(&lock).unwrap();
+        assert!(text.contains("[tools.\"npm:prettier\"]"), "{text}");
+
+        let back: Lockfile = toml::from_str(&text).unwrap();
+        assert_eq!(back, lock);
+        assert_eq!(back.get(&prettier).unwrap().version, "3.3.3");
+    }
+}
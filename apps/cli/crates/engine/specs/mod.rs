@@ -1,3 +1,6 @@
 pub mod brew;
+pub mod config;
+pub mod lock;
+pub mod receipt;
 pub mod toml;
 pub mod tool;
@@ -0,0 +1,80 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// File name of the receipt inside every install path.
+pub const RECEIPT_NAME: &str = ".still-receipt.toml";
+
+/// What an install under `tool_dir/<name>/<version>` came from, written next to
+/// the installed files so it moves and disappears with them.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Receipt {
+    /// [`ToolSpec::key`](crate::specs::tool::ToolSpec::key) of the request, or the
+    /// formula name for dependencies.
+    pub tool: String,
+    pub backend: String,
+    pub version: String,
+
+    /// The version that was asked for (`20`, `lts`); absent for dependencies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requested: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sha256: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub integrity: Option<String>,
+
+    /// Seconds since the Unix epoch.
+    pub installed_at: u64,
+}
+
+impl Receipt {
+    /// Read the receipt of the install at `install_path`, if it has one.
+    pub fn load(install_path: &Path) -> Result<Option<Self>> {
+        let path = install_path.join(RECEIPT_NAME);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read receipt at {}", path.display()))?;
+        toml::from_str(&content)
+            .map(Some)
+            .with_context(|| format!("Failed to parse receipt at {}", path.display()))
+    }
+
+    pub fn save(&self, install_path: &Path) -> Result<()> {
+        let path = install_path.join(RECEIPT_NAME);
+        let content = toml::to_string_pretty(self).context("Failed to serialize receipt")?;
+        std::fs::write(&path, content)
+            .with_context(|| format!("Failed to write receipt at {}", path.display()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch::Scratch;
+
+    #[test]
+    fn round_trips_next_to_the_install() {
+        let dir = Scratch::new("receipt");
+        assert_eq!(Receipt::load(&dir).unwrap(), None);
+
+        let receipt = Receipt {
+            tool: "asdf:terraform".into(),
+            backend: "asdf".into(),
+            version: "1.9.8".into(),
+            requested: Some("1.9".into()),
+            url: Some("https://github.com/asdf-community/asdf-hashicorp.git".into()),
+            installed_at: 1_700_000_000,
+            ..Default::default()
+        };
+        receipt.save(&dir).unwrap();
+        assert_eq!(Receipt::load(&dir).unwrap(), Some(receipt));
+    }
+}
@@ -0,0 +1,400 @@
+use crate::utils::hashing::Hashing;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_NAME: &str = "still.toml";
+
+/// Untracked per-checkout overrides, next to `still.toml`.
+pub const LOCAL_MANIFEST_NAME: &str = "still.local.toml";
+
+/// The nearest directory at or above `start` that contains a `still.toml`.
+pub fn find_project_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(MANIFEST_NAME).is_file())
+        .map(Path::to_path_buf)
+}
+
+/// Short stable id of a project root, naming the state kept for it outside the
+/// project (task fingerprints, services).
+pub fn project_id(root: &Path) -> String {
+    Hashing::sha256(root.to_string_lossy().as_bytes())[..16].to_string()
+}
+
+/// Project manifest (`still.toml`). See `examples/still.toml` and `examples/still.schema.json`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tools: BTreeMap<String, ToolEntry>,
+
+    #[serde(default, skip_serializing_if = "PackageMap::is_empty")]
+    pub packages: PackageMap,
+
+    #[serde(default, skip_serializing_if = "PackageMap::is_empty")]
+    pub apps: PackageMap,
+
+    #[serde(default, skip_serializing_if = "EnvSection::is_empty")]
+    pub env: EnvSection,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tasks: BTreeMap<String, TaskEntry>,
+
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub services: BTreeMap<String, ServiceEntry>,
+
+    /// Sections that are not modelled yet (`hooks`).
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, toml::Value>,
+}
+
+/// `node = "22"` or `[tools.rust]` with options.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ToolEntry {
+    Version(String),
+    Detailed(Box<ToolOptions>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolOptions {
+    pub version: String,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub components: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub targets: Vec<String>,
+
+    /// Glob for the release asset to download (`github` backend), e.g. `mytool-*-linux-musl.tar.gz`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub asset: Option<String>,
+
+    /// Plugin repository for the `asdf` backend: a git URL or a local path.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plugin: Option<String>,
+
+    /// Download URL template for the `url` backend; `{version}`, `{os}` and `{arch}`
+    /// are filled in (`https://example.com/tool-{version}-{os}-{arch}.tar.gz`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+
+    /// Expected sha256 of the `url` download per platform, keyed `<os>-<arch>`
+    /// (`macos-aarch64`, `linux-x86_64`).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub sha256: BTreeMap<String, String>,
+
+    /// How the `url` download is packaged: `tar.gz`, `zip` or `bin`.
+    /// Guessed from the URL, then from the file itself, when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub format: Option<String>,
+
+    /// Files inside the `url` or `github` download to link, relative to its root.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub bins: Vec<String>,
+}
+
+impl ToolEntry {
+    pub fn version(&self) -> &str {
+        match self {
+            ToolEntry::Version(version) => version,
+            ToolEntry::Detailed(options) => &options.version,
+        }
+    }
+
+    pub fn backend(&self) -> Option<&str> {
+        match self {
+            ToolEntry::Version(_) => None,
+            ToolEntry::Detailed(options) => options.backend.as_deref(),
+        }
+    }
+}
+
+/// `[env]`: variables, dotenv `files` to load and `PATH` changes.
+///
+/// Later sources win: the dotenv files in order, then the variables of the table.
+/// Values may use `${VAR}`, `${VAR:-default}` and `{{tools.<name>.path}}`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct EnvSection {
+    /// Dotenv files relative to the project root; missing ones are skipped.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub files: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "PathDirectives::is_empty")]
+    pub path: PathDirectives,
+
+    /// Strings, numbers and booleans.
+    #[serde(flatten)]
+    pub vars: BTreeMap<String, toml::Value>,
+}
+
+/// `[env.path]`: directories added to `PATH`, relative to the project root.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PathDirectives {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub prepend: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub append: Vec<String>,
+}
+
+impl EnvSection {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.path.is_empty() && self.vars.is_empty()
+    }
+}
+
+impl PathDirectives {
+    pub fn is_empty(&self) -> bool {
+        self.prepend.is_empty() && self.append.is_empty()
+    }
+}
+
+/// `test = "cargo test"`, `check = ["cargo fmt --check", "cargo test"]` or `[tasks.lint]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TaskEntry {
+    Run(TaskRun),
+    Detailed(Box<TaskOptions>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaskOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+
+    pub run: TaskRun,
+
+    /// Tasks that have to succeed before this one starts.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends: Vec<String>,
+
+    /// Services that have to be running (`[services]`).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub requires: Vec<String>,
+
+    /// Globs of the files the task reads. With them, a task whose sources,
+    /// definition and tool versions are unchanged since its last success is skipped.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sources: Vec<String>,
+
+    /// Globs of the files the task writes; it runs again when one matches nothing.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<String>,
+}
+
+/// One shell script (may span lines) or a list of commands run in order.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum TaskRun {
+    Script(String),
+    Commands(Vec<String>),
+}
+
+impl TaskEntry {
+    pub fn options(&self) -> TaskOptions {
+        match self {
+            TaskEntry::Run(run) => TaskOptions {
+                description: None,
+                run: run.clone(),
+                depends: Vec::new(),
+                requires: Vec::new(),
+                sources: Vec::new(),
+                outputs: Vec::new(),
+            },
+            TaskEntry::Detailed(options) => options.as_ref().clone(),
+        }
+    }
+}
+
+impl TaskRun {
+    pub fn commands(&self) -> Vec<String> {
+        match self {
+            TaskRun::Script(script) => vec![script.clone()],
+            TaskRun::Commands(commands) => commands.clone(),
+        }
+    }
+}
+
+/// `db = "postgres -D data"`, `web = { task = "dev-server" }` (the start command
+/// alone) or `[services.docker]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum ServiceEntry {
+    Start(ServiceAction),
+    Detailed(Box<ServiceOptions>),
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ServiceOptions {
+    /// Built-in definition to start from: `postgresql`, `redis`, `mysql` or `minio`.
+    /// `start`, `stop` and `check` given here replace the preset's own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+
+    /// Version of the preset's Homebrew package (`16`).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    /// Where a preset keeps its data, relative to the project root; under the
+    /// Still root when absent.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<String>,
+
+    /// Runs in the foreground while the service is up. One that exits successfully
+    /// while `check` passes started a daemon of its own.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<ServiceAction>,
+
+    /// Runs after the start command's processes are stopped.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop: Option<ServiceAction>,
+
+    /// Health probe: the service is healthy while this exits with 0.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub check: Option<ServiceAction>,
+}
+
+/// A shell command, `{ command = "..." }` or `{ task = "..." }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged, deny_unknown_fields)]
+pub enum ServiceAction {
+    Shell(String),
+    Command { command: String },
+    Task { task: String },
+}
+
+impl ServiceEntry {
+    pub fn options(&self) -> ServiceOptions {
+        match self {
+            ServiceEntry::Start(start) => ServiceOptions {
+                start: Some(start.clone()),
+                ..ServiceOptions::default()
+            },
+            ServiceEntry::Detailed(options) => options.as_ref().clone(),
+        }
+    }
+}
+
+/// `[packages]` / `[apps]`: a `latest` list plus per-package tables.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PackageMap {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub latest: Vec<String>,
+
+    #[serde(flatten)]
+    pub entries: BTreeMap<String, PackageOptions>,
+}
+
+impl PackageMap {
+    pub fn is_empty(&self) -> bool {
+        self.latest.is_empty() && self.entries.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PackageOptions {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub names: Option<PlatformNames>,
+
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub platforms: Vec<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ignore: Option<String>,
+
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub only: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PlatformNames {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub macos: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linux: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub windows: Option<String>,
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read manifest at {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        Ok(toml::from_str(content)?)
+    }
+
+    pub fn to_toml_string(&self) -> Result<String> {
+        toml::to_string_pretty(self).context("Failed to serialize manifest")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_example_manifest() {
+        let manifest = Manifest::parse(include_str!("../../../examples/still.toml")).unwrap();
+
+        assert_eq!(manifest.tools["node"].version(), "22");
+        assert_eq!(manifest.tools["rust"].backend(), Some("rustup"));
+        assert_eq!(manifest.tools["pnpm"].backend(), Some("npm"));
+        assert!(manifest.packages.latest.contains(&"ripgrep".to_string()));
+        assert_eq!(
+            manifest.packages.entries["llvm"].version.as_deref(),
+            Some("18")
+        );
+        assert_eq!(
+            manifest.packages.entries["fd"]
+                .names
+                .as_ref()
+                .and_then(|n| n.linux.as_deref()),
+            Some("fd-find")
+        );
+        assert!(manifest.apps.latest.contains(&"zed".to_string()));
+        assert_eq!(manifest.env.files, vec![".env", ".env.local"]);
+        assert_eq!(manifest.env.vars["NODE_ENV"].as_str(), Some("development"));
+        assert_eq!(manifest.tasks["ci"].options().depends, vec!["lint", "test"]);
+        assert_eq!(manifest.tasks["lint"].options().run.commands().len(), 2);
+        assert_eq!(
+            manifest.tasks["test"].options().run,
+            TaskRun::Script("cargo test".into())
+        );
+        assert_eq!(
+            manifest.services["docker-compose"].options().start,
+            Some(ServiceAction::Shell("docker compose up".into()))
+        );
+        assert_eq!(
+            manifest.services["dev-server"].options().start,
+            Some(ServiceAction::Task {
+                task: "dev-server".into()
+            })
+        );
+        assert_eq!(
+            manifest.services["docker"].options().check,
+            Some(ServiceAction::Task {
+                task: "docker:check".into()
+            })
+        );
+        let postgres = manifest.services["postgres"].options();
+        assert_eq!(postgres.preset.as_deref(), Some("postgresql"));
+        assert_eq!(postgres.version.as_deref(), Some("16"));
+        assert!(!manifest.extra.contains_key("services"));
+    }
+}
@@ -2,12 +2,142 @@ use std::{fmt, str::FromStr};
 
 use anyhow::{Context, Result, bail};
 
+/// Runtimes with a built-in installer; these use [`Backend::Core`] unless qualified.
+pub const CORE_TOOLS: &[&str] = &["node", "python", "rust", "go"];
+
+/// Where a tool is installed from. `None` on a [`ToolSpec`] means the default:
+/// [`Backend::Core`] for [`CORE_TOOLS`], Homebrew for everything else.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Backend {
+    Core,
+    Homebrew,
+    Npm,
+    Cargo,
+    Github,
+    Rustup,
+    Asdf,
+    Url,
+}
+
+impl Backend {
+    pub fn all() -> &'static [Backend] {
+        &[
+            Backend::Core,
+            Backend::Homebrew,
+            Backend::Npm,
+            Backend::Cargo,
+            Backend::Github,
+            Backend::Rustup,
+            Backend::Asdf,
+            Backend::Url,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Backend::Core => "core",
+            Backend::Homebrew => "brew",
+            Backend::Npm => "npm",
+            Backend::Cargo => "cargo",
+            Backend::Github => "github",
+            Backend::Rustup => "rustup",
+            Backend::Asdf => "asdf",
+            Backend::Url => "url",
+        }
+    }
+}
+
+impl fmt::Display for Backend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Backend {
+    type Err = anyhow::Error;
+
+    fn from_str(input: &str) -> Result<Self> {
+        match input.trim().to_ascii_lowercase().as_str() {
+            "core" => Ok(Backend::Core),
+            "brew" | "homebrew" => Ok(Backend::Homebrew),
+            "npm" => Ok(Backend::Npm),
+            "cargo" => Ok(Backend::Cargo),
+            "github" | "gh" => Ok(Backend::Github),
+            "rustup" => Ok(Backend::Rustup),
+            "asdf" | "mise" => Ok(Backend::Asdf),
+            "url" => Ok(Backend::Url),
+            other => {
+                let known: Vec<&str> = Backend::all().iter().map(|b| b.as_str()).collect();
+                bail!(
+                    "unknown backend \"{}\" (expected one of: {})",
+                    other,
+                    known.join(", ")
+                )
+            }
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct ToolSpec {
+    /// Explicit backend qualifier (`npm:` in `npm:prettier@3`).
+    pub backend: Option<Backend>,
+    /// Tool name, possibly namespaced (`owner/repo`, `@scope/pkg`).
     pub name: String,
     pub version: String,
 }
 
+impl ToolSpec {
+    /// Stable identifier for this tool, independent of version.
+    /// This is the key used in `still.lock`. A qualifier naming the default backend is
+    /// dropped, so `core:node` and `node` share the key `node`, and `brew:jq` and `jq`
+    /// share `jq`.
+    pub fn key(&self) -> String {
+        match self.backend {
+            Some(backend) if backend != self.default_backend() => {
+                format!("{}:{}", backend, self.name)
+            }
+            _ => self.name.clone(),
+        }
+    }
+
+    /// The backend that installs this tool, applying the default when unqualified.
+    pub fn effective_backend(&self) -> Backend {
+        self.backend.unwrap_or_else(|| self.default_backend())
+    }
+
+    fn default_backend(&self) -> Backend {
+        if CORE_TOOLS.contains(&self.name.as_str()) {
+            Backend::Core
+        } else {
+            Backend::Homebrew
+        }
+    }
+
+    /// Everything before the last `/` of a namespaced name (`owner` in `owner/repo`).
+    pub fn namespace(&self) -> Option<&str> {
+        self.name.rsplit_once('/').map(|(ns, _)| ns)
+    }
+
+    /// The last path segment of the name (`repo` in `owner/repo`).
+    pub fn short_name(&self) -> &str {
+        self.name
+            .rsplit_once('/')
+            .map(|(_, name)| name)
+            .unwrap_or(&self.name)
+    }
+
+    pub fn is_latest(&self) -> bool {
+        self.version.eq_ignore_ascii_case("latest")
+    }
+}
+
+impl fmt::Display for ToolSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}@{}", self.key(), self.version)
+    }
+}
+
 #[derive(Debug, Clone)]
 enum ParseToolSpecError {
     EmptyInput,
@@ -17,6 +147,10 @@ enum ParseToolSpecError {
     EmptyTool {
         input: String,
     },
+    InvalidBackend {
+        input: String,
+        reason: String,
+    },
     InvalidToolFormat {
         name: String,
         reason: String,
@@ -32,8 +166,7 @@ impl std::error::Error for ParseToolSpecError {}
 
 impl fmt::Display for ParseToolSpecError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let examples =
-            "Examples: bun@1.3.5, bun@latest, bun (defaults to latest), bun@ (defaults to latest)";
+        let examples = "Examples: bun@1.3.5, bun@latest, bun (defaults to latest), bun@ (defaults to latest), npm:prettier@3, github:owner/repo@v1.2.3";
         match self {
             Self::EmptyInput => write!(f, "Tool spec cannot be empty. {}", examples),
             Self::TooManyAts { input } => write!(
@@ -46,9 +179,16 @@ impl fmt::Display for ParseToolSpecError {
                 "Invalid tool spec \"{}\": tool name cannot be empty (before '@'). {}",
                 input, examples
             ),
+            Self::InvalidBackend { input, reason } => {
+                write!(
+                    f,
+                    "Invalid tool spec \"{}\": {}. {}",
+                    input, reason, examples
+                )
+            }
             Self::InvalidToolFormat { name, reason } => write!(
                 f,
-                "Invalid tool name \"{}\": {}. Tool names must match: [a-zA-Z][a-zA-Z0-9_-]*",
+                "Invalid tool name \"{}\": {}. Tool names must match: [a-zA-Z][a-zA-Z0-9_.-]*, optionally namespaced with '/'",
                 name, reason
             ),
             Self::InvalidVersion {
@@ -57,26 +197,29 @@ impl fmt::Display for ParseToolSpecError {
                 reason,
             } => write!(
                 f,
-                "Invalid version \"{}\" for tool \"{}\": {}. Version must be SemVer (e.g. 1.2.3) or \"latest\". {}",
+                "Invalid version \"{}\" for tool \"{}\": {}. Version must be SemVer (e.g. 1.2.3), a partial version (e.g. 20, 3.12), \"lts\", \"lts/<codename>\", a channel (stable, nightly-2024-06-01) or \"latest\". {}",
                 version, name, reason, examples
             ),
         }
     }
 }
 
-fn is_valid_tool_format(tool: &str) -> Result<()> {
-    let mut chars = tool.chars();
+fn is_valid_segment(segment: &str) -> Result<()> {
+    let mut chars = segment.chars();
 
     let Some(first) = chars.next() else {
-        bail!("tool name is empty");
+        bail!("namespace segment is empty");
     };
 
-    if !first.is_ascii_alphabetic() {
-        bail!("tool name must start with a letter");
+    if !first.is_ascii_alphanumeric() {
+        bail!(
+            "name segment \"{}\" must start with a letter or digit",
+            segment
+        );
     }
 
     for c in chars {
-        if !(c.is_ascii_alphanumeric() || c == '_' || c == '-') {
+        if !(c.is_ascii_alphanumeric() || c == '_' || c == '-' || c == '.') {
             bail!("tool name contains invalid character '{}'", c);
         }
     }
@@ -84,12 +227,95 @@ fn is_valid_tool_format(tool: &str) -> Result<()> {
     Ok(())
 }
 
-fn validate_version(tool: &str, version: &str) -> Result<()> {
-    if version.eq_ignore_ascii_case("latest") {
+fn is_valid_tool_format(tool: &str, backend: Option<Backend>) -> Result<()> {
+    let mut chars = tool.chars();
+
+    let Some(first) = chars.next() else {
+        bail!("tool name is empty");
+    };
+
+    let namespaced = tool.contains('/');
+
+    // npm scopes (`@types/node`) are the only names allowed to start with '@'.
+    let name = match tool.strip_prefix('@') {
+        Some(rest) if backend == Some(Backend::Npm) && namespaced => rest,
+        Some(_) => bail!("only scoped npm packages may start with '@'"),
+        None => tool,
+    };
+
+    if !namespaced && !first.is_ascii_alphabetic() {
+        bail!("tool name must start with a letter");
+    }
+
+    let segments: Vec<&str> = name.split('/').collect();
+    for segment in &segments {
+        is_valid_segment(segment)?;
+    }
+
+    if backend == Some(Backend::Github) && segments.len() != 2 {
+        bail!("github tools must be named owner/repo");
+    }
+
+    Ok(())
+}
+
+fn is_partial_version(version: &str) -> bool {
+    let parts: Vec<&str> = version.split('.').collect();
+    parts.len() <= 2
+        && parts
+            .iter()
+            .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+}
+
+/// Release-line aliases: `lts` or `lts/<codename>` (`lts/iron`).
+fn is_lts_alias(version: &str) -> bool {
+    let lower = version.to_ascii_lowercase();
+    lower == "lts"
+        || lower.strip_prefix("lts/").is_some_and(|codename| {
+            !codename.is_empty() && codename.chars().all(|c| c.is_ascii_alphabetic())
+        })
+}
+
+/// Toolchain channels: `stable`, `beta`, `nightly`, optionally dated (`nightly-2024-06-01`).
+fn is_channel(version: &str) -> bool {
+    let (channel, date) = match version.split_once('-') {
+        Some((channel, date)) => (channel, Some(date)),
+        None => (version, None),
+    };
+    matches!(channel, "stable" | "beta" | "nightly")
+        && date.is_none_or(|date| {
+            let parts: Vec<&str> = date.split('-').collect();
+            parts.len() == 3
+                && parts
+                    .iter()
+                    .all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+        })
+}
+
+/// Whether `tool` installs through rustup, the only backend that knows channels.
+fn uses_rustup(tool: &str, backend: Option<Backend>) -> bool {
+    match backend {
+        Some(Backend::Rustup) => true,
+        None | Some(Backend::Core) => tool == "rust",
+        Some(_) => false,
+    }
+}
+
+fn validate_version(tool: &str, backend: Option<Backend>, version: &str) -> Result<()> {
+    if version.eq_ignore_ascii_case("latest")
+        || is_lts_alias(version)
+        || (uses_rustup(tool, backend) && is_channel(version))
+    {
         return Ok(());
     }
 
-    semver::Version::parse(version).map(|_| ()).map_err(|e| {
+    // Release tags are commonly written with a leading 'v' (github:owner/repo@v1.2.3).
+    let bare = version.strip_prefix('v').unwrap_or(version);
+    if is_partial_version(bare) {
+        return Ok(());
+    }
+
+    semver::Version::parse(bare).map(|_| ()).map_err(|e| {
         anyhow::anyhow!(ParseToolSpecError::InvalidVersion {
             name: tool.to_string(),
             version: version.to_string(),
@@ -98,6 +324,14 @@ fn validate_version(tool: &str, version: &str) -> Result<()> {
     })
 }
 
+/// Split `name@version`, ignoring a leading scope '@' (`@scope/pkg@1.0.0`).
+fn split_version(s: &str) -> Option<(&str, &str)> {
+    let offset = usize::from(s.starts_with('@'));
+    s[offset..]
+        .split_once('@')
+        .map(|(t, v)| (&s[..offset + t.len()], v))
+}
+
 impl FromStr for ToolSpec {
     type Err = anyhow::Error;
 
@@ -108,14 +342,28 @@ impl FromStr for ToolSpec {
             bail!(ParseToolSpecError::EmptyInput);
         }
 
-        if s.matches('@').count() > 1 {
+        let (backend, rest) = match s.split_once(':') {
+            None => (None, s),
+            Some((b, rest)) => {
+                let backend = b.parse::<Backend>().map_err(|e| {
+                    anyhow::anyhow!(ParseToolSpecError::InvalidBackend {
+                        input: s.to_string(),
+                        reason: format!("{e:#}"),
+                    })
+                })?;
+                (Some(backend), rest)
+            }
+        };
+
+        let scoped = usize::from(rest.starts_with('@'));
+        if rest.matches('@').count() > 1 + scoped {
             bail!(ParseToolSpecError::TooManyAts {
                 input: s.to_string(),
             });
         }
 
-        let (tool, version) = match s.split_once('@') {
-            None => (s, "latest"),
+        let (tool, version) = match split_version(rest) {
+            None => (rest, "latest"),
             Some((t, v)) if v.is_empty() => (t, "latest"),
             Some((t, v)) => (t, v),
         };
@@ -127,7 +375,7 @@ impl FromStr for ToolSpec {
         }
 
         // Tool format validation
-        if let Err(reason) = is_valid_tool_format(tool) {
+        if let Err(reason) = is_valid_tool_format(tool, backend) {
             bail!(ParseToolSpecError::InvalidToolFormat {
                 name: tool.to_string(),
                 reason: format!("{reason:#}"), // preserve anyhow message nicely
@@ -135,12 +383,105 @@ impl FromStr for ToolSpec {
         }
 
         // Version validation (+ context)
-        validate_version(tool, version)
+        validate_version(tool, backend, version)
             .with_context(|| format!("while validating version for tool \"{}\"", tool))?;
 
         Ok(Self {
+            backend,
             name: tool.to_string(),
             version: version.to_string(),
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(s: &str) -> ToolSpec {
+        s.parse().unwrap_or_else(|e| panic!("{s}: {e:#}"))
+    }
+
+    #[test]
+    fn plain_specs() {
+        let spec = parse("ripgrep");
+        assert_eq!(spec.backend, None);
+        assert_eq!(spec.name, "ripgrep");
+        assert_eq!(spec.version, "latest");
+
+        assert_eq!(parse("bun@").version, "latest");
+        assert_eq!(parse("bun@1.3.5").version, "1.3.5");
+        assert_eq!(parse("node@20").version, "20");
+        assert_eq!(parse("python@3.12").version, "3.12");
+        assert_eq!(parse("node@lts").version, "lts");
+        assert_eq!(parse("node@lts/iron").version, "lts/iron");
+        assert_eq!(
+            parse("rust@nightly-2024-06-01").version,
+            "nightly-2024-06-01"
+        );
+        assert_eq!(parse("rustup:rust@beta").version, "beta");
+    }
+
+    #[test]
+    fn default_backends() {
+        assert_eq!(parse("node@20").effective_backend(), Backend::Core);
+        assert_eq!(parse("brew:node@20").effective_backend(), Backend::Homebrew);
+        assert_eq!(parse("jq").effective_backend(), Backend::Homebrew);
+        assert_eq!(parse("core:node@20").key(), "node");
+        assert_eq!(parse("brew:jq@1.7").key(), "jq");
+        assert_eq!(parse("brew:node@20").key(), "brew:node");
+    }
+
+    #[test]
+    fn backend_qualified_specs() {
+        let spec = parse("npm:prettier@3");
+        assert_eq!(spec.backend, Some(Backend::Npm));
+        assert_eq!(spec.name, "prettier");
+        assert_eq!(spec.key(), "npm:prettier");
+
+        let spec = parse("github:BurntSushi/ripgrep@v14.1.0");
+        assert_eq!(spec.backend, Some(Backend::Github));
+        assert_eq!(spec.namespace(), Some("BurntSushi"));
+        assert_eq!(spec.short_name(), "ripgrep");
+        assert_eq!(spec.version, "v14.1.0");
+
+        let spec = parse("npm:@types/node@22.1.0");
+        assert_eq!(spec.name, "@types/node");
+        assert_eq!(spec.version, "22.1.0");
+    }
+
+    #[test]
+    fn display_round_trips() {
+        for input in [
+            "ripgrep@latest",
+            "node@20",
+            "npm:prettier@3",
+            "npm:@scope/pkg@1.0.0",
+            "cargo:cargo-nextest@0.9.72",
+            "github:cli/cli@v2.50.0",
+        ] {
+            let spec = parse(input);
+            assert_eq!(spec.to_string(), input);
+            assert_eq!(parse(&spec.to_string()), spec);
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_specs() {
+        for input in [
+            "",
+            "npm:",
+            "nope:thing",
+            "github:ripgrep",
+            "@scope/pkg",
+            "a@b@c",
+            "1tool",
+            "tool@not-a-version",
+            "node@lts/",
+            "rust@nightly-june",
+            "node@nightly",
+        ] {
+            assert!(input.parse::<ToolSpec>().is_err(), "{input} should fail");
+        }
+    }
+}
@@ -36,9 +36,46 @@ impl ArchiveExtractor {
         let mut archive = Archive::new(decompressed.as_slice());
         archive.unpack(&temp_extract)?;
 
+        Self::move_into_place(&temp_extract, install_path).await
+    }
+
+    /// Extract a zip archive, stripping a single top-level directory like
+    /// [`extract_tar_gz`](Self::extract_tar_gz). Unix permissions are kept.
+    pub async fn extract_zip(
+        data: &[u8],
+        install_path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        fs::create_dir_all(install_path).await?;
+
+        let temp_extract = install_path.join(".tmp_extract");
+        fs::create_dir_all(&temp_extract).await?;
+
+        let mut archive = zip::ZipArchive::new(std::io::Cursor::new(data))?;
+        archive.extract(&temp_extract)?;
+
+        Self::move_into_place(&temp_extract, install_path).await
+    }
+
+    /// Extract a `.tar.gz` or `.zip` archive, detected from its contents.
+    pub async fn extract(
+        data: &[u8],
+        install_path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match ArchiveFormat::detect(data) {
+            Some(ArchiveFormat::TarGz) => Self::extract_tar_gz(data, install_path).await,
+            Some(ArchiveFormat::Zip) => Self::extract_zip(data, install_path).await,
+            None => Err("Unsupported archive format".into()),
+        }
+    }
+
+    /// Move the unpacked contents of `temp_extract` into `install_path`.
+    async fn move_into_place(
+        temp_extract: &Path,
+        install_path: &Path,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         // Check if there's a single top-level directory (common in Homebrew bottles)
         let mut entries = Vec::new();
-        let mut read_dir = fs::read_dir(&temp_extract).await?;
+        let mut read_dir = fs::read_dir(temp_extract).await?;
         while let Some(entry) = read_dir.next_entry().await? {
             entries.push(entry);
         }
@@ -84,8 +121,41 @@ impl ArchiveExtractor {
         }
 
         // Clean up temp directory
-        fs::remove_dir(&temp_extract).await?;
+        fs::remove_dir(temp_extract).await?;
 
         Ok(())
     }
 }
+
+/// Archive formats [`ArchiveExtractor::extract`] understands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ArchiveFormat {
+    TarGz,
+    Zip,
+}
+
+impl ArchiveFormat {
+    /// Recognise an archive by its magic bytes; `None` for anything else,
+    /// such as a bare executable.
+    pub fn detect(data: &[u8]) -> Option<Self> {
+        if data.starts_with(&[0x1f, 0x8b]) {
+            Some(ArchiveFormat::TarGz)
+        } else if data.starts_with(b"PK\x03\x04") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+
+    /// The format a file name's extension (`.tar.gz`, `.tgz`, `.zip`) indicates.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let lower = name.to_ascii_lowercase();
+        if lower.ends_with(".tar.gz") || lower.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else if lower.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else {
+            None
+        }
+    }
+}
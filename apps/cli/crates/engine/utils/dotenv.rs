@@ -0,0 +1,299 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+
+/// One `KEY=value` assignment of a dotenv file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DotenvEntry {
+    pub key: String,
+    pub value: String,
+    /// Single-quoted values are taken literally; everything else is interpolated.
+    pub interpolate: bool,
+}
+
+pub struct Dotenv;
+
+/// Evaluates the expression inside `{{ }}`.
+type Template<'a> = dyn Fn(&str) -> Result<String> + 'a;
+
+impl Dotenv {
+    pub fn load(path: &Path) -> Result<Vec<DotenvEntry>> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Self::parse(&content).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// Parse `KEY=value` lines with optional `export`, `#` comments, single-quoted
+    /// (literal) and double-quoted (escapes, may span lines) values.
+    pub fn parse(content: &str) -> Result<Vec<DotenvEntry>> {
+        let chars: Vec<char> = content.chars().collect();
+        let mut entries = Vec::new();
+        let mut pos = 0;
+        let mut line = 1;
+
+        while pos < chars.len() {
+            // Blank lines, indentation and comment lines.
+            match chars[pos] {
+                '\n' => {
+                    line += 1;
+                    pos += 1;
+                    continue;
+                }
+                c if c.is_whitespace() => {
+                    pos += 1;
+                    continue;
+                }
+                '#' => {
+                    while pos < chars.len() && chars[pos] != '\n' {
+                        pos += 1;
+                    }
+                    continue;
+                }
+                _ => {}
+            }
+
+            let rest: String = chars[pos..chars.len().min(pos + 7)].iter().collect();
+            if rest == "export " || rest == "export\t" {
+                pos += 7;
+                while pos < chars.len() && (chars[pos] == ' ' || chars[pos] == '\t') {
+                    pos += 1;
+                }
+            }
+
+            let start = pos;
+            while pos < chars.len()
+                && (chars[pos].is_ascii_alphanumeric() || matches!(chars[pos], '_' | '.' | '-'))
+            {
+                pos += 1;
+            }
+            let key: String = chars[start..pos].iter().collect();
+            while pos < chars.len() && (chars[pos] == ' ' || chars[pos] == '\t') {
+                pos += 1;
+            }
+            if key.is_empty() || chars.get(pos) != Some(&'=') {
+                anyhow::bail!("line {}: expected KEY=value", line);
+            }
+            pos += 1;
+            while pos < chars.len() && (chars[pos] == ' ' || chars[pos] == '\t') {
+                pos += 1;
+            }
+
+            let (value, interpolate) = match chars.get(pos) {
+                Some('\'') => {
+                    let start_line = line;
+                    pos += 1;
+                    let mut value = String::new();
+                    loop {
+                        match chars.get(pos) {
+                            None => anyhow::bail!("line {}: unterminated ' quote", start_line),
+                            Some('\'') => break,
+                            Some(c) => {
+                                if *c == '\n' {
+                                    line += 1;
+                                }
+                                value.push(*c);
+                            }
+                        }
+                        pos += 1;
+                    }
+                    pos += 1;
+                    (value, false)
+                }
+                Some('"') => {
+                    let start_line = line;
+                    pos += 1;
+                    let mut value = String::new();
+                    loop {
+                        match chars.get(pos) {
+                            None => anyhow::bail!("line {}: unterminated \" quote", start_line),
+                            Some('"') => break,
+                            Some('\\') => {
+                                pos += 1;
+                                match chars.get(pos) {
+                                    Some('n') => value.push('\n'),
+                                    Some('t') => value.push('\t'),
+                                    Some('r') => value.push('\r'),
+                                    // Kept escaped for `interpolate`.
+                                    Some('$') => value.push_str("$$"),
+                                    Some(c) => value.push(*c),
+                                    None => continue,
+                                }
+                            }
+                            Some(c) => {
+                                if *c == '\n' {
+                                    line += 1;
+                                }
+                                value.push(*c);
+                            }
+                        }
+                        pos += 1;
+                    }
+                    pos += 1;
+                    (value, true)
+                }
+                _ => {
+                    let start = pos;
+                    while pos < chars.len() && chars[pos] != '\n' {
+                        // ` #` starts a comment in unquoted values.
+                        if chars[pos] == '#' && pos > start && chars[pos - 1].is_whitespace() {
+                            break;
+                        }
+                        pos += 1;
+                    }
+                    let value: String = chars[start..pos].iter().collect();
+                    (value.trim().to_string(), true)
+                }
+            };
+
+            // Anything after a closing quote up to the end of the line is ignored.
+            while pos < chars.len() && chars[pos] != '\n' {
+                pos += 1;
+            }
+            entries.push(DotenvEntry {
+                key,
+                value,
+                interpolate,
+            });
+        }
+
+        Ok(entries)
+    }
+
+    /// Expand `${VAR}`, `${VAR:-default}` and `$VAR` with `lookup`, as a `.env`
+    /// file does; `{{` is kept as written. `$$` is a literal `$`; unknown variables
+    /// expand to nothing.
+    pub fn expand_vars(value: &str, lookup: impl Fn(&str) -> Option<String>) -> Result<String> {
+        Self::expand(value, lookup, None)
+    }
+
+    /// Like [`expand_vars`](Self::expand_vars), and `{{ expr }}` with `template`.
+    pub fn interpolate(
+        value: &str,
+        lookup: impl Fn(&str) -> Option<String>,
+        template: impl Fn(&str) -> Result<String>,
+    ) -> Result<String> {
+        Self::expand(value, lookup, Some(&template))
+    }
+
+    fn expand(
+        value: &str,
+        lookup: impl Fn(&str) -> Option<String>,
+        template: Option<&Template<'_>>,
+    ) -> Result<String> {
+        let chars: Vec<char> = value.chars().collect();
+        let mut out = String::new();
+        let mut pos = 0;
+
+        while pos < chars.len() {
+            match chars[pos] {
+                '$' if chars.get(pos + 1) == Some(&'$') => {
+                    out.push('$');
+                    pos += 2;
+                }
+                '$' if chars.get(pos + 1) == Some(&'{') => {
+                    let close = chars[pos..]
+                        .iter()
+                        .position(|c| *c == '}')
+                        .map(|i| pos + i)
+                        .ok_or_else(|| anyhow::anyhow!("unterminated ${{ in \"{}\"", value))?;
+                    let inner: String = chars[pos + 2..close].iter().collect();
+                    let (name, default) = match inner.split_once(":-") {
+                        Some((name, default)) => (name, Some(default)),
+                        None => (inner.as_str(), None),
+                    };
+                    let resolved = lookup(name).filter(|v| !v.is_empty() || default.is_none());
+                    out.push_str(&resolved.unwrap_or_else(|| default.unwrap_or("").to_string()));
+                    pos = close + 1;
+                }
+                '$' if chars
+                    .get(pos + 1)
+                    .is_some_and(|c| c.is_ascii_alphabetic() || *c == '_') =>
+                {
+                    let start = pos + 1;
+                    let mut end = start;
+                    while end < chars.len()
+                        && (chars[end].is_ascii_alphanumeric() || chars[end] == '_')
+                    {
+                        end += 1;
+                    }
+                    let name: String = chars[start..end].iter().collect();
+                    out.push_str(&lookup(&name).unwrap_or_default());
+                    pos = end;
+                }
+                '{' if template.is_some() && chars.get(pos + 1) == Some(&'{') => {
+                    let template = template.unwrap();
+                    let inner_start = pos + 2;
+                    let close = (inner_start..chars.len().saturating_sub(1))
+                        .find(|&i| chars[i] == '}' && chars[i + 1] == '}')
+                        .ok_or_else(|| anyhow::anyhow!("unterminated {{{{ in \"{}\"", value))?;
+                    let expr: String = chars[inner_start..close].iter().collect();
+                    out.push_str(&template(expr.trim())?);
+                    pos = close + 2;
+                }
+                c => {
+                    out.push(c);
+                    pos += 1;
+                }
+            }
+        }
+
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_quotes_comments_and_multiline_values() {
+        let content = r#"
+# database
+export DB_HOST=localhost   # local only
+DB_PASS='p#ss $word'
+GREETING="hello\tworld \"quoted\" \$HOME"
+CERT="-----BEGIN-----
+abc
+-----END-----" # trailing
+EMPTY=
+"#;
+        let entries = Dotenv::parse(content).unwrap();
+        let get = |key: &str| entries.iter().find(|e| e.key == key).unwrap();
+
+        assert_eq!(get("DB_HOST").value, "localhost");
+        assert_eq!(get("DB_PASS").value, "p#ss $word");
+        assert!(!get("DB_PASS").interpolate);
+        assert_eq!(get("GREETING").value, "hello\tworld \"quoted\" $$HOME");
+        assert_eq!(get("CERT").value, "-----BEGIN-----\nabc\n-----END-----");
+        assert_eq!(get("EMPTY").value, "");
+        assert_eq!(entries.len(), 5);
+
+        let err = Dotenv::parse("A=1\nnot a line\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"), "{err}");
+    }
+
+    #[test]
+    fn interpolates_variables_and_templates() {
+        let lookup = |name: &str| (name == "HOME").then(|| "/home/me".to_string());
+        let template = |expr: &str| match expr {
+            "tools.node.path" => Ok("/opt/still/tools/node/20.18.1".to_string()),
+            other => anyhow::bail!("unknown template {}", other),
+        };
+
+        let expand = |value: &str| Dotenv::interpolate(value, lookup, template);
+        assert_eq!(expand("${HOME}/.cache").unwrap(), "/home/me/.cache");
+        assert_eq!(
+            expand("$HOME:$MISSING:${MISSING:-x}").unwrap(),
+            "/home/me::x"
+        );
+        assert_eq!(expand("cost $$5").unwrap(), "cost $5");
+        assert_eq!(
+            expand("{{ tools.node.path }}/bin").unwrap(),
+            "/opt/still/tools/node/20.18.1/bin"
+        );
+        assert!(expand("{{ tools.deno.path }}").is_err());
+
+        let expand_vars = |value: &str| Dotenv::expand_vars(value, lookup);
+        assert_eq!(expand_vars("{{ $HOME }}").unwrap(), "{{ /home/me }}");
+        assert_eq!(expand_vars("a{{b").unwrap(), "a{{b");
+    }
+}
@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Filesystem utilities
 pub struct FsUtils;
@@ -13,5 +13,22 @@ impl FsUtils {
 
         Ok(home.join(".cache").join("still"))
     }
-}
 
+    /// Total size in bytes of the regular files under `path`. Symlinks are not followed.
+    pub fn dir_size(path: &Path) -> std::io::Result<u64> {
+        let mut total = 0;
+        let mut dirs = vec![path.to_path_buf()];
+        while let Some(dir) = dirs.pop() {
+            for entry in std::fs::read_dir(&dir)? {
+                let entry = entry?;
+                let file_type = entry.file_type()?;
+                if file_type.is_dir() {
+                    dirs.push(entry.path());
+                } else if file_type.is_file() {
+                    total += entry.metadata()?.len();
+                }
+            }
+        }
+        Ok(total)
+    }
+}
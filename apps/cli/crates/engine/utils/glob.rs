@@ -0,0 +1,201 @@
+use std::path::{Path, PathBuf};
+
+/// Glob patterns over `/`-separated paths: `*` and `?` within one path segment,
+/// `**` for any number of segments (`src/**/*.rs`).
+pub struct Glob;
+
+impl Glob {
+    /// Match with `*` (any run of characters) and `?` (one character).
+    pub fn match_name(pattern: &str, text: &str) -> bool {
+        let (pattern, text): (Vec<char>, Vec<char>) =
+            (pattern.chars().collect(), text.chars().collect());
+        let (mut p, mut t) = (0, 0);
+        let mut backtrack = None;
+        while t < text.len() {
+            match pattern.get(p) {
+                Some('*') => {
+                    backtrack = Some((p, t));
+                    p += 1;
+                }
+                Some(&c) if c == '?' || c == text[t] => {
+                    p += 1;
+                    t += 1;
+                }
+                _ => match backtrack {
+                    Some((star, matched)) => {
+                        p = star + 1;
+                        t = matched + 1;
+                        backtrack = Some((star, matched + 1));
+                    }
+                    None => return false,
+                },
+            }
+        }
+        pattern[p..].iter().all(|&c| c == '*')
+    }
+
+    /// Whether the relative `path` matches `pattern`.
+    pub fn match_path(pattern: &str, path: &str) -> bool {
+        let pattern: Vec<&str> = pattern.trim_start_matches("./").split('/').collect();
+        let path: Vec<&str> = path.split('/').collect();
+        Self::match_segments(&pattern, &path)
+    }
+
+    fn match_segments(pattern: &[&str], path: &[&str]) -> bool {
+        match pattern.split_first() {
+            None => path.is_empty(),
+            Some((&"**", rest)) => {
+                (0..=path.len()).any(|skip| Self::match_segments(rest, &path[skip..]))
+            }
+            Some((segment, rest)) => match path.split_first() {
+                Some((name, path)) => {
+                    Self::match_name(segment, name) && Self::match_segments(rest, path)
+                }
+                None => false,
+            },
+        }
+    }
+
+    /// Whether files under the relative directory `dir` could match `pattern`.
+    fn may_contain(pattern: &[&str], dir: &[&str]) -> bool {
+        match (pattern.split_first(), dir.split_first()) {
+            (_, None) | (Some((&"**", _)), _) => true,
+            (Some((segment, rest)), Some((name, dir))) => {
+                !rest.is_empty() && Self::match_name(segment, name) && Self::may_contain(rest, dir)
+            }
+            (None, Some(_)) => false,
+        }
+    }
+
+    /// The leading directories of `pattern` that hold no wildcard, where a walk for it
+    /// can start.
+    fn literal_prefix(pattern: &[&str]) -> PathBuf {
+        let dirs = &pattern[..pattern.len().saturating_sub(1)];
+        dirs.iter()
+            .take_while(|segment| !segment.contains(['*', '?']) && **segment != "..")
+            .collect()
+    }
+
+    /// Files under `root` matching any of `patterns`, relative to `root` and sorted.
+    /// Only directories a pattern can reach are read, starting from each pattern's
+    /// literal prefix; `.git` is never entered.
+    pub fn files(root: &Path, patterns: &[String]) -> std::io::Result<Vec<String>> {
+        Self::walk(root, patterns, |_, _| false)
+    }
+
+    /// [`Glob::files`], leaving out every path for which `skip(path, is_dir)` holds.
+    pub fn walk(
+        root: &Path,
+        patterns: &[String],
+        skip: impl Fn(&str, bool) -> bool,
+    ) -> std::io::Result<Vec<String>> {
+        let patterns: Vec<Vec<&str>> = patterns
+            .iter()
+            .map(|p| p.trim_start_matches("./").split('/').collect())
+            .collect();
+        let mut starts: Vec<PathBuf> = patterns.iter().map(|p| Self::literal_prefix(p)).collect();
+        starts.sort();
+        starts.dedup_by(|nested, parent| nested.starts_with(parent));
+        // A start inside `.git` or a skipped directory would bypass the checks below.
+        let mut dirs: Vec<PathBuf> = starts
+            .into_iter()
+            .filter(|start| {
+                start
+                    .ancestors()
+                    .filter(|a| !a.as_os_str().is_empty())
+                    .all(|a| {
+                        a.file_name().is_none_or(|name| name != ".git")
+                            && !skip(&a.to_string_lossy().replace('\\', "/"), true)
+                    })
+            })
+            .collect();
+        let mut files = Vec::new();
+        while let Some(dir) = dirs.pop() {
+            let entries = match std::fs::read_dir(root.join(&dir)) {
+                Ok(entries) => entries,
+                Err(e)
+                    if matches!(
+                        e.kind(),
+                        std::io::ErrorKind::NotFound | std::io::ErrorKind::NotADirectory
+                    ) =>
+                {
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            for entry in entries {
+                let entry = entry?;
+                let name = entry.file_name().to_string_lossy().to_string();
+                let relative = dir.join(&name);
+                let path = relative.to_string_lossy().replace('\\', "/");
+                let file_type = entry.file_type()?;
+                if file_type.is_dir() {
+                    let segments: Vec<&str> = path.split('/').collect();
+                    if name != ".git"
+                        && patterns.iter().any(|p| Self::may_contain(p, &segments))
+                        && !skip(&path, true)
+                    {
+                        dirs.push(relative);
+                    }
+                    continue;
+                }
+                let segments: Vec<&str> = path.split('/').collect();
+                if !skip(&path, false)
+                    && patterns.iter().any(|p| Self::match_segments(p, &segments))
+                {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch::Scratch;
+
+    #[test]
+    fn matches_paths_with_double_star() {
+        assert!(Glob::match_name("a*b?d", "axxbcd"));
+        assert!(!Glob::match_name("a*b?d", "axxbd"));
+
+        assert!(Glob::match_path("src/**/*.rs", "src/main.rs"));
+        assert!(Glob::match_path("src/**/*.rs", "src/a/b/lib.rs"));
+        assert!(!Glob::match_path("src/*.rs", "src/a/lib.rs"));
+        assert!(Glob::match_path("./build.rs", "build.rs"));
+        assert!(Glob::match_path("**", "any/thing"));
+        assert!(!Glob::match_path("proto/*.proto", "proto/v1/a.proto"));
+    }
+
+    #[test]
+    fn walks_only_directories_a_pattern_reaches() {
+        let root = Scratch::new("glob-walk");
+        for file in [
+            "proto/v1/a.proto",
+            "proto/b.proto",
+            "target/proto/c.proto",
+            "node_modules/x/d.proto",
+            "e.proto",
+        ] {
+            std::fs::create_dir_all(root.join(file).parent().unwrap()).unwrap();
+            std::fs::write(root.join(file), "").unwrap();
+        }
+
+        let seen = std::cell::RefCell::new(Vec::new());
+        let files = Glob::walk(&root, &["proto/**/*.proto".to_string()], |path, is_dir| {
+            if is_dir {
+                seen.borrow_mut().push(path.to_string());
+            }
+            false
+        })
+        .unwrap();
+        assert_eq!(files, ["proto/b.proto", "proto/v1/a.proto"]);
+        assert_eq!(*seen.borrow(), ["proto", "proto/v1"]);
+
+        let files = Glob::files(&root, &["*.proto".to_string()]).unwrap();
+        assert_eq!(files, ["e.proto"]);
+    }
+}
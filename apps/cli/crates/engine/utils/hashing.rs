@@ -1,4 +1,6 @@
-use sha2::{Digest, Sha256};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use sha2::{Digest, Sha256, Sha512};
 
 /// Hashing utilities
 pub struct Hashing;
@@ -24,5 +26,41 @@ impl Hashing {
 
         Ok(())
     }
-}
 
+    /// Subresource Integrity string (`sha512-<base64>`), the format npm publishes.
+    pub fn sha512_integrity(data: &[u8]) -> String {
+        format!("sha512-{}", STANDARD.encode(Sha512::digest(data)))
+    }
+
+    /// Verify data against an integrity string. Entries with an unsupported
+    /// algorithm (`sha1-`) are skipped; at least one entry must be checked.
+    pub fn verify_integrity(
+        data: &[u8],
+        integrity: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let mut checked = false;
+        for entry in integrity.split_whitespace() {
+            let Some((algorithm, expected)) = entry.split_once('-') else {
+                continue;
+            };
+            let computed = match algorithm {
+                "sha512" => STANDARD.encode(Sha512::digest(data)),
+                "sha256" => STANDARD.encode(Sha256::digest(data)),
+                _ => continue,
+            };
+            if computed != expected {
+                return Err(format!(
+                    "{} integrity check failed: expected {}, got {}",
+                    algorithm, expected, computed
+                )
+                .into());
+            }
+            checked = true;
+        }
+
+        if !checked {
+            return Err(format!("No supported hash in integrity \"{}\"", integrity).into());
+        }
+        Ok(())
+    }
+}
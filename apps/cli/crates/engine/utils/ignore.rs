@@ -0,0 +1,100 @@
+use crate::utils::glob::Glob;
+use std::path::Path;
+
+/// Ignore files read from the project root, in order.
+pub const IGNORE_FILES: &[&str] = &[".gitignore", ".ignore"];
+
+/// `.gitignore`-style rules: `#` comments, `!` to re-include, a trailing `/` for
+/// directories only, and patterns with a `/` anchored to the root. The last
+/// matching rule wins.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IgnoreRules {
+    rules: Vec<IgnoreRule>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct IgnoreRule {
+    pattern: String,
+    negate: bool,
+    dir_only: bool,
+    anchored: bool,
+}
+
+impl IgnoreRules {
+    /// The rules of every [`IGNORE_FILES`] entry under `root`; missing files add none.
+    pub fn load(root: &Path) -> Self {
+        let mut rules = Self::default();
+        for name in IGNORE_FILES {
+            if let Ok(content) = std::fs::read_to_string(root.join(name)) {
+                rules.rules.extend(Self::parse(&content).rules);
+            }
+        }
+        rules
+    }
+
+    pub fn parse(content: &str) -> Self {
+        let rules = content
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(|line| {
+                let (negate, line) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                let (dir_only, line) = match line.strip_suffix('/') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+                IgnoreRule {
+                    anchored: line.contains('/'),
+                    pattern: line.trim_start_matches('/').to_string(),
+                    negate,
+                    dir_only,
+                }
+            })
+            .collect();
+        Self { rules }
+    }
+
+    /// Whether `path` (relative to the root, `/`-separated) is ignored. Callers
+    /// walking the tree do not enter ignored directories.
+    pub fn is_ignored(&self, path: &str, is_dir: bool) -> bool {
+        let name = path.rsplit('/').next().unwrap_or(path);
+        let mut ignored = false;
+        for rule in &self.rules {
+            if rule.dir_only && !is_dir {
+                continue;
+            }
+            let matched = if rule.anchored {
+                Glob::match_path(&rule.pattern, path)
+            } else {
+                Glob::match_name(&rule.pattern, name)
+            };
+            if matched {
+                ignored = !rule.negate;
+            }
+        }
+        ignored
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn follows_gitignore_rules() {
+        let rules = IgnoreRules::parse(
+            "# build output\n/target\nnode_modules/\n*.log\n!keep.log\ndocs/*.html\n",
+        );
+        assert!(rules.is_ignored("target", true));
+        assert!(!rules.is_ignored("crates/target", true));
+        assert!(rules.is_ignored("web/node_modules", true));
+        assert!(!rules.is_ignored("node_modules", false));
+        assert!(rules.is_ignored("logs/debug.log", false));
+        assert!(!rules.is_ignored("keep.log", false));
+        assert!(rules.is_ignored("docs/index.html", false));
+        assert!(!rules.is_ignored("src/index.html", false));
+    }
+}
@@ -11,4 +11,3 @@ impl SymlinkOps for MacOS {
         Ok(())
     }
 }
-
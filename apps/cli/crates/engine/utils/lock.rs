@@ -0,0 +1,86 @@
+use anyhow::{Context, Result};
+use std::fs::{File, OpenOptions};
+use std::os::fd::AsRawFd;
+use std::path::{Path, PathBuf};
+
+/// Exclusive advisory lock guarding `target`, held on a `.<name>.lock` file beside
+/// it and released on drop.
+///
+/// The lock lives on its own file because `target` itself may be replaced by a
+/// rename (see [`FsTransaction`](crate::utils::transaction::FsTransaction)), which
+/// would leave a lock on the old inode guarding nothing.
+#[derive(Debug)]
+pub struct FileLock {
+    _file: File,
+}
+
+impl FileLock {
+    /// Wait until the lock on `target` is free, then take it.
+    pub fn acquire(target: &Path) -> Result<Self> {
+        let (file, path) = open(target)?;
+        flock(&file, libc::LOCK_EX)
+            .with_context(|| format!("Failed to lock {}", path.display()))?;
+        Ok(Self { _file: file })
+    }
+
+    /// Take the lock on `target` if nobody holds it.
+    pub fn try_acquire(target: &Path) -> Result<Option<Self>> {
+        let (file, path) = open(target)?;
+        match flock(&file, libc::LOCK_EX | libc::LOCK_NB) {
+            Ok(()) => Ok(Some(Self { _file: file })),
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => Ok(None),
+            Err(e) => Err(e).with_context(|| format!("Failed to lock {}", path.display())),
+        }
+    }
+}
+
+/// `flock(2)` on `file`, retried when interrupted by a signal. The lock goes
+/// away when the file is closed.
+fn flock(file: &File, operation: libc::c_int) -> std::io::Result<()> {
+    loop {
+        // SAFETY: the descriptor is owned by `file` and stays open for the call.
+        if unsafe { libc::flock(file.as_raw_fd(), operation) } == 0 {
+            return Ok(());
+        }
+        let error = std::io::Error::last_os_error();
+        if error.kind() != std::io::ErrorKind::Interrupted {
+            return Err(error);
+        }
+    }
+}
+
+fn open(target: &Path) -> Result<(File, PathBuf)> {
+    let name = target
+        .file_name()
+        .with_context(|| format!("Path has no file name: {}", target.display()))?;
+    let path = target.with_file_name(format!(".{}.lock", name.to_string_lossy()));
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    Ok((file, path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch::Scratch;
+
+    #[test]
+    fn one_holder_at_a_time() {
+        let root = Scratch::new("lock");
+        let target = root.join("shims.toml");
+
+        let held = FileLock::acquire(&target).unwrap();
+        assert!(root.join(".shims.toml.lock").is_file());
+        assert!(FileLock::try_acquire(&target).unwrap().is_none());
+        drop(held);
+        assert!(FileLock::try_acquire(&target).unwrap().is_some());
+    }
+}
@@ -1,6 +1,13 @@
 pub mod archive;
+pub mod dotenv;
 pub mod fs;
+pub mod glob;
 pub mod hashing;
+pub mod ignore;
 pub mod link;
+pub mod lock;
 pub mod net;
 pub mod paths;
+#[cfg(test)]
+pub mod scratch;
+pub mod transaction;
@@ -4,9 +4,92 @@ use reqwest::Client;
 pub struct NetUtils;
 
 impl NetUtils {
-    /// Create a new HTTP client
+    /// Create a new HTTP client with a `still/<version>` user agent,
+    /// which the GitHub API requires.
     pub fn client() -> Client {
-        Client::new()
+        Client::builder()
+            .user_agent(concat!("still/", env!("CARGO_PKG_VERSION")))
+            .build()
+            .unwrap_or_default()
     }
 }
 
+/// A minimal HTTP server for tests that stand in for real registries.
+#[cfg(test)]
+pub(crate) mod fixture {
+    use std::collections::HashMap;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Serve `files` (request path -> body) on localhost for the rest of the test run.
+    /// Returns the base URL, e.g. `http://127.0.0.1:54321`.
+    pub fn serve(files: Vec<(String, Vec<u8>)>) -> String {
+        serve_with(|_| files)
+    }
+
+    /// Like [`serve`], for files that need to link back to the server's own URL.
+    pub fn serve_with(files: impl FnOnce(&str) -> Vec<(String, Vec<u8>)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let files: HashMap<String, Vec<u8>> = files(&url).into_iter().collect();
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Ok(mut stream) = stream else {
+                    continue;
+                };
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                let _ = reader.read_line(&mut request_line);
+                loop {
+                    let mut header = String::new();
+                    if reader.read_line(&mut header).unwrap_or(0) <= 2 {
+                        break;
+                    }
+                }
+
+                let path = request_line.split_whitespace().nth(1).unwrap_or("/");
+                let (status, body) = match files.get(path) {
+                    Some(body) => ("200 OK", body.as_slice()),
+                    None => ("404 Not Found", &b"not found"[..]),
+                };
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    status,
+                    body.len()
+                );
+                if !request_line.starts_with("HEAD ") {
+                    let _ = stream.write_all(body);
+                }
+            }
+        });
+
+        url
+    }
+
+    /// A `.tar.gz` with one top-level directory holding `files` (path -> contents).
+    pub fn tar_gz(top_level: &str, files: &[(&str, &[u8])]) -> Vec<u8> {
+        let encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::fast());
+        let mut builder = tar::Builder::new(encoder);
+        for (path, contents) in files {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(contents.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            builder
+                .append_data(&mut header, format!("{}/{}", top_level, path), *contents)
+                .unwrap();
+        }
+        builder.into_inner().unwrap().finish().unwrap()
+    }
+
+    /// Run a future to completion on a fresh runtime.
+    pub fn block_on<F: std::future::Future>(future: F) -> F::Output {
+        tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap()
+            .block_on(future)
+    }
+}
@@ -12,6 +12,7 @@ pub trait PathOps {
     fn apps_dir() -> PathBuf;
     fn home_dir() -> PathBuf;
     fn tool_dir() -> PathBuf;
+    fn services_dir() -> PathBuf;
 }
 
 impl PathOps for MacOS {
@@ -34,6 +35,11 @@ impl PathOps for MacOS {
         Self::root_dir().join("apps")
     }
 
+    /// Data of service presets, per project.
+    fn services_dir() -> PathBuf {
+        Self::root_dir().join("services")
+    }
+
     fn config_dir() -> PathBuf {
         home_dir().unwrap().join(".config").join("still")
     }
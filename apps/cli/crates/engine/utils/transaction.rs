@@ -0,0 +1,237 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Filesystem transaction for installs and removals.
+///
+/// New content is staged next to its target and only swapped in on [`commit`](Self::commit).
+/// If any swap fails, every swap made so far is undone, so a failed multi-package
+/// install leaves the previous state in place. Staged content that is never
+/// committed is removed on drop.
+#[derive(Debug, Default)]
+pub struct FsTransaction {
+    installs: Vec<(PathBuf, PathBuf)>,
+    removals: Vec<PathBuf>,
+    committed: bool,
+}
+
+enum Undo {
+    Remove(PathBuf),
+    Restore { trash: PathBuf, target: PathBuf },
+}
+
+impl FsTransaction {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserve a staging directory that will replace `target` on commit.
+    pub fn stage_install(&mut self, target: &Path) -> Result<PathBuf> {
+        let staging = self.reserve(target)?;
+        fs::create_dir_all(&staging)
+            .with_context(|| format!("Failed to create staging dir {}", staging.display()))?;
+
+        self.installs.push((staging.clone(), target.to_path_buf()));
+        Ok(staging)
+    }
+
+    /// Replace the file `target` with `contents` on commit.
+    pub fn stage_write(&mut self, target: &Path, contents: impl AsRef<[u8]>) -> Result<()> {
+        let staging = self.reserve(target)?;
+        fs::write(&staging, contents)
+            .with_context(|| format!("Failed to write {}", staging.display()))?;
+
+        self.installs.push((staging, target.to_path_buf()));
+        Ok(())
+    }
+
+    /// Make `target` a symlink to `original` on commit.
+    pub fn stage_symlink(&mut self, target: &Path, original: &Path) -> Result<()> {
+        let staging = self.reserve(target)?;
+        std::os::unix::fs::symlink(original, &staging)
+            .with_context(|| format!("Failed to create {}", staging.display()))?;
+
+        self.installs.push((staging, target.to_path_buf()));
+        Ok(())
+    }
+
+    /// The staging path for `target`. Staging the same target again replaces what
+    /// was staged for it before.
+    fn reserve(&mut self, target: &Path) -> Result<PathBuf> {
+        self.installs.retain(|(_, staged)| staged != target);
+        sibling(target, "staging")
+    }
+
+    /// Schedule `target` for removal on commit.
+    pub fn stage_removal(&mut self, target: &Path) {
+        self.removals.push(target.to_path_buf());
+    }
+
+    pub fn commit(mut self) -> Result<()> {
+        let mut undo = Vec::new();
+
+        if let Err(e) = self.apply(&mut undo) {
+            for step in undo.into_iter().rev() {
+                match step {
+                    Undo::Remove(path) => {
+                        let _ = remove_path(&path);
+                    }
+                    Undo::Restore { trash, target } => {
+                        let _ = fs::rename(&trash, &target);
+                    }
+                }
+            }
+            return Err(e);
+        }
+
+        for step in undo {
+            if let Undo::Restore { trash, .. } = step {
+                let _ = remove_path(&trash);
+            }
+        }
+        self.committed = true;
+        Ok(())
+    }
+
+    fn apply(&self, undo: &mut Vec<Undo>) -> Result<()> {
+        for target in &self.removals {
+            let trash = sibling(target, "trash")?;
+            fs::rename(target, &trash)
+                .with_context(|| format!("Failed to remove {}", target.display()))?;
+            undo.push(Undo::Restore {
+                trash,
+                target: target.clone(),
+            });
+        }
+
+        for (staging, target) in &self.installs {
+            if target.exists() || target.is_symlink() {
+                let trash = sibling(target, "trash")?;
+                fs::rename(target, &trash).with_context(|| {
+                    format!("Failed to move aside existing {}", target.display())
+                })?;
+                undo.push(Undo::Restore {
+                    trash,
+                    target: target.clone(),
+                });
+            }
+
+            fs::rename(staging, target)
+                .with_context(|| format!("Failed to move {} into place", target.display()))?;
+            undo.push(Undo::Remove(target.clone()));
+        }
+
+        Ok(())
+    }
+}
+
+impl Drop for FsTransaction {
+    fn drop(&mut self) {
+        if self.committed {
+            return;
+        }
+        for (staging, _) in &self.installs {
+            let _ = remove_path(staging);
+        }
+    }
+}
+
+/// `<dir>/.<name>.<suffix>`, on the same filesystem as the target so renames stay atomic.
+/// Whatever an interrupted run left at that path is removed first.
+fn sibling(target: &Path, suffix: &str) -> Result<PathBuf> {
+    let name = target
+        .file_name()
+        .with_context(|| format!("Path has no file name: {}", target.display()))?;
+    let sibling = target.with_file_name(format!(".{}.{}", name.to_string_lossy(), suffix));
+    if sibling.exists() || sibling.is_symlink() {
+        remove_path(&sibling)
+            .with_context(|| format!("Failed to remove stale {}", sibling.display()))?;
+    }
+    Ok(sibling)
+}
+
+fn remove_path(path: &Path) -> std::io::Result<()> {
+    if path.is_dir() && !path.is_symlink() {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::scratch::Scratch;
+
+    #[test]
+    fn commit_swaps_in_staged_content() {
+        let root = Scratch::new("tx-commit");
+        let target = root.join("jq").join("1.7.1");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("old"), "old").unwrap();
+        let removed = root.join("gone");
+        fs::create_dir_all(&removed).unwrap();
+
+        let mut tx = FsTransaction::new();
+        let staging = tx.stage_install(&target).unwrap();
+        fs::write(staging.join("new"), "new").unwrap();
+        tx.stage_removal(&removed);
+        tx.commit().unwrap();
+
+        assert!(target.join("new").exists());
+        assert!(!target.join("old").exists());
+        assert!(!removed.exists());
+        assert_eq!(fs::read_dir(target.parent().unwrap()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn failed_commit_restores_previous_state() {
+        let root = Scratch::new("tx-rollback");
+        let kept = root.join("kept");
+        fs::create_dir_all(&kept).unwrap();
+        let target = root.join("a");
+        fs::create_dir_all(&target).unwrap();
+        fs::write(target.join("old"), "old").unwrap();
+
+        let mut tx = FsTransaction::new();
+        tx.stage_removal(&kept);
+        let staging = tx.stage_install(&target).unwrap();
+        // Losing the staged content makes the final swap fail.
+        fs::remove_dir_all(&staging).unwrap();
+        assert!(tx.commit().is_err());
+
+        assert!(kept.exists());
+        assert!(target.join("old").exists());
+    }
+
+    #[test]
+    fn stages_files_and_symlinks_over_leftovers() {
+        let root = Scratch::new("tx-files");
+        let lock = root.join("still.lock");
+        fs::write(&lock, "old").unwrap();
+        let shim = root.join("jq");
+        // An interrupted run left these behind.
+        fs::create_dir_all(root.join(".still.lock.trash/x")).unwrap();
+        fs::write(root.join(".jq.staging"), "").unwrap();
+
+        let mut tx = FsTransaction::new();
+        tx.stage_write(&lock, "first").unwrap();
+        tx.stage_write(&lock, "new").unwrap();
+        tx.stage_symlink(&shim, Path::new("still")).unwrap();
+        tx.commit().unwrap();
+
+        assert_eq!(fs::read_to_string(&lock).unwrap(), "new");
+        assert_eq!(fs::read_link(&shim).unwrap(), Path::new("still"));
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn dropped_transaction_cleans_up_staging() {
+        let root = Scratch::new("tx-drop");
+        let staging = {
+            let mut tx = FsTransaction::new();
+            tx.stage_install(&root.join("tool")).unwrap()
+        };
+        assert!(!staging.exists());
+    }
+}
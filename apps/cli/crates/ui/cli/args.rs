@@ -1,4 +1,7 @@
 use clap::{Parser, Subcommand};
+use engine::actions::activate::Shell;
+use engine::actions::env::ExportFormat;
+use engine::registries::specs::config::ConfigScope;
 use engine::registries::specs::tool::ToolSpec;
 
 #[derive(Parser)]
@@ -18,33 +21,92 @@ pub enum Command {
     Translate(TranslateArgs), // Translate project definitions between supported formats.
     Init(InitArgs),           // Initialize configuration for a new project.
     Convert(ConvertArgs),     // Convert configuration or lockfiles to another supported format.
-    Env,                      // Display environment information required for debugging.
+    Env(EnvArgs),             // Display environment information required for debugging.
     Tui,                      // Launch the text-based user interface.
     Web,                      // Open or run the web-based management dashboard.
-    Activate,                 // Activate a workspace or profile for the current shell session.
+    Activate(ActivateArgs),   // Activate a workspace or profile for the current shell session.
     Sync,                     // Synchronize the workspace state with configured sources.
-    Task,
-    Config,
+    Task(TaskArgs),           // Run tasks from the [tasks] table of still.toml.
+    Service(ServiceArgs),     // Start, stop and inspect the [services] of still.toml.
+    Config(ConfigArgs),       // Read and write layered configuration.
     PostInstall,
+    #[command(hide = true)]
+    HookEnv(HookEnvArgs), // Print the environment changes for the current directory.
 }
 
 // Command argument structs
 #[derive(clap::Args, Debug, Clone)]
 pub struct InstallArgs {
-    #[arg(value_name = "TOOL@VERSION")]
-    pub tool: ToolSpec,
+    #[arg(value_name = "[BACKEND:]TOOL@VERSION", required = true)]
+    pub tools: Vec<ToolSpec>,
+
+    /// Skip the confirmation prompt
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// Build cargo crates from source with their Cargo.lock
+    #[arg(long)]
+    pub locked: bool,
 }
 
 #[derive(clap::Args, Debug, Clone)]
 pub struct UninstallArgs {
-    #[arg(value_name = "TOOL@VERSION")]
-    pub tool: ToolSpec,
+    #[arg(value_name = "[BACKEND:]TOOL@VERSION", required = true)]
+    pub tools: Vec<ToolSpec>,
+
+    /// Skip the confirmation prompt
+    #[arg(short, long)]
+    pub yes: bool,
 }
 
 #[derive(clap::Args, Debug, Clone)]
 pub struct UseArgs {
-    #[arg(short, long, value_name = "TOOL")]
-    pub tool_name: String,
+    #[arg(
+        value_name = "[BACKEND:]TOOL@VERSION",
+        required_unless_present = "unset"
+    )]
+    pub tools: Vec<ToolSpec>,
+
+    /// Pin in the global config instead of the nearest still.toml
+    #[arg(short, long)]
+    pub global: bool,
+
+    /// Remove the pin for TOOL
+    #[arg(long, value_name = "TOOL")]
+    pub unset: Vec<String>,
+
+    /// Install missing tools without the confirmation prompt
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ActivateArgs {
+    /// Shell to print the hook for: bash, zsh, fish or nu
+    #[arg(value_name = "SHELL")]
+    pub shell: Shell,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct HookEnvArgs {
+    #[arg(value_name = "SHELL")]
+    pub shell: Shell,
+
+    /// Undo everything the hook applied
+    #[arg(long)]
+    pub deactivate: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct EnvArgs {
+    /// Print only the variables: bash, zsh, fish, dotenv or json; github appends
+    /// them to $GITHUB_ENV and the PATH additions to $GITHUB_PATH
+    #[arg(long, value_name = "FORMAT")]
+    pub export: Option<ExportFormat>,
+
+    /// Same as --export json
+    #[arg(long, conflicts_with = "export")]
+    pub json: bool,
 }
 
 #[derive(clap::Args, Debug, Clone)]
@@ -52,14 +114,164 @@ pub struct DoctorArgs {}
 
 #[derive(clap::Args, Debug, Clone)]
 pub struct RunArgs {
+    /// Use this version for the run without changing still.toml (repeatable)
+    #[arg(long = "tool", value_name = "[BACKEND:]TOOL@VERSION")]
+    pub tools: Vec<ToolSpec>,
+
+    #[arg(
+        value_name = "COMMAND",
+        required = true,
+        trailing_var_arg = true,
+        allow_hyphen_values = true
+    )]
     pub command: Vec<String>,
 }
 
+#[derive(clap::Args, Debug, Clone)]
+pub struct TaskArgs {
+    /// Tasks to run, after their dependencies
+    #[arg(value_name = "TASK", required_unless_present = "list")]
+    pub tasks: Vec<String>,
+
+    /// Print the tasks and their descriptions
+    #[arg(short, long)]
+    pub list: bool,
+
+    /// Keep running tasks that do not depend on a failed one
+    #[arg(long = "continue")]
+    pub keep_going: bool,
+
+    /// Tasks to run at the same time [default: settings.jobs]
+    #[arg(short, long)]
+    pub jobs: Option<usize>,
+
+    /// Run tasks even when their sources are unchanged
+    #[arg(short, long)]
+    pub force: bool,
+
+    /// Say why each task ran or was skipped
+    #[arg(long)]
+    pub explain: bool,
+
+    /// Rerun the tasks, restarting long-running ones, when their sources change
+    #[arg(short, long, conflicts_with = "list")]
+    pub watch: bool,
+}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ServiceArgs {
+    #[command(subcommand)]
+    pub command: ServiceCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ServiceCommand {
+    /// Start services and wait until they are healthy (all when none are given)
+    Start {
+        #[arg(value_name = "SERVICE")]
+        names: Vec<String>,
+    },
+    /// Stop services (all when none are given)
+    Stop {
+        #[arg(value_name = "SERVICE")]
+        names: Vec<String>,
+    },
+    /// Print the state and health of every service
+    Status,
+    /// Print the log of a service
+    Logs {
+        #[arg(value_name = "SERVICE")]
+        name: String,
+
+        /// Keep printing what is appended
+        #[arg(short, long)]
+        follow: bool,
+    },
+    /// Keep a service running; started in the background by `start`
+    #[command(hide = true)]
+    Supervise {
+        #[arg(value_name = "SERVICE")]
+        name: String,
+    },
+}
+
 #[derive(clap::Args, Debug, Clone)]
 pub struct TranslateArgs {}
 
 #[derive(clap::Args, Debug, Clone)]
-pub struct InitArgs {}
+pub struct InitArgs {
+    /// Accept every detected tool and package without prompting
+    #[arg(short, long)]
+    pub yes: bool,
+
+    /// Overwrite an existing still.toml
+    #[arg(short, long)]
+    pub force: bool,
+}
 
 #[derive(clap::Args, Debug, Clone)]
 pub struct ConvertArgs {}
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ConfigArgs {
+    #[command(subcommand)]
+    pub command: ConfigCommand,
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigCommand {
+    /// Print the effective value of a key
+    Get {
+        #[arg(value_name = "KEY")]
+        key: String,
+
+        /// Also print which layer the value came from
+        #[arg(long)]
+        show_origin: bool,
+    },
+    /// Write a key to the global config, still.toml or still.local.toml
+    Set {
+        #[arg(value_name = "KEY")]
+        key: String,
+
+        #[arg(value_name = "VALUE")]
+        value: String,
+
+        #[command(flatten)]
+        scope: ScopeArgs,
+    },
+    /// Print every effective key
+    List {
+        /// Also print which layer each value came from
+        #[arg(long)]
+        show_origin: bool,
+    },
+}
+
+#[derive(clap::Args, Debug, Clone)]
+#[group(multiple = false)]
+pub struct ScopeArgs {
+    /// Write to the global config (default)
+    #[arg(long)]
+    pub global: bool,
+
+    /// Write to the nearest still.toml
+    #[arg(long)]
+    pub project: bool,
+
+    /// Write to still.local.toml next to the nearest still.toml
+    #[arg(long)]
+    pub local: bool,
+}
+
+impl ScopeArgs {
+    pub fn scope(&self) -> ConfigScope {
+        if self.project {
+            ConfigScope::Project
+        } else if self.local {
+            ConfigScope::Local
+        } else {
+            ConfigScope::Global
+        }
+    }
+}
@@ -1,50 +1,739 @@
-use crate::cli::args::InstallArgs;
-use crate::cli::args::{Cli, Command};
+use crate::cli::args::{ActivateArgs, Cli, Command, EnvArgs, HookEnvArgs};
+use crate::cli::args::{ConfigArgs, ConfigCommand, InitArgs, InstallArgs, RunArgs};
+use crate::cli::args::{ServiceArgs, ServiceCommand, TaskArgs, UninstallArgs, UseArgs};
+use crate::cli::output::Output;
 use crate::tui;
 use clap::Parser;
-use engine::actions::install::{InstallRequest, run};
+use engine::actions::activate::{self, Activation};
+use engine::actions::env::{self, ExportFormat};
+use engine::actions::init;
+use engine::actions::install::{self, InstallPlan, InstallRequest};
+use engine::actions::run::{self, RunRequest};
+use engine::actions::service::{self, ServiceReport, Services, Supervision};
+use engine::actions::shim;
+use engine::actions::task::{self, TaskEnv, TaskGraph, TaskRequest, TaskStatus};
+use engine::actions::uninstall::{self, UninstallPlan, UninstallRequest};
+use engine::actions::r#use::{self, UseRequest};
+use engine::actions::watch;
+use engine::registries::specs::config::{self, ConfigScope, ConfigValue, LayeredConfig, Origin};
+use std::collections::BTreeMap;
 
 pub fn install(args: InstallArgs) {
     let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
-    let install_request = InstallRequest { tool: args.tool };
-    let result = rt.block_on(run(install_request));
-    match result {
-        Ok(res) => {
-            if let Some(binary_path) = &res.binary_path {
-                println!("Binary installed at: {}", binary_path.display());
-            } else {
+    let install_request = InstallRequest {
+        tools: args.tools,
+        locked: args.locked,
+    };
+
+    let plan = match rt.block_on(install::plan(&install_request)) {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("install failed: {e:#}");
+            std::process::exit(1);
+        }
+    };
+
+    if !confirm_install_plan(&plan, args.yes) {
+        return;
+    }
+
+    match rt.block_on(install::execute(plan)) {
+        Ok(results) => {
+            for res in results {
+                if let Some(binary_path) = &res.binary_path {
+                    println!("Binary installed at: {}", binary_path.display());
+                } else {
+                    println!(
+                        "Warning: Could not find binary in {}",
+                        res.install_path.display()
+                    );
+                }
                 println!(
-                    "Warning: Could not find binary in {}",
+                    "Successfully installed {}@{} to {}",
+                    res.tool_name,
+                    res.version,
                     res.install_path.display()
                 );
             }
-            println!(
-                "Successfully installed {}@{} to {}",
-                res.tool_name,
-                res.version,
-                res.install_path.display()
-            );
         }
         Err(e) => {
-            eprintln!("install failed: {e}");
+            eprintln!("install failed: {e:#}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Show `plan` and ask before running it unless `yes`. False when there is nothing
+/// to install or the user declined.
+fn confirm_install_plan(plan: &InstallPlan, yes: bool) -> bool {
+    print_install_plan(plan);
+    if plan.steps.is_empty() {
+        println!("Nothing to install");
+        return false;
+    }
+    if !yes && !Output::confirm("Proceed with installation?") {
+        println!("Aborted");
+        return false;
+    }
+    true
+}
+
+fn print_install_plan(plan: &InstallPlan) {
+    println!("Install plan:");
+    for step in &plan.steps {
+        let origin = if step.requested.is_some() {
+            ""
+        } else {
+            " (dependency)"
+        };
+        println!("  {} {}{}", step.name, step.version, origin);
+    }
+
+    println!(
+        "{} packages ({} requested, {} dependencies), {} already installed",
+        plan.steps.len(),
+        plan.requested_count(),
+        plan.dependency_count(),
+        plan.satisfied.len()
+    );
+    println!(
+        "Download size: {}",
+        plan.download_size()
+            .map_or("unknown".to_string(), Output::bytes)
+    );
+    println!(
+        "Disk usage: {}",
+        plan.installed_size()
+            .map_or("unknown".to_string(), Output::bytes)
+    );
+}
+
+pub fn uninstall(args: UninstallArgs) {
+    let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+    let uninstall_request = UninstallRequest { tools: args.tools };
+
+    let plan = match rt.block_on(uninstall::plan(&uninstall_request)) {
+        Ok(plan) => plan,
+        Err(e) => {
+            eprintln!("uninstall failed: {e:#}");
+            std::process::exit(1);
+        }
+    };
+
+    print_uninstall_plan(&plan);
+    if plan.steps.is_empty() {
+        println!("Nothing to uninstall");
+        return;
+    }
+    if !args.yes && !Output::confirm("Proceed with removal?") {
+        println!("Aborted");
+        return;
+    }
+
+    match rt.block_on(uninstall::execute(plan)) {
+        Ok(removed) => {
+            for step in removed {
+                println!("Successfully uninstalled {}@{}", step.name, step.version);
+            }
+        }
+        Err(e) => {
+            eprintln!("uninstall failed: {e:#}");
             std::process::exit(1);
         }
     }
 }
 
+fn print_uninstall_plan(plan: &UninstallPlan) {
+    for tool in &plan.missing {
+        Output::warning(&format!("{} is not installed", tool));
+    }
+
+    println!("Uninstall plan:");
+    for step in &plan.steps {
+        println!("  {} {}", step.name, step.version);
+    }
+    println!(
+        "{} packages, {} freed",
+        plan.steps.len(),
+        Output::bytes(plan.disk_usage())
+    );
+}
+
+pub fn use_tools(args: UseArgs) {
+    let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+    let request = UseRequest {
+        tools: args.tools,
+        unset: args.unset,
+        global: args.global,
+    };
+
+    let missing = r#use::missing(&request);
+    if !missing.is_empty() {
+        let install_request = InstallRequest {
+            tools: missing,
+            locked: false,
+        };
+        let plan = match rt.block_on(install::plan(&install_request)) {
+            Ok(plan) => plan,
+            Err(e) => {
+                eprintln!("use failed: {e:#}");
+                std::process::exit(1);
+            }
+        };
+        if !plan.steps.is_empty() {
+            if !confirm_install_plan(&plan, args.yes) {
+                return;
+            }
+            if let Err(e) = rt.block_on(install::execute(plan)) {
+                eprintln!("use failed: {e:#}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    let result = LayeredConfig::load().and_then(|config| r#use::apply(&request, &config));
+    let result = match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("use failed: {e:#}");
+            std::process::exit(1);
+        }
+    };
+    for name in &result.unset {
+        Output::success(&format!("Unpinned {} in {}", name, result.path.display()));
+    }
+    for name in request.unset.iter().filter(|n| !result.unset.contains(n)) {
+        Output::warning(&format!(
+            "{} is not pinned in {}",
+            name,
+            result.path.display()
+        ));
+    }
+    for tool in &result.pinned {
+        Output::success(&format!("Pinned {} in {}", tool, result.path.display()));
+    }
+
+    // A higher layer (still.local.toml, STILL_<TOOL>_VERSION) still wins over the new pin.
+    let Ok(config) = LayeredConfig::load() else {
+        return;
+    };
+    let versions = config.tool_versions();
+    for tool in &result.pinned {
+        let Some(effective) = versions.get(&tool.key()) else {
+            continue;
+        };
+        if effective.to_string() != tool.version {
+            Output::warning(&format!(
+                "{} is still {} here, set by {}",
+                tool.key(),
+                effective,
+                effective.origin
+            ));
+        }
+    }
+}
+
+/// Exec the command in the project environment; on success this never returns.
+pub fn run(args: RunArgs) {
+    let request = RunRequest {
+        command: args.command,
+        tools: args.tools,
+    };
+    let mut config = match LayeredConfig::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("run failed: {e:#}");
+            std::process::exit(1);
+        }
+    };
+    run::apply_overrides(&request, &mut config);
+    let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+    if let Err(e) = rt.block_on(run::auto_install(&config)) {
+        eprintln!("run failed: {e:#}");
+        std::process::exit(1);
+    }
+
+    let command = match run::prepare(&request, &config) {
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("run failed: {e:#}");
+            std::process::exit(1);
+        }
+    };
+    let Err(e) = run::exec(&command);
+    eprintln!("run failed: {e:#}");
+    std::process::exit(127);
+}
+
+pub fn task(args: TaskArgs) {
+    let result = LayeredConfig::load().and_then(|config| {
+        let root = config.scope_path(ConfigScope::Project)?;
+        let root = root.parent().unwrap_or(&root).to_path_buf();
+        Ok((TaskGraph::load(&root)?, config))
+    });
+    let (graph, config) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("task failed: {e:#}");
+            std::process::exit(1);
+        }
+    };
+
+    if args.list {
+        let tasks = task::list(&graph);
+        let width = tasks.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+        for (name, description) in tasks {
+            match description {
+                Some(description) => println!("{:<width$}  {}", name, description),
+                None => println!("{}", name),
+            }
+        }
+        return;
+    }
+
+    let request = TaskRequest {
+        tasks: args.tasks,
+        keep_going: args.keep_going,
+        jobs: args.jobs.unwrap_or_else(|| {
+            config
+                .get("settings.jobs")
+                .and_then(|v| v.value.as_integer())
+                .map_or(1, |jobs| jobs.max(1) as usize)
+        }),
+        force: args.force,
+    };
+    if args.watch {
+        watch_tasks(&graph, &request, args.explain);
+        return;
+    }
+
+    let prepared = graph.plan(&request.tasks).and_then(|order| {
+        let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+        rt.block_on(run::auto_install(&config))?;
+        let mut vars = run::environment(&config)?;
+        let required = service::required(&graph, &order);
+        if !required.is_empty() {
+            let services = Services::load(&graph.root)?;
+            rt.block_on(service::install_presets(&services, &required))?;
+            for name in &required {
+                if service::start(&services, name, &vars)? {
+                    Output::success(&format!("Started {}", name));
+                }
+            }
+            // Presets record their ports when they first start.
+            vars = run::environment(&config)?;
+        }
+        let env = TaskEnv {
+            vars,
+            tools: env::tool_env(&config)
+                .tools
+                .into_iter()
+                .map(|tool| (tool.name, tool.version))
+                .collect(),
+        };
+        Ok((order, env))
+    });
+    let (order, env) = match prepared {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            eprintln!("task failed: {e:#}");
+            std::process::exit(1);
+        }
+    };
+
+    let outcomes = task::execute(&graph, &order, &request, &env);
+    let mut failed = false;
+    for outcome in &outcomes {
+        let reason = if args.explain {
+            format!(": {}", outcome.reason)
+        } else {
+            String::new()
+        };
+        match outcome.status {
+            TaskStatus::Succeeded if order.len() > 1 || args.explain => Output::success(&format!(
+                "{} ({:.1}s){}",
+                outcome.name,
+                outcome.duration.as_secs_f64(),
+                reason
+            )),
+            TaskStatus::Succeeded => {}
+            TaskStatus::UpToDate => Output::info(&format!("{} is up to date", outcome.name)),
+            TaskStatus::Failed(code) => {
+                failed = true;
+                let code = code.map_or("a signal".to_string(), |c| format!("code {}", c));
+                Output::error(&format!("{} failed with {}{}", outcome.name, code, reason));
+            }
+            TaskStatus::Skipped => Output::warning(&format!("{} skipped{}", outcome.name, reason)),
+        }
+    }
+    if failed {
+        std::process::exit(1);
+    }
+}
+
+/// `still task --watch`: rerun the tasks in a child `still task`, restarted when
+/// the `sources` of any of them change.
+fn watch_tasks(graph: &TaskGraph, request: &TaskRequest, explain: bool) {
+    let mut watcher = match task::watcher(graph, &request.tasks) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            eprintln!("task failed: {e:#}");
+            std::process::exit(1);
+        }
+    };
+
+    let exe = std::env::current_exe().expect("Failed to locate the still executable");
+    let command = || {
+        let mut command = std::process::Command::new(&exe);
+        command
+            .arg("task")
+            .args(&request.tasks)
+            .arg("--jobs")
+            .arg(request.jobs.to_string());
+        for (flag, set) in [
+            ("--continue", request.keep_going),
+            ("--force", request.force),
+            ("--explain", explain),
+        ] {
+            if set {
+                command.arg(flag);
+            }
+        }
+        command
+    };
+    if let Err(e) = watch::watch(&mut watcher, command) {
+        eprintln!("task failed: {e:#}");
+        std::process::exit(1);
+    }
+}
+
+pub fn service(args: ServiceArgs) {
+    let result = LayeredConfig::load().and_then(|config| {
+        let root = config.scope_path(ConfigScope::Project)?;
+        let root = root.parent().unwrap_or(&root).to_path_buf();
+        Ok((Services::load(&root)?, config))
+    });
+    let (services, config) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("service failed: {e:#}");
+            std::process::exit(1);
+        }
+    };
+
+    if let ServiceCommand::Logs { name, follow } = &args.command {
+        if let Err(e) = service::logs(&services, name, &mut std::io::stdout(), *follow) {
+            eprintln!("service failed: {e:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let all = |names: Vec<String>| {
+        if names.is_empty() {
+            services.services.keys().cloned().collect()
+        } else {
+            names
+        }
+    };
+    if let ServiceCommand::Start { names } = &args.command {
+        let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+        let installed = rt.block_on(async {
+            run::auto_install(&config).await?;
+            service::install_presets(&services, &all(names.clone())).await
+        });
+        if let Err(e) = installed {
+            eprintln!("service failed: {e:#}");
+            std::process::exit(1);
+        }
+    }
+    let env = match run::environment(&config) {
+        Ok(env) => env,
+        Err(e) => {
+            eprintln!("service failed: {e:#}");
+            std::process::exit(1);
+        }
+    };
+
+    match args.command {
+        ServiceCommand::Start { names } => {
+            for name in all(names) {
+                match service::start(&services, &name, &env) {
+                    Ok(true) => Output::success(&format!("Started {}", name)),
+                    Ok(false) => Output::info(&format!("{} is already running", name)),
+                    Err(e) => {
+                        eprintln!("service failed: {e:#}");
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+        ServiceCommand::Stop { names } => {
+            for name in all(names) {
+                match service::stop(&services, &name, &env) {
+                    Ok(true) => Output::success(&format!("Stopped {}", name)),
+                    Ok(false) => Output::info(&format!("{} is not running", name)),
+                    Err(e) => {
+                        eprintln!("service failed: {e:#}");
+                        std::process::exit(1);
+                    }
+                }
+            }
+        }
+        ServiceCommand::Status => print_service_status(&service::status(&services, &env)),
+        ServiceCommand::Supervise { name } => {
+            let interrupted = watch::interrupt_flag();
+            let supervision = Supervision::default();
+            if let Err(e) = service::supervise(&services, &name, &env, &supervision, interrupted) {
+                eprintln!("service failed: {e:#}");
+                std::process::exit(1);
+            }
+        }
+        ServiceCommand::Logs { .. } => {}
+    }
+}
+
+fn print_service_status(reports: &[ServiceReport]) {
+    let width = reports.iter().map(|r| r.name.len()).max().unwrap_or(0);
+    for report in reports {
+        let mut details = vec![match (&report.state, report.healthy) {
+            (Some(state), _) => match state.pid {
+                Some(pid) => format!("{} (pid {})", state.status, pid),
+                None => state.status.to_string(),
+            },
+            (None, Some(true)) => "running (not started by still)".to_string(),
+            (None, _) => "stopped".to_string(),
+        }];
+        if let Some(state) = report.state.as_ref().filter(|s| s.restarts > 0) {
+            details.push(format!("{} restarts", state.restarts));
+        }
+        match report.healthy {
+            Some(true) => details.push("healthy".to_string()),
+            Some(false) => details.push("unhealthy".to_string()),
+            None => {}
+        }
+        println!("{:<width$}  {}", report.name, details.join(", "));
+    }
+}
+
+pub fn activate(args: ActivateArgs) {
+    let exe = std::env::current_exe().expect("Failed to locate the still executable");
+    print!("{}", activate::hook_script(args.shell, &exe));
+}
+
+/// Runs on every prompt of an activated shell, so errors go to stderr and the
+/// shell is left as it is.
+pub fn hook_env(args: HookEnvArgs) {
+    let diff = Activation::from_system().and_then(|activation| {
+        if args.deactivate {
+            Ok(activation.deactivate())
+        } else {
+            activation.hook_env()
+        }
+    });
+    match diff {
+        Ok(diff) => print!("{}", diff.render(args.shell)),
+        Err(e) => eprintln!("still: {e:#}"),
+    }
+}
+
+/// Without a format, a report of where the project environment comes from;
+/// with one, just the variables for a shell, `.env` file or CI to load.
+pub fn env(args: EnvArgs) {
+    let result = LayeredConfig::load()
+        .and_then(|config| env::project_env(&config).map(|project| (config, project)));
+    let (config, project) = match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("env failed: {e:#}");
+            std::process::exit(1);
+        }
+    };
+    let base: BTreeMap<String, String> = std::env::vars().collect();
+    let exports = project.exports(&base);
+
+    let format = if args.json {
+        Some(ExportFormat::Json)
+    } else {
+        args.export
+    };
+    if format == Some(ExportFormat::GithubEnv) {
+        let (Some(env_file), Some(path_file)) = (
+            std::env::var_os("GITHUB_ENV"),
+            std::env::var_os("GITHUB_PATH"),
+        ) else {
+            eprintln!("env failed: --export github needs $GITHUB_ENV and $GITHUB_PATH");
+            std::process::exit(1);
+        };
+        if let Err(e) = env::export_github(
+            &project,
+            std::path::Path::new(&env_file),
+            std::path::Path::new(&path_file),
+        ) {
+            eprintln!("env failed: {e:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
+    if let Some(format) = format {
+        match format.render(&exports) {
+            Ok(out) => print!("{}", out),
+            Err(e) => {
+                eprintln!("env failed: {e:#}");
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let versions = config.tool_versions();
+    println!("Tools:");
+    if project.tools.is_empty() && project.missing.is_empty() {
+        println!("  none pinned");
+    }
+    for tool in &project.tools {
+        let origin = versions
+            .get(&tool.name)
+            .map(|value| value.origin.to_string())
+            .unwrap_or_default();
+        println!(
+            "  {} {} (requested {}, from {})",
+            tool.name, tool.version, tool.requested, origin
+        );
+        println!("    {}", tool.install_path.display());
+    }
+    for tool in &project.missing {
+        Output::warning(&format!("{} is not installed", tool));
+    }
+
+    println!("Directories:");
+    for (name, path) in env::directories() {
+        println!("  {:<8}{}", name, path.display());
+    }
+
+    println!("Config layers (lowest precedence first):");
+    println!("  default");
+    for layer in &config.layers {
+        println!("  {}", layer);
+    }
+    let overrides: Vec<_> = config
+        .iter()
+        .filter(|(_, value)| matches!(value.origin, Origin::Env(_)))
+        .collect();
+    for (key, value) in overrides {
+        println!("  {} = {} ({})", key, value, value.origin);
+    }
+
+    println!("Environment:");
+    for (name, value) in &exports {
+        println!("  {}={}", name, value);
+    }
+}
+
+pub fn init(args: InitArgs) {
+    let dir = std::env::current_dir().expect("Failed to read current directory");
+    if let Err(e) = init::ensure_can_write(&dir, args.force) {
+        eprintln!("init failed: {e:#}");
+        std::process::exit(1);
+    }
+
+    let detections = match init::detect(&dir) {
+        Ok(detections) => detections,
+        Err(e) => {
+            eprintln!("init failed: {e:#}");
+            std::process::exit(1);
+        }
+    };
+
+    if detections.is_empty() {
+        println!("No project markers found, creating an empty manifest");
+    }
+
+    let accepted: Vec<_> = if args.yes {
+        for detection in &detections {
+            println!("Detected {}", detection);
+        }
+        detections
+    } else {
+        detections
+            .into_iter()
+            .filter(|d| Output::confirm_with_default(&format!("Add {}?", d), true))
+            .collect()
+    };
+
+    let manifest = init::build_manifest(&accepted);
+    match init::write_manifest(&dir, &manifest, args.force) {
+        Ok(path) => Output::success(&format!("Created {}", path.display())),
+        Err(e) => {
+            eprintln!("init failed: {e:#}");
+            std::process::exit(1);
+        }
+    }
+}
+
+pub fn config(args: ConfigArgs) {
+    let layered = match LayeredConfig::load() {
+        Ok(layered) => layered,
+        Err(e) => {
+            eprintln!("config failed: {e:#}");
+            std::process::exit(1);
+        }
+    };
+
+    match args.command {
+        ConfigCommand::Get { key, show_origin } => match layered.get(&key) {
+            Some(value) => print_config_value(None, value, show_origin),
+            None => {
+                eprintln!("config failed: {} is not set", key);
+                std::process::exit(1);
+            }
+        },
+        ConfigCommand::List { show_origin } => {
+            for (key, value) in layered.iter() {
+                print_config_value(Some(key), value, show_origin);
+            }
+        }
+        ConfigCommand::Set { key, value, scope } => {
+            let result = layered.scope_path(scope.scope()).and_then(|path| {
+                config::set_in_file(&path, &key, config::parse_value(&key, &value))?;
+                Ok(path)
+            });
+            match result {
+                Ok(path) => Output::success(&format!("Set {} in {}", key, path.display())),
+                Err(e) => {
+                    eprintln!("config failed: {e:#}");
+                    std::process::exit(1);
+                }
+            }
+        }
+    }
+}
+
+fn print_config_value(key: Option<&str>, value: &ConfigValue, show_origin: bool) {
+    let line = match key {
+        Some(key) => format!("{} = {}", key, value),
+        None => value.to_string(),
+    };
+    if show_origin {
+        println!("{}\t{}", value.origin, line);
+    } else {
+        println!("{}", line);
+    }
+}
+
 pub fn run_cli(cmd: Command) {
     match cmd {
         Command::Install(args) => {
             install(args);
         }
         Command::Uninstall(args) => {
-            println!("Uninstall command: {:?}", args);
+            uninstall(args);
         }
         Command::Use(args) => {
-            println!("Use command: {:?}", args);
+            use_tools(args);
         }
         Command::Run(args) => {
-            println!("Run command: {:?}", args);
+            run(args);
         }
         Command::Translate(args) => {
             println!("Translate command: {:?}", args);
@@ -53,16 +742,39 @@ pub fn run_cli(cmd: Command) {
             println!("Doctor command: {:?}", args);
         }
         Command::Init(args) => {
-            println!("Init command: {:?}", args);
+            init(args);
         }
         Command::Convert(args) => {
             println!("Convert command: {:?}", args);
         }
+        Command::Config(args) => {
+            config(args);
+        }
+        Command::Task(args) => {
+            task(args);
+        }
+        Command::Service(args) => {
+            service(args);
+        }
+        Command::Env(args) => {
+            env(args);
+        }
+        Command::Activate(args) => {
+            activate(args);
+        }
+        Command::HookEnv(args) => {
+            hook_env(args);
+        }
         _ => {}
     }
 }
 
 pub fn entry() {
+    if let Some(Err(e)) = shim::dispatch_from_args() {
+        eprintln!("still: {e:#}");
+        std::process::exit(127);
+    }
+
     let cli = Cli::parse();
     match cli.command {
         None => {
@@ -21,5 +21,49 @@ impl Output {
     pub fn warning(msg: &str) {
         eprintln!("⚠ {}", msg);
     }
-}
 
+    /// Ask a yes/no question on stdin. Anything but `y`/`yes` counts as no.
+    pub fn confirm(prompt: &str) -> bool {
+        Self::confirm_with_default(prompt, false)
+    }
+
+    /// Ask a yes/no question on stdin; an empty answer typed at a terminal picks
+    /// `default`. End of input, or an empty answer piped in, counts as no.
+    pub fn confirm_with_default(prompt: &str, default: bool) -> bool {
+        use std::io::{IsTerminal, Write};
+
+        let hint = if default { "[Y/n]" } else { "[y/N]" };
+        print!("{} {} ", prompt, hint);
+        let _ = std::io::stdout().flush();
+
+        let mut answer = String::new();
+        match std::io::stdin().read_line(&mut answer) {
+            Ok(0) | Err(_) => {
+                println!();
+                return false;
+            }
+            Ok(_) => {}
+        }
+        match answer.trim().to_ascii_lowercase().as_str() {
+            "" => default && std::io::stdin().is_terminal(),
+            "y" | "yes" => true,
+            _ => false,
+        }
+    }
+
+    /// Human-readable byte count (`12.3 MB`).
+    pub fn bytes(bytes: u64) -> String {
+        const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+        let mut value = bytes as f64;
+        let mut unit = 0;
+        while value >= 1000.0 && unit < UNITS.len() - 1 {
+            value /= 1000.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            format!("{} {}", bytes, UNITS[0])
+        } else {
+            format!("{:.1} {}", value, UNITS[unit])
+        }
+    }
+}
@@ -1,4 +1,5 @@
 use crate::tui::components::action_menu::{Action, ActionMenu, ActionMenuState};
+use crate::tui::tabs::config::ConfigTab;
 use crate::tui::tabs::formula::{FormulaTab, NavigationDirection};
 use crate::tui::tabs::resources::ResourcesTab;
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
@@ -78,6 +79,7 @@ pub struct App {
     action_menu: ActionMenuState,
     // Tab-specific state
     formula_tab: FormulaTab,
+    config_tab: ConfigTab,
     resources_tab: ResourcesTab,
 }
 
@@ -95,6 +97,7 @@ impl App {
             search_focused: false,
             action_menu: ActionMenuState::Closed,
             formula_tab,
+            config_tab: ConfigTab::new(),
             resources_tab: ResourcesTab::new(),
         }
     }
@@ -217,11 +220,17 @@ impl App {
                 self.formula_tab
                     .render_preview(horizontal[1], buf, &self.search_query);
             }
+            Tab::Config => {
+                self.config_tab
+                    .render_table(horizontal[0], buf, &self.search_query);
+                self.config_tab
+                    .render_preview(horizontal[1], buf, &self.search_query);
+            }
             Tab::Resources => {
                 // Resources tab uses full area for btop
                 self.render_resources(area, buf);
             }
-            Tab::Tasks | Tab::Config | Tab::Logs => {
+            Tab::Tasks | Tab::Logs => {
                 // Placeholder for other tabs - render empty state
                 self.render_empty_state(horizontal[0], buf);
                 self.render_empty_state(horizontal[1], buf);
@@ -432,6 +441,8 @@ impl App {
             KeyCode::Char('r') if !self.search_focused => {
                 if self.current_tab == Tab::Packages {
                     self.formula_tab.reset_filters();
+                } else if self.current_tab == Tab::Config {
+                    self.config_tab.reload();
                 }
             }
 
@@ -470,6 +481,10 @@ impl App {
                 self.formula_tab
                     .handle_navigation(direction, &self.search_query);
             }
+            Tab::Config => {
+                self.config_tab
+                    .handle_navigation(direction, &self.search_query);
+            }
             _ => {
                 // Other tabs don't support navigation yet
             }
@@ -486,6 +501,9 @@ impl App {
             Tab::Packages => {
                 self.formula_tab.reset_selection();
             }
+            Tab::Config => {
+                self.config_tab.reset_selection();
+            }
             _ => {
                 // Other tabs don't have selection yet
             }
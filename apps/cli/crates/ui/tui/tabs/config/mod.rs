@@ -1,14 +1,187 @@
-pub struct ConfigTab;
+use crate::tui::tabs::formula::NavigationDirection;
+use engine::registries::specs::config::{LayeredConfig, Origin};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, Borders, Cell, Paragraph, Row, Table, Widget},
+};
 
-impl Default for ConfigTab {
-    fn default() -> Self {
-        Self
-    }
+/// One effective key of the layered configuration.
+#[derive(Debug, Clone)]
+pub struct ConfigRow {
+    pub key: String,
+    pub value: String,
+    pub origin: Origin,
+}
+
+/// State for the Config tab: the merged configuration and the layers it came from.
+#[derive(Debug, Default)]
+pub struct ConfigTab {
+    rows: Vec<ConfigRow>,
+    layers: Vec<Origin>,
+    error: Option<String>,
+    pub selected_index: usize,
 }
 
 impl ConfigTab {
-    pub fn render(&self, _area: ratatui::layout::Rect, _buf: &mut ratatui::buffer::Buffer) {
-        // TODO: Implement config tab
+    pub fn new() -> Self {
+        let mut tab = Self::default();
+        tab.reload();
+        tab
+    }
+
+    /// Re-read every layer from disk and the environment.
+    pub fn reload(&mut self) {
+        match LayeredConfig::load() {
+            Ok(config) => {
+                self.rows = config
+                    .iter()
+                    .map(|(key, value)| ConfigRow {
+                        key: key.clone(),
+                        value: value.to_string(),
+                        origin: value.origin.clone(),
+                    })
+                    .collect();
+                self.layers = config.layers.clone();
+                self.error = None;
+            }
+            Err(e) => {
+                self.rows.clear();
+                self.layers.clear();
+                self.error = Some(format!("{e:#}"));
+            }
+        }
+        self.selected_index = 0;
+    }
+
+    pub fn filter(&self, search_query: &str) -> Vec<&ConfigRow> {
+        let query = search_query.to_lowercase();
+        self.rows
+            .iter()
+            .filter(|row| query.is_empty() || row.key.to_lowercase().contains(&query))
+            .collect()
+    }
+
+    pub fn handle_navigation(&mut self, direction: NavigationDirection, search_query: &str) {
+        let count = self.filter(search_query).len();
+        if count == 0 {
+            return;
+        }
+        self.selected_index = match direction {
+            NavigationDirection::Up => self.selected_index.saturating_sub(1),
+            NavigationDirection::Down => (self.selected_index + 1).min(count - 1),
+        };
+    }
+
+    pub fn reset_selection(&mut self) {
+        self.selected_index = 0;
+    }
+
+    pub fn render_table(&self, area: Rect, buf: &mut Buffer, search_query: &str) {
+        let rows = self.filter(search_query);
+        let selected = self.selected_index.min(rows.len().saturating_sub(1));
+        let viewport = area.height.saturating_sub(3) as usize;
+        let offset = selected.saturating_sub(viewport.saturating_sub(1));
+
+        let table_rows: Vec<Row> = rows
+            .iter()
+            .enumerate()
+            .skip(offset)
+            .take(viewport)
+            .map(|(idx, row)| {
+                let style = if idx == selected {
+                    Style::default()
+                        .fg(Color::Black)
+                        .bg(Color::Cyan)
+                        .add_modifier(Modifier::BOLD)
+                } else {
+                    Style::default()
+                };
+                Row::new(vec![
+                    Cell::from(row.key.as_str()),
+                    Cell::from(row.value.as_str()),
+                    Cell::from(origin_label(&row.origin)),
+                ])
+                .style(style)
+            })
+            .collect();
+
+        let header = Row::new(vec!["Key", "Value", "Origin"]).style(
+            Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+        );
+
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Blue))
+            .title(format!(" Config ({}) ", rows.len()));
+
+        Table::new(
+            table_rows,
+            [
+                Constraint::Percentage(40),
+                Constraint::Percentage(35),
+                Constraint::Percentage(25),
+            ],
+        )
+        .header(header)
+        .block(block)
+        .render(area, buf);
+    }
+
+    pub fn render_preview(&self, area: Rect, buf: &mut Buffer, search_query: &str) {
+        let block = Block::default()
+            .borders(Borders::ALL)
+            .border_style(Style::default().fg(Color::Blue))
+            .title(" Details ");
+
+        let mut lines = Vec::new();
+        if let Some(error) = &self.error {
+            lines.push(Line::from(Span::styled(
+                error.clone(),
+                Style::default().fg(Color::Red),
+            )));
+        }
+
+        let rows = self.filter(search_query);
+        if let Some(row) = rows.get(self.selected_index.min(rows.len().saturating_sub(1))) {
+            lines.push(Line::from(Span::styled(
+                row.key.clone(),
+                Style::default()
+                    .fg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            )));
+            lines.push(Line::from(format!("Value:  {}", row.value)));
+            lines.push(Line::from(format!("Origin: {}", row.origin)));
+            lines.push(Line::from(""));
+        }
+
+        lines.push(Line::from(Span::styled(
+            "Layers (lowest precedence first)",
+            Style::default().fg(Color::Yellow),
+        )));
+        lines.push(Line::from("  defaults"));
+        for layer in &self.layers {
+            lines.push(Line::from(format!("  {}", layer)));
+        }
+        lines.push(Line::from("  STILL_* environment variables"));
+
+        Paragraph::new(Text::from(lines))
+            .block(block)
+            .render(area, buf);
     }
 }
 
+fn origin_label(origin: &Origin) -> &'static str {
+    match origin {
+        Origin::Default => "default",
+        Origin::Global(_) => "global",
+        Origin::Project(_) => "project",
+        Origin::Local(_) => "local",
+        Origin::Env(_) => "env",
+        Origin::Override(_) => "override",
+    }
+}